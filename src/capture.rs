@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Sender},
+    thread::{self, JoinHandle},
+};
+
+use eden::{SAMPLE_COUNT, TEXTURE_FORMAT};
+
+/// offscreen colour targets at a fixed resolution, independent of the window
+pub struct CaptureTarget {
+    pub width: u32,
+    pub height: u32,
+    msaa_texture: wgpu::Texture,
+    resolve_texture: wgpu::Texture,
+    readback_buffer: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+}
+
+impl CaptureTarget {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let msaa_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture MSAA Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: SAMPLE_COUNT,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[TEXTURE_FORMAT],
+        });
+        let resolve_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[TEXTURE_FORMAT],
+        });
+
+        // rows of a texture-to-buffer copy have to be 256 byte aligned
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Readback Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            width,
+            height,
+            msaa_texture,
+            resolve_texture,
+            readback_buffer,
+            padded_bytes_per_row,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// (render target, resolve target) in the same shape `State::render` takes for the window
+    pub fn views(&self) -> (wgpu::TextureView, Option<wgpu::TextureView>) {
+        let resolve = self
+            .resolve_texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        if SAMPLE_COUNT == 1 {
+            (resolve, None)
+        } else {
+            let msaa = self
+                .msaa_texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            (msaa, Some(resolve))
+        }
+    }
+
+    /// copies the resolved frame back and converts it to RGBA, blocking until the gpu is done
+    pub fn read_image(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> image::RgbaImage {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.resolve_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("capture readback callback was dropped")
+            .expect("failed to map capture buffer");
+
+        let mut pixels = Vec::with_capacity((self.width * self.height * 4) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                for bgra in row[..(self.width * 4) as usize].chunks(4) {
+                    // the surface format is BGRA, PNG wants RGBA
+                    pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], 255]);
+                }
+            }
+        }
+        self.readback_buffer.unmap();
+
+        image::RgbaImage::from_raw(self.width, self.height, pixels)
+            .expect("capture buffer has the wrong size")
+    }
+}
+
+/// writes numbered PNGs from a background thread so encoding does not hold up the render loop
+pub struct PngSequence {
+    pub dir: PathBuf,
+    pub frames: u32,
+    sender: Option<Sender<(PathBuf, image::RgbaImage)>>,
+    writer: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl PngSequence {
+    pub fn start(dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+        let (sender, receiver) = channel::<(PathBuf, image::RgbaImage)>();
+        let writer = thread::spawn(move || {
+            for (path, image) in receiver {
+                image
+                    .save(&path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+            Ok(())
+        });
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            frames: 0,
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    pub fn push(&mut self, image: image::RgbaImage) -> Result<(), String> {
+        let path = self.dir.join(format!("frame_{:05}.png", self.frames));
+        self.frames += 1;
+        self.sender
+            .as_ref()
+            .and_then(|sender| sender.send((path, image)).ok())
+            .ok_or_else(|| String::from("png writer stopped"))
+    }
+
+    /// waits for the queued frames to be written
+    pub fn finish(mut self) -> Result<u32, String> {
+        self.sender.take();
+        match self.writer.take().map(|writer| writer.join()) {
+            Some(Ok(result)) => result.map(|()| self.frames),
+            Some(Err(_)) => Err(String::from("png writer panicked")),
+            None => Ok(self.frames),
+        }
+    }
+}
+
+/// writes a single frame next to the recordings, named after the step it shows
+pub fn save_screenshot(dir: &Path, step: u64, image: &image::RgbaImage) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(format!("screenshot_{:08}.png", step));
+    image
+        .save(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
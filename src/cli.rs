@@ -0,0 +1,333 @@
+use std::{fs, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+
+use eden::{
+    scenario,
+    shader_registry::{self, ShaderRegistry},
+    snapshot::Snapshot,
+    Algorithm, BoundaryMode, Integrator, Params,
+};
+
+/// GPU particle simulation
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Open the interactive window (the default when no subcommand is given)
+    Run {
+        #[command(flatten)]
+        sim: SimArgs,
+        /// Window width in pixels
+        #[arg(long, default_value_t = 1920)]
+        width: u32,
+        /// Window height in pixels
+        #[arg(long, default_value_t = 1080)]
+        height: u32,
+    },
+    /// Step the simulation without a window and report how long it took
+    Headless {
+        #[command(flatten)]
+        sim: SimArgs,
+        /// Number of steps to run
+        #[arg(long, default_value_t = 1000)]
+        steps: u32,
+        /// Write a snapshot of the final state to this file
+        #[arg(long)]
+        save_snapshot: Option<PathBuf>,
+        /// Record a trajectory to this file; the format follows the extension (.csv, .xyz, .npy)
+        #[arg(long)]
+        trajectory: Option<PathBuf>,
+        /// Steps between trajectory frames
+        #[arg(long, default_value_t = 10)]
+        every: u32,
+    },
+    /// Time the compute passes and report milliseconds per step
+    Bench {
+        #[command(flatten)]
+        sim: SimArgs,
+        /// Number of timed steps
+        #[arg(long, default_value_t = 1000)]
+        steps: u32,
+        /// Untimed steps run before measuring
+        #[arg(long, default_value_t = 100)]
+        warmup: u32,
+    },
+    /// Step the simulation and write the final particle state to a CSV file
+    Export {
+        #[command(flatten)]
+        sim: SimArgs,
+        /// Number of steps to run before writing
+        #[arg(long, default_value_t = 1000)]
+        steps: u32,
+        /// Output file
+        #[arg(short, long, default_value = "particles.csv")]
+        output: PathBuf,
+    },
+}
+
+/// options shared by every subcommand, each overriding the matching `Params` default
+#[derive(Args, Debug, Default)]
+pub struct SimArgs {
+    /// Snapshot file to resume from instead of starting a new run
+    #[arg(long, conflicts_with_all = ["scenario", "particles", "world_size", "types", "grids", "dt", "shader", "seed", "deterministic", "sort_every", "boundary", "algorithm", "theta", "gravity", "integrator", "adaptive_dt", "dt_min", "dt_max", "courant", "shader_params"])]
+    pub resume: Option<PathBuf>,
+    /// Scenario file to start from; the other options override its values
+    #[arg(long)]
+    pub scenario: Option<PathBuf>,
+    /// Number of particles
+    #[arg(short = 'n', long)]
+    pub particles: Option<u32>,
+    /// Side length of the square world
+    #[arg(short, long, value_parser = parse_positive)]
+    pub world_size: Option<f32>,
+    /// Number of particle types
+    #[arg(short, long)]
+    pub types: Option<u32>,
+    /// Grid cells per side used by the neighbour search
+    #[arg(short, long)]
+    pub grids: Option<u32>,
+    /// Integration timestep
+    #[arg(long, value_parser = parse_positive)]
+    pub dt: Option<f32>,
+    /// WGSL compute shader to run: a file, or the name of a shader in the shader directory or built in
+    #[arg(short, long)]
+    pub shader: Option<PathBuf>,
+    /// Directory of user shaders offered next to the built-in ones [default: shaders]
+    #[arg(long)]
+    pub shader_dir: Option<PathBuf>,
+    /// Seed for the attraction matrix and the initial particle positions
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// What happens at the edge of the world: reflect, periodic, absorb or open
+    #[arg(long)]
+    pub boundary: Option<BoundaryMode>,
+    /// Build neighbour lists in a fixed order so the same seed replays bit for bit
+    #[arg(long)]
+    pub deterministic: bool,
+    /// Steps between Morton reorders of the particle buffers, 0 disables reordering
+    #[arg(long)]
+    pub sort_every: Option<u32>,
+    /// Force evaluation: brute_force and uniform_grid run the compute shader over every pair or the cell list, barnes_hut and particle_mesh the built-in gravity solvers
+    #[arg(long)]
+    pub algorithm: Option<Algorithm>,
+    /// Barnes-Hut opening angle, 0 sums every pair exactly
+    #[arg(long)]
+    pub theta: Option<f32>,
+    /// Gravitational constant of the Barnes-Hut and particle-mesh solvers
+    #[arg(long)]
+    pub gravity: Option<f32>,
+    /// Time integration: euler, trapezoidal, velocity_verlet, leapfrog or rk4
+    #[arg(long)]
+    pub integrator: Option<Integrator>,
+    /// Pick every step's dt from the fastest and most accelerated particle
+    #[arg(long)]
+    pub adaptive_dt: bool,
+    /// Smallest adaptive dt
+    #[arg(long)]
+    pub dt_min: Option<f32>,
+    /// Largest adaptive dt
+    #[arg(long)]
+    pub dt_max: Option<f32>,
+    /// Particle radii a particle may move in one adaptive step
+    #[arg(long)]
+    pub courant: Option<f32>,
+    /// Value of one of the shader's `// @param` tunables, may be repeated
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_shader_param)]
+    pub shader_params: Vec<(String, f32)>,
+}
+
+/// a finite number above zero; NaN compares false with everything, so it is refused too
+fn parse_positive(arg: &str) -> Result<f32, String> {
+    let value: f32 = arg
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", arg))?;
+    if !(value.is_finite() && value > 0.0) {
+        return Err(format!("must be positive, got {}", value));
+    }
+    Ok(value)
+}
+
+fn parse_shader_param(arg: &str) -> Result<(String, f32), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got {:?}", arg))?;
+    let value: f32 = value
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", value))?;
+    if !value.is_finite() {
+        return Err(format!("{} must be a finite number", name));
+    }
+    Ok((name.to_string(), value))
+}
+
+/// how a run begins: from parameters or from a saved snapshot
+pub enum Start {
+    Fresh(Params),
+    Resume(Snapshot),
+}
+
+impl Start {
+    pub fn into_simulation(
+        self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<eden::Simulation, String> {
+        match self {
+            Start::Fresh(params) => eden::Simulation::try_new(params, device),
+            Start::Resume(snapshot) => snapshot
+                .to_simulation(device, queue)
+                .map_err(|e| format!("could not resume: {}", e)),
+        }
+    }
+}
+
+impl SimArgs {
+    pub fn start(&self) -> Result<Start, String> {
+        match &self.resume {
+            Some(path) => Snapshot::load(path)
+                .map(Start::Resume)
+                .map_err(|e| format!("could not resume from {}: {}", path.display(), e)),
+            None => self.params().map(Start::Fresh),
+        }
+    }
+
+    /// the built-in shaders and those in `--shader-dir`
+    pub fn shader_registry(&self) -> ShaderRegistry {
+        let dir = self
+            .shader_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(shader_registry::DEFAULT_USER_DIR));
+        ShaderRegistry::new(Some(dir))
+    }
+
+    pub fn params(&self) -> Result<Params, String> {
+        let mut params = match &self.scenario {
+            Some(path) => {
+                scenario::load_params(path, &self.shader_registry()).map_err(|e| e.to_string())?
+            }
+            None => Params::new(),
+        };
+
+        if let Some(num_particles) = self.particles {
+            params.num_particles = num_particles;
+        }
+        if let Some(world_size) = self.world_size {
+            params.world_size = world_size;
+        }
+        if let Some(num_grids_side) = self.grids {
+            params.num_grids_side = num_grids_side;
+        }
+        if let Some(dt) = self.dt {
+            params.dt = dt;
+        }
+        if let Some(seed) = self.seed {
+            params.seed = seed;
+        }
+        if let Some(num_types) = self.types {
+            params.num_types = num_types;
+        }
+        // a scenario's matrix is kept as written unless the number of types changes
+        if self.types.is_some() || (self.seed.is_some() && self.scenario.is_none()) {
+            params.randomize_matrix();
+        }
+        if let Some(boundary) = self.boundary {
+            params.boundary = boundary;
+        }
+        if self.deterministic {
+            params.deterministic = true;
+        }
+        if let Some(sort_every) = self.sort_every {
+            params.sort_every = sort_every;
+        }
+        if let Some(algorithm) = self.algorithm {
+            params.algorithm = algorithm;
+        }
+        if let Some(theta) = self.theta {
+            params.theta = theta;
+        }
+        if let Some(gravity) = self.gravity {
+            params.gravity = gravity;
+        }
+        if let Some(integrator) = self.integrator {
+            params.integrator = integrator;
+        }
+        if self.adaptive_dt {
+            params.adaptive_dt = true;
+        }
+        if let Some(dt_min) = self.dt_min {
+            params.dt_min = dt_min;
+        }
+        if let Some(dt_max) = self.dt_max {
+            params.dt_max = dt_max;
+        }
+        if let Some(courant) = self.courant {
+            params.courant = courant;
+        }
+        if let Some(path) = &self.shader {
+            let entry = match fs::read_to_string(path) {
+                Ok(source) => Ok((path.file_name(), source)),
+                Err(e) => self
+                    .shader_registry()
+                    .get(&path.to_string_lossy())
+                    .map(|entry| (path.file_name(), entry.source.clone()))
+                    .ok_or_else(|| format!("could not read shader {}: {}", path.display(), e)),
+            };
+            let (name, source) = entry?;
+            params.shader_buffer = source;
+            params.shader_name =
+                name.map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        }
+        if !self.shader_params.is_empty() {
+            let declared = params
+                .shader_params()
+                .map_err(|e| format!("{}: {}", params.shader_name, e))?;
+            for (name, value) in &self.shader_params {
+                if !declared.iter().any(|param| &param.name == name) {
+                    return Err(format!(
+                        "--param {}: {} declares no such @param",
+                        name, params.shader_name
+                    ));
+                }
+                params.shader_values.insert(name.clone(), *value);
+            }
+        }
+
+        if params.num_particles == 0 {
+            return Err("--particles must be at least 1".to_string());
+        }
+        if params.num_types == 0 {
+            return Err("--types must be at least 1".to_string());
+        }
+        if params.num_grids_side == 0 {
+            return Err("--grids must be at least 1".to_string());
+        }
+        // a scenario can bring these as well, so they are checked once everything is merged
+        for (flag, value) in [
+            ("--world-size", params.world_size),
+            ("--dt", params.dt),
+            ("particle_radius", params.particle_radius),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(format!("{} must be positive, got {}", flag, value));
+            }
+        }
+        params
+            .algorithm
+            .check_grids_side(params.num_grids_side)
+            .map_err(|reason| format!("--grids {}", reason))?;
+        if params.theta.is_nan() || params.theta < 0.0 {
+            return Err("--theta must be zero or positive".to_string());
+        }
+        params
+            .check_dt_bounds()
+            .map_err(|(field, reason)| format!("--{} {}", field.replace('_', "-"), reason))?;
+
+        Ok(params)
+    }
+}
@@ -0,0 +1,204 @@
+//! What the in-app shader editor needs from the shader it shows: WGSL split
+//! into tokens to colour, and naga's diagnostics placed back on the text being
+//! edited rather than on the composed shader.
+
+use std::ops::Range;
+
+use crate::{force, shader_params, Params, Simulation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Comment,
+    Keyword,
+    /// built-in types such as `f32` or `vec2`
+    Type,
+    /// `@compute`, `@binding` and the like
+    Attribute,
+    Number,
+    Identifier,
+    Punctuation,
+    Whitespace,
+}
+
+/// `source` split into tokens covering every byte of it, in order
+pub fn tokenize(source: &str) -> Vec<(TokenKind, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    while start < source.len() {
+        let rest = &source[start..];
+        let (kind, len) = if rest.starts_with("//") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            let len = rest.find("*/").map_or(rest.len(), |end| end + 2);
+            (TokenKind::Comment, len)
+        } else {
+            let first = rest.chars().next().unwrap_or_default();
+            if first.is_whitespace() {
+                (TokenKind::Whitespace, run(rest, char::is_whitespace))
+            } else if first.is_ascii_digit() {
+                (TokenKind::Number, number_len(rest))
+            } else if first == '@' {
+                let len = 1 + run(&rest[1..], is_word_char);
+                (TokenKind::Attribute, len)
+            } else if is_word_char(first) {
+                let len = run(rest, is_word_char);
+                (word_kind(&rest[..len]), len)
+            } else {
+                (TokenKind::Punctuation, first.len_utf8())
+            }
+        };
+        tokens.push((kind, start..start + len));
+        start += len;
+    }
+    tokens
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// bytes at the start of `text` whose chars all satisfy `f`
+fn run(text: &str, f: impl Fn(char) -> bool) -> usize {
+    text.find(|c: char| !f(c)).unwrap_or(text.len())
+}
+
+/// a decimal or hex literal with its suffix, and the sign of an exponent
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        let c = bytes[len];
+        let exponent_sign = (c == b'+' || c == b'-')
+            && len > 0
+            && matches!(bytes[len - 1], b'e' | b'E')
+            && !text.starts_with("0x");
+        if c.is_ascii_alphanumeric() || c == b'.' || c == b'_' || exponent_sign {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+fn word_kind(word: &str) -> TokenKind {
+    match word {
+        "alias" | "break" | "case" | "const" | "const_assert" | "continue" | "continuing"
+        | "default" | "discard" | "else" | "enable" | "false" | "fn" | "for" | "if" | "let"
+        | "loop" | "override" | "return" | "struct" | "switch" | "true" | "var" | "while"
+        | "uniform" | "storage" | "read" | "read_write" | "workgroup" | "private" | "function" => {
+            TokenKind::Keyword
+        }
+        "bool" | "f16" | "f32" | "i32" | "u32" | "array" | "atomic" | "ptr" | "sampler" => {
+            TokenKind::Type
+        }
+        _ if word.starts_with("vec") || word.starts_with("mat") || word.starts_with("texture_") => {
+            TokenKind::Type
+        }
+        _ => TokenKind::Identifier,
+    }
+}
+
+/// something wrong with the shader being edited
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// bytes of the edited source it points at, if it points into it at all
+    pub span: Option<Range<usize>>,
+    /// 1-based line of the start of `span`
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(source: &str, message: String, span: Option<Range<usize>>) -> Self {
+        // spans past the end are in the core or the generated `@param` uniform
+        let span = span.filter(|span| span.end <= source.len());
+        let line = span
+            .as_ref()
+            .map(|span| source[..span.start].matches('\n').count() + 1);
+        Diagnostic {
+            message,
+            span,
+            line,
+        }
+    }
+}
+
+/// everything that keeps `params.shader_buffer` from building, as the editor shows it:
+/// malformed `@param` annotations, then naga's parse or validation errors, then bindings
+/// eden does not provide. Empty when the shader would build
+pub fn diagnose(params: &Params) -> Vec<Diagnostic> {
+    let source = params.shader_buffer.as_str();
+    if let Err(reason) = shader_params::parse(source) {
+        let span = reason
+            .strip_prefix("line ")
+            .and_then(|rest| rest.split(':').next()?.parse::<usize>().ok())
+            .and_then(|line| line_span(source, line));
+        return vec![Diagnostic::new(source, reason, span)];
+    }
+
+    // modules and full shaders both start with the edited source, so spans carry over
+    let composed = force::compose(source);
+    let module = match naga::front::wgsl::parse_str(&composed) {
+        Ok(module) => module,
+        Err(e) => {
+            return e
+                .labels()
+                .map(|(span, label)| {
+                    let message = match label {
+                        "" => e.message().to_string(),
+                        label => format!("{}: {}", e.message(), label),
+                    };
+                    Diagnostic::new(source, message, span.to_range())
+                })
+                .collect()
+        }
+    };
+    let validated = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module);
+    if let Err(e) = validated {
+        let message = error_chain(e.as_inner());
+        let mut diagnostics: Vec<Diagnostic> = e
+            .spans()
+            .map(|(span, label)| {
+                Diagnostic::new(source, format!("{}: {}", message, label), span.to_range())
+            })
+            .collect();
+        if diagnostics.is_empty() {
+            diagnostics.push(Diagnostic::new(source, message, None));
+        }
+        return diagnostics;
+    }
+
+    match Simulation::check_shader(params) {
+        Ok(_) => Vec::new(),
+        Err(reason) => vec![Diagnostic::new(source, reason, None)],
+    }
+}
+
+/// the error and the errors it wraps, outermost first
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(inner) = source {
+        message.push_str(": ");
+        message.push_str(&inner.to_string());
+        source = inner.source();
+    }
+    message
+}
+
+/// bytes of the 1-based `line` of `source`, without its newline
+fn line_span(source: &str, line: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+    for (index, text) in source.split('\n').enumerate() {
+        if index + 1 == line {
+            return Some(start..start + text.len());
+        }
+        start += text.len() + 1;
+    }
+    None
+}
@@ -0,0 +1,64 @@
+//! Force modules: the interaction law of the uniform-grid pass without the
+//! neighbour search around it.
+//!
+//! A module is a WGSL snippet defining
+//!
+//! ```wgsl
+//! fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32>
+//! ```
+//!
+//! which returns the acceleration `other` gives `particle`, `r` being the
+//! shortest vector from one to the other. [`compose`] puts it in front of
+//! `shaders/core.wgsl`, so the lines naga reports are the module's, and the
+//! core's `main` walks the cell list and sums `force` into the accelerations
+//! the integration passes read. Everything the core declares is in scope:
+//! `params`, `attraction(particle, other)`, `separation` and the boundary
+//! constants. A module may also define `fn interaction_radius() -> f32` for how
+//! far the search reaches; it defaults to one cell.
+//!
+//! A shader with its own `@compute` entry point is a complete force pass and is
+//! used as it is, apart from the `shader_params` uniform any `// @param`
+//! annotations ask for (see [`shader_params`]).
+
+use std::borrow::Cow;
+
+use crate::shader_params;
+
+/// bindings, neighbour search and helpers every force module is spliced into
+pub const CORE_SHADER: &str = include_str!("shaders/core.wgsl");
+
+/// search radius of a module that does not pick one
+const DEFAULT_INTERACTION_RADIUS: &str = "
+fn interaction_radius() -> f32 {
+  return params.grid_size_side;
+}
+";
+
+/// whether `source` is a force module rather than a complete compute shader
+pub fn is_module(source: &str) -> bool {
+    !source.contains("@compute")
+}
+
+/// the compute shader for `source`, spliced into the core when it is a module.
+/// Malformed `@param` annotations are left out here, [`shader_params::parse`] reports them
+pub fn compose(source: &str) -> Cow<'_, str> {
+    let declarations =
+        shader_params::declarations(&shader_params::parse(source).unwrap_or_default());
+    if !is_module(source) {
+        if declarations.is_empty() {
+            return Cow::Borrowed(source);
+        }
+        // appended so the lines naga reports still match the file
+        return Cow::Owned(format!("{}\n{}", source, declarations));
+    }
+    // WGSL declarations may come in any order, so the module goes first and keeps its lines
+    let mut shader = String::with_capacity(CORE_SHADER.len() + source.len() + 128);
+    shader.push_str(source);
+    shader.push_str("\n// ---- core ----\n\n");
+    shader.push_str(CORE_SHADER);
+    if !source.contains("fn interaction_radius") {
+        shader.push_str(DEFAULT_INTERACTION_RADIUS);
+    }
+    shader.push_str(&declarations);
+    Cow::Owned(shader)
+}
@@ -1,26 +1,43 @@
 use egui::{self};
 
-use std::fs;
+use std::path::Path;
 
 use egui_wgpu_backend::{RenderPass, ScreenDescriptor};
 use egui_winit_platform::{Platform, PlatformDescriptor};
 
-use glob::glob;
 use winit::window::Window;
 
 use wgpu::{Device, SurfaceConfiguration, TextureView};
 #[derive(PartialEq)]
 pub enum OutputState {
     ReloadRequired,
+    /// rebuild the running force pass from the editor's source
+    ApplyShader,
+    SaveSnapshot,
+    LoadSnapshot,
+    ToggleRecording,
+    Screenshot,
+    ToggleFrameRecording,
     TogglePlay,
     Debug,
     None,
     Step,
 }
-use eden::Params;
+use eden::{
+    editor::{self, Diagnostic, TokenKind},
+    matrix::{self, MatrixGenerator},
+    palette::Palette,
+    scenario,
+    shader_params::ShaderParam,
+    shader_registry::ShaderRegistry,
+    Algorithm, BoundaryMode, Integrator, Params, Simulation,
+};
 
 use eden::TEXTURE_FORMAT;
 
+/// smallest dt the time step fields go down to, scenarios need it positive
+const MIN_DT: f32 = 1e-7;
+
 pub struct Gui {
     pub platform: Platform,
     egui_rpass: egui_wgpu_backend::RenderPass,
@@ -28,36 +45,67 @@ pub struct Gui {
     pub state: OutputState,
     inner_params: Params,
     pub frame_rate: f32,
-    pub shader_options: Vec<String>,
+    /// dt of the last step and the time simulated so far, refreshed with the frame rate
+    pub effective_dt: f32,
+    pub simulated_time: f32,
+    /// the shaders offered in the shader list
+    pub shaders: ShaderRegistry,
     pub selected_shader_file: String,
+    /// the `// @param` tunables of the selected shader, one slider each
+    shader_params: Vec<ShaderParam>,
+    scenario_path: String,
+    pub scenario_status: String,
+    matrix_generator: MatrixGenerator,
+    palette: Palette,
+    /// the matrix entry whose exact value is shown under the heatmap
+    matrix_selected: Option<(usize, usize)>,
+    /// the matrix as text, filled by "Copy" and read by "Paste"
+    matrix_text: String,
+    /// parameters changed in the panel that only take effect on restart
+    pub pending_restart: Vec<&'static str>,
+    /// why the shader saved last did not build, shown until it builds or is dismissed
+    pub shader_error: Option<String>,
+    editor_open: bool,
+    /// the source in the shader editor, run with "Apply"
+    pub editor_source: String,
+    /// the shader source the editor was last loaded from, it reloads when that changes
+    editor_base: String,
+    editor_diagnostics: Vec<Diagnostic>,
+    pub snapshot_path: String,
+    pub snapshot_status: String,
+    pub trajectory_path: String,
+    pub trajectory_every: u32,
+    pub trajectory_status: String,
+    pub recording: bool,
+    pub capture_width: u32,
+    pub capture_height: u32,
+    pub capture_dir: String,
+    pub capture_status: String,
+    pub recording_frames: bool,
 }
 
 impl Gui {
-    pub fn new(window: &Window, device: &Device, _config: &SurfaceConfiguration) -> Self {
+    pub fn new(
+        window: &Window,
+        device: &Device,
+        _config: &SurfaceConfiguration,
+        inner_params: Params,
+        shaders: ShaderRegistry,
+    ) -> Self {
         let egui_rpass = RenderPass::new(device, TEXTURE_FORMAT, 1);
         let platform = Platform::new(PlatformDescriptor {
-            physical_width: window.inner_size().width as u32,
-            physical_height: window.inner_size().height as u32,
+            physical_width: window.inner_size().width,
+            physical_height: window.inner_size().height,
             scale_factor: window.scale_factor(),
             font_definitions: egui::FontDefinitions::default(),
             style: Default::default(),
         });
         let tdelta = egui::TexturesDelta::default();
         let state = OutputState::None;
-        let inner_params = Params::new();
         let frame_rate = 0.0;
 
-        let mut shader_options: Vec<String> = Vec::new();
-        let selected_shader_file = String::from("experimental.wgsl");
-
-        for entry in glob("./shaders/*").expect("Failed to read glob pattern") {
-            match entry {
-                Ok(path) => {
-                    shader_options.push(String::from(path.file_name().unwrap().to_str().unwrap()))
-                }
-                Err(e) => println!("{:?}", e),
-            }
-        }
+        let selected_shader_file = inner_params.shader_name.clone();
+        let shader_params = inner_params.shader_params().unwrap_or_default();
 
         Self {
             platform,
@@ -66,8 +114,34 @@ impl Gui {
             state,
             inner_params,
             frame_rate,
-            shader_options,
+            effective_dt: 0.0,
+            simulated_time: 0.0,
+            shaders,
             selected_shader_file,
+            shader_params,
+            scenario_path: String::from("scenario.toml"),
+            scenario_status: String::new(),
+            matrix_generator: MatrixGenerator::default(),
+            palette: Palette::default(),
+            matrix_selected: None,
+            matrix_text: String::new(),
+            pending_restart: Vec::new(),
+            shader_error: None,
+            editor_open: false,
+            editor_source: String::new(),
+            editor_base: String::new(),
+            editor_diagnostics: Vec::new(),
+            snapshot_path: String::from("snapshot.eden"),
+            snapshot_status: String::new(),
+            trajectory_path: String::from("trajectory.xyz"),
+            trajectory_every: 10,
+            trajectory_status: String::new(),
+            recording: false,
+            capture_width: 1920,
+            capture_height: 1080,
+            capture_dir: String::from("captures"),
+            capture_status: String::new(),
+            recording_frames: false,
         }
     }
     pub fn ui(&mut self) {
@@ -79,6 +153,11 @@ impl Gui {
         // });
         let mut open = true;
         self.state = OutputState::None;
+        if self.editor_base != self.inner_params.shader_buffer {
+            self.editor_base = self.inner_params.shader_buffer.clone();
+            self.editor_source = self.editor_base.clone();
+            self.diagnose_editor();
+        }
         egui::Window::new("Simulation Parameters")
             .open(&mut open)
             .resizable(true)
@@ -94,41 +173,191 @@ impl Gui {
                         ui.label(format!("Frame Rate: {}", self.frame_rate));
                         ui.end_row();
 
+                        ui.label(format!("Effective dt: {:.3e}", self.effective_dt));
+                        ui.label(format!("Simulated Time: {:.4}", self.simulated_time));
+                        ui.end_row();
+
                         ui.label("World Size: ");
-                        ui.add(egui::DragValue::new(&mut self.inner_params.world_size));
+                        ui.add(
+                            egui::DragValue::new(&mut self.inner_params.world_size)
+                                .clamp_range(1.0..=f32::MAX),
+                        );
                         ui.end_row();
 
                         ui.label("Grid Lengths Per Side: ");
                         ui.add(egui::DragValue::new(&mut self.inner_params.num_grids_side));
                         ui.end_row();
 
+                        ui.label("Seed: ");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.inner_params.seed));
+                            if ui.button("Matrix From Seed").clicked() {
+                                self.inner_params.randomize_matrix();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Boundary: ");
+                        egui::ComboBox::from_id_source("boundary")
+                            .selected_text(self.inner_params.boundary.to_string())
+                            .show_ui(ui, |ui| {
+                                for mode in BoundaryMode::ALL {
+                                    ui.selectable_value(
+                                        &mut self.inner_params.boundary,
+                                        mode,
+                                        mode.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Algorithm: ");
+                        egui::ComboBox::from_id_source("algorithm")
+                            .selected_text(self.inner_params.algorithm.to_string())
+                            .show_ui(ui, |ui| {
+                                for algorithm in Algorithm::ALL {
+                                    ui.selectable_value(
+                                        &mut self.inner_params.algorithm,
+                                        algorithm,
+                                        algorithm.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("(Barnes-Hut) Opening Angle θ: ");
+                        ui.add(egui::Slider::new(&mut self.inner_params.theta, 0.0..=2.0));
+                        ui.end_row();
+
+                        ui.label("(Barnes-Hut, Particle Mesh) Gravity: ");
+                        ui.add(egui::DragValue::new(&mut self.inner_params.gravity));
+                        ui.end_row();
+
+                        ui.label("Integrator: ");
+                        egui::ComboBox::from_id_source("integrator")
+                            .selected_text(self.inner_params.integrator.to_string())
+                            .show_ui(ui, |ui| {
+                                for integrator in Integrator::ALL {
+                                    ui.selectable_value(
+                                        &mut self.inner_params.integrator,
+                                        integrator,
+                                        integrator.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Deterministic Neighbour Search: ");
+                        ui.checkbox(&mut self.inner_params.deterministic, "");
+                        ui.end_row();
+
+                        ui.label("Morton Sort Every (steps): ");
+                        ui.add(egui::DragValue::new(&mut self.inner_params.sort_every));
+                        ui.end_row();
+
                         ui.label("Number of Types: ");
                         ui.add(egui::DragValue::new(&mut self.inner_params.num_types));
                         ui.end_row();
 
-                        let max_types: usize = ((self.inner_params.attraction_matrix.len() / 4)
-                            as f32)
-                            .sqrt() as usize;
-                        for i in 0..max_types {
-                            ui.label(format!("Particle Type {} Forces: ", i));
+                        ui.label("Attraction Matrix: ");
+                        ui.vertical(|ui| {
+                            matrix_heatmap(ui, &mut self.inner_params, &mut self.matrix_selected);
+                        });
+                        ui.end_row();
+
+                        ui.label("Matrix Generator: ");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("matrix_generator")
+                                .selected_text(self.matrix_generator.to_string())
+                                .show_ui(ui, |ui| {
+                                    for generator in MatrixGenerator::ALL {
+                                        ui.selectable_value(
+                                            &mut self.matrix_generator,
+                                            generator,
+                                            generator.to_string(),
+                                        );
+                                    }
+                                });
+                            if ui.button("Generate").clicked() {
+                                self.inner_params.generate_matrix(self.matrix_generator);
+                            }
+                            if ui.button("Transpose").clicked() {
+                                let rows =
+                                    matrix::transpose(&shown_matrix_rows(&self.inner_params));
+                                self.inner_params.set_matrix_rows(&rows);
+                            }
+                            if ui.button("Negate").clicked() {
+                                let rows = matrix::negate(&shown_matrix_rows(&self.inner_params));
+                                self.inner_params.set_matrix_rows(&rows);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Matrix As Text: ");
+                        ui.vertical(|ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut self.matrix_text)
+                                    .code_editor()
+                                    .desired_rows(3),
+                            );
                             ui.horizontal(|ui| {
-                                for j in 0..max_types {
-                                    ui.add(
-                                        egui::Slider::new(
-                                            &mut self.inner_params.attraction_matrix
-                                                [i * 4 * max_types + j * 4],
-                                            -1.0..=1.0,
-                                        )
-                                        .show_value(false),
-                                    );
+                                if ui.button("Copy").clicked() {
+                                    self.matrix_text =
+                                        matrix::to_text(&shown_matrix_rows(&self.inner_params));
+                                    let text = self.matrix_text.clone();
+                                    ui.output_mut(|o| o.copied_text = text);
+                                }
+                                if ui.button("Paste").clicked() {
+                                    match matrix::parse(&self.matrix_text) {
+                                        Ok(rows) => {
+                                            // a matrix of another size brings its number of types
+                                            self.inner_params.num_types = rows.len() as u32;
+                                            self.inner_params.set_matrix_rows(&rows);
+                                            self.matrix_selected = None;
+                                        }
+                                        Err(reason) => {
+                                            self.scenario_status = format!("matrix: {}", reason)
+                                        }
+                                    }
                                 }
                             });
-
-                            ui.end_row();
-                        }
+                        });
+                        ui.end_row();
 
                         ui.label("Delta Time: ");
-                        ui.add(egui::DragValue::new(&mut self.inner_params.dt).max_decimals(5));
+                        ui.add(
+                            egui::DragValue::new(&mut self.inner_params.dt)
+                                .max_decimals(5)
+                                .clamp_range(MIN_DT..=f32::MAX),
+                        );
+                        ui.end_row();
+
+                        ui.label("Adaptive dt: ");
+                        ui.checkbox(&mut self.inner_params.adaptive_dt, "");
+                        ui.end_row();
+
+                        ui.label("(Adaptive) dt Bounds: ");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.inner_params.dt_min)
+                                    .max_decimals(7)
+                                    .speed(1e-6)
+                                    .clamp_range(MIN_DT..=f32::MAX),
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut self.inner_params.dt_max)
+                                    .max_decimals(5)
+                                    .speed(1e-4)
+                                    .clamp_range(MIN_DT..=f32::MAX),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("(Adaptive) Courant Number: ");
+                        ui.add(egui::Slider::new(
+                            &mut self.inner_params.courant,
+                            0.01..=1.0,
+                        ));
                         ui.end_row();
 
                         ui.label("Number of Particles: ");
@@ -163,48 +392,139 @@ impl Gui {
                         ui.label("Particle Radius");
                         ui.add(egui::Slider::new(
                             &mut self.inner_params.particle_radius,
-                            0.0..=1.0,
+                            0.01..=1.0,
                         ));
 
                         ui.end_row();
                         ui.label("Compute Shader File");
-                        egui::ComboBox::from_label("")
-                            .selected_text(format!("{}", self.selected_shader_file))
-                            .show_ui(ui, |ui| {
-                                for file in &self.shader_options {
-                                    if ui
-                                        .add(egui::SelectableLabel::new(
-                                            self.selected_shader_file == *file,
-                                            file,
-                                        ))
-                                        .clicked()
-                                    {
-                                        self.selected_shader_file = file.clone();
-                                        let filename = String::from("shaders/")
-                                            + &self.selected_shader_file.clone();
-                                        self.inner_params.shader_buffer =
-                                            fs::read_to_string(&filename[..]).unwrap();
-                                        println!("{:?}", self.inner_params.shader_buffer);
+                        // the built-in solvers bring their own shaders
+                        let uses_shader = self.inner_params.algorithm.uses_shader_buffer();
+                        ui.add_enabled_ui(uses_shader, |ui| {
+                            egui::ComboBox::from_label("")
+                                .selected_text(self.selected_shader_file.to_string())
+                                .show_ui(ui, |ui| {
+                                    let algorithm = self.inner_params.algorithm;
+                                    for entry in self.shaders.entries() {
+                                        let label = egui::SelectableLabel::new(
+                                            self.selected_shader_file == entry.name,
+                                            &entry.name,
+                                        );
+                                        if ui
+                                            .add_enabled(entry.runs_with(algorithm), label)
+                                            .on_hover_text(&entry.description)
+                                            .on_disabled_hover_text(format!(
+                                                "{} needs {}",
+                                                entry.name,
+                                                entry.algorithm.unwrap_or(algorithm)
+                                            ))
+                                            .clicked()
+                                        {
+                                            self.selected_shader_file = entry.name.clone();
+                                            self.inner_params.shader_buffer = entry.source.clone();
+                                            self.inner_params.shader_name = entry.name.clone();
+                                            self.shader_params = self
+                                                .inner_params
+                                                .shader_params()
+                                                .unwrap_or_default();
+                                            self.scenario_status =
+                                                shader_status(&self.inner_params);
+                                        }
                                     }
-                                }
-                            });
+                                });
+                            if ui.add(egui::Button::new("Edit")).clicked() {
+                                self.editor_open = true;
+                            }
+                        });
+                        ui.end_row();
+
+                        if uses_shader {
+                            for param in &self.shader_params {
+                                let value = self
+                                    .inner_params
+                                    .shader_values
+                                    .entry(param.name.clone())
+                                    .or_insert(param.default);
+                                ui.label(format!("(Shader) {}", param.name));
+                                ui.add(egui::Slider::new(value, param.min..=param.max));
+                                ui.end_row();
+                            }
+                        }
 
+                        ui.label("Type Colours: ");
+                        ui.horizontal_wrapped(|ui| {
+                            let num_types = self.inner_params.num_types as usize;
+                            for index in 0..num_types {
+                                let colour = self.inner_params.type_colour(index);
+                                let mut srgb = colour.map(|c| (c * 255.0).round() as u8);
+                                let response = egui::widgets::color_picker::color_edit_button_srgb(
+                                    ui, &mut srgb,
+                                )
+                                .on_hover_text(format!("type {}", index));
+                                if response.changed() {
+                                    // the types before this one keep the colours they showed
+                                    let params = &mut self.inner_params;
+                                    while params.type_colours.len() <= index {
+                                        let shown = params.type_colour(params.type_colours.len());
+                                        params.type_colours.push(shown);
+                                    }
+                                    params.type_colours[index] = srgb.map(|c| c as f32 / 255.0);
+                                }
+                            }
+                        });
                         ui.end_row();
 
-                        egui::widgets::color_picker::color_edit_button_rgb(
-                            ui,
-                            &mut [255.0, 0.0, 0.0],
-                        );
+                        ui.label("Palette: ");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("palette")
+                                .selected_text(self.palette.to_string())
+                                .show_ui(ui, |ui| {
+                                    for palette in Palette::ALL {
+                                        let label = if palette.colour_blind_safe() {
+                                            format!("{} (colour-blind safe)", palette)
+                                        } else {
+                                            palette.to_string()
+                                        };
+                                        ui.selectable_value(&mut self.palette, palette, label);
+                                    }
+                                });
+                            if ui.button("Apply Palette").clicked() {
+                                let num_types = self.inner_params.num_types as usize;
+                                self.inner_params.type_colours = self.palette.colours(num_types);
+                            }
+                        });
+                        ui.end_row();
                     });
 
                 if ui.add(egui::Button::new("Restart Simulation")).clicked() {
-                    self.state = OutputState::ReloadRequired;
+                    // the built-in solvers only run on some grid sizes
+                    let checked = self
+                        .inner_params
+                        .algorithm
+                        .check_grids_side(self.inner_params.num_grids_side)
+                        .map_err(|reason| format!("Grid Lengths Per Side {}", reason))
+                        .and_then(|()| {
+                            self.inner_params
+                                .check_dt_bounds()
+                                .map_err(|(field, reason)| format!("{} {}", field, reason))
+                        })
+                        .and_then(|()| Simulation::check_shader(&self.inner_params).map(|_| ()));
+                    match checked {
+                        Ok(()) => self.state = OutputState::ReloadRequired,
+                        Err(reason) => self.scenario_status = reason,
+                    }
+                }
+                if !self.pending_restart.is_empty() {
+                    ui.label(format!(
+                        "Restart to apply: {}",
+                        self.pending_restart.join(", ")
+                    ));
                 }
 
                 if ui
                     .add(egui::Button::new("Randomize Attraction Matrix"))
                     .clicked()
                 {
+                    self.inner_params.reseed();
                     self.inner_params.randomize_matrix();
                 }
                 if ui.add(egui::Button::new("Play / Pause")).clicked() {
@@ -218,18 +538,194 @@ impl Gui {
                 if ui.add(egui::Button::new("Step")).clicked() {
                     self.state = OutputState::Step;
                 }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Scenario File: ");
+                    ui.text_edit_singleline(&mut self.scenario_path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.add(egui::Button::new("Save Scenario")).clicked() {
+                        let path = Path::new(&self.scenario_path);
+                        self.scenario_status = match scenario::save_params(&self.inner_params, path)
+                        {
+                            Ok(()) => format!("saved {}", path.display()),
+                            Err(e) => e.to_string(),
+                        };
+                    }
+                    if ui.add(egui::Button::new("Load Scenario")).clicked() {
+                        let path = Path::new(&self.scenario_path);
+                        self.scenario_status = match scenario::load_params(path, &self.shaders) {
+                            Ok(params) => {
+                                self.selected_shader_file = params.shader_name.clone();
+                                self.shader_params = params.shader_params().unwrap_or_default();
+                                self.inner_params = params;
+                                self.state = OutputState::ReloadRequired;
+                                format!("loaded {}", path.display())
+                            }
+                            Err(e) => e.to_string(),
+                        };
+                    }
+                });
+                if !self.scenario_status.is_empty() {
+                    ui.label(&self.scenario_status);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Snapshot File: ");
+                    ui.text_edit_singleline(&mut self.snapshot_path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.add(egui::Button::new("Save Snapshot")).clicked() {
+                        self.state = OutputState::SaveSnapshot;
+                    }
+                    if ui.add(egui::Button::new("Resume Snapshot")).clicked() {
+                        self.state = OutputState::LoadSnapshot;
+                    }
+                });
+                if !self.snapshot_status.is_empty() {
+                    ui.label(&self.snapshot_status);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Trajectory File: ");
+                    ui.add_enabled(
+                        !self.recording,
+                        egui::TextEdit::singleline(&mut self.trajectory_path),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Record Every N Steps: ");
+                    ui.add_enabled(
+                        !self.recording,
+                        egui::DragValue::new(&mut self.trajectory_every).clamp_range(1..=100000),
+                    );
+                });
+                let label = if self.recording {
+                    "Stop Recording"
+                } else {
+                    "Record Trajectory"
+                };
+                if ui.add(egui::Button::new(label)).clicked() {
+                    self.state = OutputState::ToggleRecording;
+                }
+                if !self.trajectory_status.is_empty() {
+                    ui.label(&self.trajectory_status);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Capture Resolution: ");
+                    ui.add_enabled(
+                        !self.recording_frames,
+                        egui::DragValue::new(&mut self.capture_width).clamp_range(1..=8192),
+                    );
+                    ui.label("x");
+                    ui.add_enabled(
+                        !self.recording_frames,
+                        egui::DragValue::new(&mut self.capture_height).clamp_range(1..=8192),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Capture Folder: ");
+                    ui.add_enabled(
+                        !self.recording_frames,
+                        egui::TextEdit::singleline(&mut self.capture_dir),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    if ui.add(egui::Button::new("Screenshot (P)")).clicked() {
+                        self.state = OutputState::Screenshot;
+                    }
+                    let label = if self.recording_frames {
+                        "Stop Recording Frames"
+                    } else {
+                        "Record Frames"
+                    };
+                    if ui.add(egui::Button::new(label)).clicked() {
+                        self.state = OutputState::ToggleFrameRecording;
+                    }
+                });
+                if !self.capture_status.is_empty() {
+                    ui.label(&self.capture_status);
+                }
             });
 
-        //  egui::Window::new("Edit Shader")
-        //  .resizable(true)
-        //   .min_width(10000.0)
-        // .fixed_size([500.0, 500.0])
-        // .anchor(egui::Align2::RIGHT_TOP, [-5.0, 5.0])
-        // .show(&self.platform.context(), |ui| {
-        //   ScrollArea::vertical().show(ui, |ui| {
-        //     ui.add(TextEdit::multiline(&mut self.inner_params.shader_buffer).code_editor());
-        // });
-        // });
+        let mut editor_open = self.editor_open;
+        let mut edited = false;
+        egui::Window::new("Edit Shader")
+            .open(&mut editor_open)
+            .resizable(true)
+            .default_width(560.0)
+            .show(&self.platform.context(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(&self.inner_params.shader_name);
+                    let applicable = self.editor_diagnostics.is_empty()
+                        && self.inner_params.algorithm.uses_shader_buffer();
+                    if ui
+                        .add_enabled(applicable, egui::Button::new("Apply"))
+                        .clicked()
+                    {
+                        self.state = OutputState::ApplyShader;
+                    }
+                    if ui.add(egui::Button::new("Revert")).clicked() {
+                        self.editor_source = self.editor_base.clone();
+                        edited = true;
+                    }
+                });
+
+                let diagnostics = &self.editor_diagnostics;
+                let source = &mut self.editor_source;
+                let mut layouter = |ui: &egui::Ui, source: &str, wrap_width: f32| {
+                    let mut job = highlight(ui, source, diagnostics);
+                    job.wrap.max_width = wrap_width;
+                    ui.fonts(|fonts| fonts.layout_job(job))
+                };
+                egui::ScrollArea::vertical()
+                    .max_height(480.0)
+                    .show(ui, |ui| {
+                        let response = ui.add(
+                            egui::TextEdit::multiline(source)
+                                .code_editor()
+                                .desired_rows(30)
+                                .desired_width(f32::INFINITY)
+                                .layouter(&mut layouter),
+                        );
+                        edited |= response.changed();
+                    });
+
+                for diagnostic in diagnostics {
+                    let text = match diagnostic.line {
+                        Some(line) => format!("line {}: {}", line, diagnostic.message),
+                        None => diagnostic.message.clone(),
+                    };
+                    ui.colored_label(egui::Color32::LIGHT_RED, text);
+                }
+            });
+        self.editor_open = editor_open;
+        if edited {
+            self.diagnose_editor();
+        }
+
+        if let Some(error) = &self.shader_error {
+            let mut open = true;
+            egui::Window::new("Shader Error")
+                .open(&mut open)
+                .resizable(true)
+                .default_width(520.0)
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-5.0, -5.0])
+                .show(&self.platform.context(), |ui| {
+                    ui.label("The last shader that built keeps running.");
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.monospace(error);
+                    });
+                });
+            if !open {
+                self.shader_error = None;
+            }
+        }
     }
 
     pub fn render(
@@ -246,7 +742,7 @@ impl Gui {
         self.ui();
 
         // End the UI frame. We could now handle the output and draw the UI with the backend.
-        let full_output = self.platform.end_frame(Some(&window));
+        let full_output = self.platform.end_frame(Some(window));
         let paint_jobs = self.platform.context().tessellate(full_output.shapes);
 
         //render UI on wgpu backend
@@ -262,14 +758,14 @@ impl Gui {
         };
         self.tdelta = full_output.textures_delta;
         self.egui_rpass
-            .add_textures(&device, &queue, &self.tdelta)
+            .add_textures(device, queue, &self.tdelta)
             .expect("add texture ok");
         self.egui_rpass
-            .update_buffers(&device, &queue, &paint_jobs, &screen_descriptor);
+            .update_buffers(device, queue, &paint_jobs, &screen_descriptor);
 
         // Record all render passes.
         self.egui_rpass
-            .execute(&mut encoder, &view, &paint_jobs, &screen_descriptor, None)
+            .execute(&mut encoder, view, &paint_jobs, &screen_descriptor, None)
             .unwrap();
         // Submit the commands.
         queue.submit(std::iter::once(encoder.finish()));
@@ -285,4 +781,163 @@ impl Gui {
     pub fn gen_params(&self) -> Params {
         self.inner_params.clone()
     }
+
+    /// the parameters as set in the panel, without cloning them every frame
+    pub fn params(&self) -> &Params {
+        &self.inner_params
+    }
+
+    /// a shader in the shader folder was saved: it is offered in the shader list, and the
+    /// editor picks up its source if it is the selected one
+    pub fn shader_saved(&mut self, path: &Path, source: &str) {
+        let name = self
+            .shaders
+            .insert_file(path, source.to_string())
+            .name
+            .clone();
+        if self.inner_params.shader_name == name {
+            self.inner_params.shader_buffer = source.to_string();
+            self.shader_params = self.inner_params.shader_params().unwrap_or_default();
+        }
+    }
+
+    /// the editor's source now runs, so it is also what a restart builds
+    pub fn shader_applied(&mut self) {
+        self.inner_params.shader_buffer = self.editor_source.clone();
+        self.shader_params = self.inner_params.shader_params().unwrap_or_default();
+        self.shader_error = None;
+    }
+
+    /// validates the editor's source as the selected shader
+    fn diagnose_editor(&mut self) {
+        let mut params = self.inner_params.clone();
+        params.shader_buffer = self.editor_source.clone();
+        self.editor_diagnostics = editor::diagnose(&params);
+    }
+
+    /// shows `params` in the editor, e.g. after a snapshot was resumed
+    pub fn set_params(&mut self, params: Params) {
+        self.selected_shader_file = params.shader_name.clone();
+        self.shader_params = params.shader_params().unwrap_or_default();
+        self.inner_params = params;
+    }
+}
+
+/// widest the heatmap grows, in points, however few types there are
+const MATRIX_HEATMAP_SIZE: f32 = 240.0;
+/// largest heatmap cell, so a couple of types do not fill the panel
+const MATRIX_CELL_SIZE: f32 = 32.0;
+/// change of a matrix entry per point dragged
+const MATRIX_DRAG_SPEED: f32 = 0.01;
+
+/// the matrix as it is being edited, which has `num_types` rows only once it is regenerated
+fn shown_matrix_rows(params: &Params) -> Vec<Vec<f32>> {
+    let n = ((params.attraction_matrix.len() / 4) as f32).sqrt() as usize;
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| params.attraction_matrix[(i * n + j) * 4])
+                .collect()
+        })
+        .collect()
+}
+
+/// green for attraction and red for repulsion, brighter the stronger
+fn heat_colour(value: f32) -> egui::Color32 {
+    let level = (value.abs().min(1.0) * 255.0) as u8;
+    if value >= 0.0 {
+        egui::Color32::from_rgb(0, level, level / 3)
+    } else {
+        egui::Color32::from_rgb(level, 0, level / 4)
+    }
+}
+
+/// the attraction matrix as a grid of coloured cells, row `i` being what type `i` feels.
+/// Dragging a cell up or down changes it, clicking selects it so its exact value can be typed
+fn matrix_heatmap(ui: &mut egui::Ui, params: &mut Params, selected: &mut Option<(usize, usize)>) {
+    let mut rows = shown_matrix_rows(params);
+    let n = rows.len();
+    if n == 0 {
+        return;
+    }
+    let cell = (MATRIX_HEATMAP_SIZE / n as f32).min(MATRIX_CELL_SIZE);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(cell, cell) * n as f32, egui::Sense::hover());
+    let mut changed = false;
+    for (i, row) in rows.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            let min = rect.min + egui::vec2(j as f32, i as f32) * cell;
+            let cell_rect = egui::Rect::from_min_size(min, egui::vec2(cell, cell));
+            let response = ui
+                .interact(
+                    cell_rect,
+                    ui.id().with(("matrix", i, j)),
+                    egui::Sense::click_and_drag(),
+                )
+                .on_hover_text(format!("type {} from type {}: {:.3}", i, j, value));
+            if response.clicked() || response.drag_started() {
+                *selected = Some((i, j));
+            }
+            if response.dragged() {
+                *value = (*value - response.drag_delta().y * MATRIX_DRAG_SPEED).clamp(-1.0, 1.0);
+                changed = true;
+            }
+            let painter = ui.painter();
+            painter.rect_filled(cell_rect.shrink(0.5), 0.0, heat_colour(*value));
+            if *selected == Some((i, j)) {
+                painter.rect_stroke(cell_rect, 0.0, ui.visuals().selection.stroke);
+            }
+        }
+    }
+
+    if let Some((i, j)) = selected.filter(|&(i, j)| i < n && j < n) {
+        ui.horizontal(|ui| {
+            ui.label(format!("type {} from type {}:", i, j));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut rows[i][j])
+                        .clamp_range(-1.0..=1.0)
+                        .speed(MATRIX_DRAG_SPEED),
+                )
+                .changed();
+        });
+    }
+    if changed {
+        params.set_matrix_rows(&rows);
+    }
+}
+
+/// why eden cannot bind what the shader in `params` needs, empty if it can
+fn shader_status(params: &Params) -> String {
+    match Simulation::check_shader(params) {
+        Ok(_) => String::new(),
+        Err(reason) => reason,
+    }
+}
+
+/// colours `source` by token and underlines what `diagnostics` point at
+fn highlight(ui: &egui::Ui, source: &str, diagnostics: &[Diagnostic]) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let mut job = egui::text::LayoutJob::default();
+    for (kind, range) in editor::tokenize(source) {
+        let color = match kind {
+            TokenKind::Comment => egui::Color32::from_rgb(0x7f, 0x84, 0x8e),
+            TokenKind::Keyword => egui::Color32::from_rgb(0xc6, 0x78, 0xdd),
+            TokenKind::Type => egui::Color32::from_rgb(0x56, 0xb6, 0xc2),
+            TokenKind::Attribute => egui::Color32::from_rgb(0xe5, 0xc0, 0x7b),
+            TokenKind::Number => egui::Color32::from_rgb(0xd1, 0x9a, 0x66),
+            TokenKind::Identifier | TokenKind::Punctuation | TokenKind::Whitespace => {
+                ui.visuals().text_color()
+            }
+        };
+        let mut format = egui::TextFormat::simple(font_id.clone(), color);
+        let marked = diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.span.as_ref())
+            .any(|span| span.start < range.end && range.start < span.end.max(span.start + 1));
+        if marked {
+            format.underline = egui::Stroke::new(1.5, egui::Color32::RED);
+        }
+        job.append(&source[range], 0.0, format);
+    }
+    job
 }
@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use eden::{
+    simulation::request_headless_device,
+    snapshot::Snapshot,
+    trajectory::{TrajectoryFormat, TrajectoryRecorder},
+    Simulation,
+};
+
+use super::cli::Start;
+
+fn init(start: Start) -> Result<(Simulation, wgpu::Device, wgpu::Queue), String> {
+    let (adapter, device, queue) = pollster::block_on(request_headless_device())
+        .ok_or_else(|| "no compute-capable GPU adapter found".to_string())?;
+
+    let info = adapter.get_info();
+    println!("Using {} ({:?})", info.name, info.backend);
+
+    let sim = start.into_simulation(&device, &queue)?;
+    Ok((sim, device, queue))
+}
+
+/// steps `sim` and blocks until the GPU has caught up
+fn run_steps(sim: &mut Simulation, device: &wgpu::Device, queue: &wgpu::Queue, steps: u32) {
+    sim.step(device, queue, steps);
+    device.poll(wgpu::Maintain::Wait);
+}
+
+pub fn headless(
+    start: Start,
+    steps: u32,
+    save_snapshot: Option<&Path>,
+    trajectory: Option<(&Path, u32)>,
+) -> Result<(), String> {
+    let (mut sim, device, queue) = init(start)?;
+
+    let mut recorder = match trajectory {
+        Some((path, every)) => {
+            let format = TrajectoryFormat::from_path(path).ok_or_else(|| {
+                format!(
+                    "unknown trajectory format for {}, expected .csv, .xyz or .npy",
+                    path.display()
+                )
+            })?;
+            let recorder = TrajectoryRecorder::create(path, format, every, &sim)
+                .map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+            Some(recorder)
+        }
+        None => None,
+    };
+    let write_error = |e: std::io::Error| format!("could not write trajectory: {}", e);
+
+    let timer = Instant::now();
+    match &mut recorder {
+        Some(recorder) => {
+            recorder.record(&sim, &device, &queue);
+            for _ in 0..steps {
+                sim.step(&device, &queue, 1);
+                recorder.record(&sim, &device, &queue);
+                device.poll(wgpu::Maintain::Poll);
+                recorder.drain().map_err(write_error)?;
+            }
+            device.poll(wgpu::Maintain::Wait);
+        }
+        None => run_steps(&mut sim, &device, &queue, steps),
+    }
+    let elapsed = timer.elapsed();
+
+    println!(
+        "{} steps of {} particles in {:.3}s",
+        sim.step_count,
+        sim.params.num_particles,
+        elapsed.as_secs_f64()
+    );
+    let clock = sim.read_clock(&device, &queue);
+    println!("simulated time {} (last dt {})", clock.time, clock.dt);
+
+    if let Some(recorder) = recorder {
+        let path = recorder.path.clone();
+        let frames = recorder.finish(&device).map_err(write_error)?;
+        println!("wrote {} trajectory frames to {}", frames, path.display());
+    }
+
+    if let Some(path) = save_snapshot {
+        Snapshot::capture(&sim, None, &device, &queue)
+            .save(path)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+        println!(
+            "saved snapshot at step {} to {}",
+            sim.step_count,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+pub fn bench(start: Start, steps: u32, warmup: u32) -> Result<(), String> {
+    let (mut sim, device, queue) = init(start)?;
+
+    run_steps(&mut sim, &device, &queue, warmup);
+
+    let timer = Instant::now();
+    run_steps(&mut sim, &device, &queue, steps);
+    let elapsed = timer.elapsed().as_secs_f64();
+
+    let ms_per_step = elapsed * 1000.0 / steps.max(1) as f64;
+    println!("particles:  {}", sim.params.num_particles);
+    println!("steps:      {} (+{} warmup)", steps, warmup);
+    println!("total:      {:.3}s", elapsed);
+    println!("per step:   {:.4}ms", ms_per_step);
+    println!("steps/s:    {:.1}", 1000.0 / ms_per_step);
+    Ok(())
+}
+
+pub fn export(start: Start, steps: u32, output: &Path) -> Result<(), String> {
+    let (mut sim, device, queue) = init(start)?;
+
+    run_steps(&mut sim, &device, &queue, steps);
+    let particles = sim.read_particles(&device, &queue);
+
+    let write = || -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(output)?);
+        writeln!(out, "x,y,vx,vy,mass,kind")?;
+        for p in &particles {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                p.pos[0], p.pos[1], p.vel[0], p.vel[1], p.mass, p.kind
+            )?;
+        }
+        out.flush()
+    };
+    write().map_err(|e| format!("could not write {}: {}", output.display(), e))?;
+
+    println!(
+        "wrote {} particles after {} steps to {}",
+        particles.len(),
+        sim.step_count,
+        output.display()
+    );
+    Ok(())
+}
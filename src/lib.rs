@@ -1,6 +1,26 @@
-use eframe::epaint::CircleShape;
+use std::{collections::BTreeMap, fmt};
 
-use rand::prelude::*;
+use rand::{prelude::*, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use matrix::MatrixGenerator;
+
+pub mod editor;
+pub mod force;
+pub mod matrix;
+pub mod mesh;
+pub mod palette;
+pub mod reference;
+pub mod reflect;
+pub mod scenario;
+pub mod shader_params;
+pub mod shader_registry;
+pub mod simulation;
+pub mod snapshot;
+pub mod trajectory;
+pub mod watch;
+
+pub use simulation::Simulation;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Camera {
@@ -14,14 +34,26 @@ pub const SAMPLE_COUNT: u32 = 4;
 pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb; // wgpu::TextureFormat::Rgba8UnormSrgb;
 pub const CIRCLE_RES: u32 = 16;
 pub const DEFAULT_COMPUTE_SHADER: &str = include_str!("shaders/experimental.wgsl");
+pub const DEFAULT_COMPUTE_SHADER_NAME: &str = "experimental.wgsl";
+/// steps between two Morton reorders of the particle buffers
+pub const DEFAULT_SORT_EVERY: u32 = 16;
+/// Barnes-Hut opening angle, the usual trade-off between speed and accuracy
+pub const DEFAULT_THETA: f32 = 0.5;
+/// bounds of the adaptive dt
+pub const DEFAULT_DT_MIN: f32 = 1e-6;
+pub const DEFAULT_DT_MAX: f32 = 0.01;
+/// fraction of a particle radius a particle may move in one adaptive step
+pub const DEFAULT_COURANT: f32 = 0.2;
+/// mixed into the seed for the matrix so it does not share a stream with the spawn positions
+const MATRIX_SEED_STREAM: u64 = 0x9e37_79b9_7f4a_7c15;
 
 impl Camera {
     pub fn new(x: f32, y: f32, zoom: f32, aspect_ratio: f32) -> Self {
         Camera {
-            x: x,
-            y: y,
-            zoom: zoom,
-            aspect_ratio: aspect_ratio,
+            x,
+            y,
+            zoom,
+            aspect_ratio,
         }
     }
 
@@ -30,6 +62,232 @@ impl Camera {
     }
 }
 
+/// where particles are placed when a simulation starts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SpawnLayout {
+    /// uniformly over the whole world
+    #[default]
+    Uniform,
+    /// uniformly inside a disk centred on the world
+    Disk { radius: f32 },
+    /// on a square lattice filling the world
+    Grid,
+}
+
+/// what happens to particles at the edge of the world
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoundaryMode {
+    /// walls that mirror the velocity of particles hitting them
+    #[default]
+    Reflect = 0,
+    /// the world wraps around, distances use the nearest periodic image
+    Periodic = 1,
+    /// walls that stop particles dead
+    Absorb = 2,
+    /// no walls; particles past the edge share the border cells of the neighbour search
+    Open = 3,
+}
+
+impl BoundaryMode {
+    pub const ALL: [BoundaryMode; 4] = [
+        BoundaryMode::Reflect,
+        BoundaryMode::Periodic,
+        BoundaryMode::Absorb,
+        BoundaryMode::Open,
+    ];
+}
+
+impl std::str::FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BoundaryMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown boundary mode `{}`, expected reflect, periodic, absorb or open",
+                    s
+                )
+            })
+    }
+}
+
+/// how the force on each particle is evaluated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// the force module in `shader_buffer` summed over every pair, O(N²) without a cell list
+    BruteForce,
+    /// the compute shader in `shader_buffer`, walking the cell list for short-range forces
+    #[default]
+    UniformGrid,
+    /// softened gravity from a quadtree over the cell list, see barneshut.wgsl
+    BarnesHut,
+    /// gravity solved with an FFT on a mesh of one node per cell, see particlemesh.wgsl
+    ParticleMesh,
+}
+
+impl Algorithm {
+    /// why this algorithm cannot run on a grid of `num_grids_side` cells per side, if it cannot
+    pub fn check_grids_side(&self, num_grids_side: u32) -> Result<(), String> {
+        match self {
+            Algorithm::BruteForce | Algorithm::UniformGrid => Ok(()),
+            Algorithm::BarnesHut if num_grids_side > simulation::MAX_TREE_GRIDS_SIDE => {
+                Err(format!(
+                    "must be at most {} for barnes_hut, got {}",
+                    simulation::MAX_TREE_GRIDS_SIDE,
+                    num_grids_side
+                ))
+            }
+            Algorithm::BarnesHut => Ok(()),
+            Algorithm::ParticleMesh
+                if !num_grids_side.is_power_of_two()
+                    || !(4..=simulation::MAX_MESH_GRIDS_SIDE).contains(&num_grids_side) =>
+            {
+                Err(format!(
+                    "must be a power of two between 4 and {} for particle_mesh, got {}",
+                    simulation::MAX_MESH_GRIDS_SIDE,
+                    num_grids_side
+                ))
+            }
+            Algorithm::ParticleMesh => Ok(()),
+        }
+    }
+
+    /// whether the force pass is `shader_buffer` rather than a built-in solver
+    pub fn uses_shader_buffer(&self) -> bool {
+        matches!(self, Algorithm::BruteForce | Algorithm::UniformGrid)
+    }
+
+    /// whether each force evaluation first buckets the particles into cells
+    pub fn uses_cell_list(&self) -> bool {
+        *self != Algorithm::BruteForce
+    }
+
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::BruteForce,
+        Algorithm::UniformGrid,
+        Algorithm::BarnesHut,
+        Algorithm::ParticleMesh,
+    ];
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown algorithm `{}`, expected brute_force, uniform_grid, barnes_hut or particle_mesh",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::BruteForce => write!(f, "brute_force"),
+            Algorithm::UniformGrid => write!(f, "uniform_grid"),
+            Algorithm::BarnesHut => write!(f, "barnes_hut"),
+            Algorithm::ParticleMesh => write!(f, "particle_mesh"),
+        }
+    }
+}
+
+/// how each step turns accelerations into new positions and velocities, see integrate.wgsl
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    /// semi-implicit Euler: kick, then drift with the new velocity
+    Euler,
+    /// drift with the mean of the old and new velocity, what the shaders always did
+    #[default]
+    Trapezoidal,
+    /// kick-drift-kick, symplectic, two force evaluations per step
+    VelocityVerlet,
+    /// drift-kick-drift, symplectic, one force evaluation at the half step
+    Leapfrog,
+    /// classic fourth-order Runge-Kutta, four force evaluations per step
+    Rk4,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 5] = [
+        Integrator::Euler,
+        Integrator::Trapezoidal,
+        Integrator::VelocityVerlet,
+        Integrator::Leapfrog,
+        Integrator::Rk4,
+    ];
+
+    /// force passes a step runs with this integrator
+    pub fn force_evaluations(&self) -> u32 {
+        match self {
+            Integrator::Euler | Integrator::Trapezoidal | Integrator::Leapfrog => 1,
+            Integrator::VelocityVerlet => 2,
+            Integrator::Rk4 => 4,
+        }
+    }
+
+    /// symplectic schemes keep the energy error bounded instead of letting it drift
+    pub fn is_symplectic(&self) -> bool {
+        matches!(
+            self,
+            Integrator::Euler | Integrator::VelocityVerlet | Integrator::Leapfrog
+        )
+    }
+}
+
+impl std::str::FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Integrator::ALL
+            .iter()
+            .copied()
+            .find(|integrator| integrator.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown integrator `{}`, expected euler, trapezoidal, velocity_verlet, leapfrog or rk4",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for Integrator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integrator::Euler => write!(f, "euler"),
+            Integrator::Trapezoidal => write!(f, "trapezoidal"),
+            Integrator::VelocityVerlet => write!(f, "velocity_verlet"),
+            Integrator::Leapfrog => write!(f, "leapfrog"),
+            Integrator::Rk4 => write!(f, "rk4"),
+        }
+    }
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundaryMode::Reflect => write!(f, "reflect"),
+            BoundaryMode::Periodic => write!(f, "periodic"),
+            BoundaryMode::Absorb => write!(f, "absorb"),
+            BoundaryMode::Open => write!(f, "open"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 
 pub struct Params {
@@ -38,6 +296,10 @@ pub struct Params {
     pub num_particles: u32,
     pub world_size: f32,
     pub shader_buffer: String,
+    pub shader_name: String,
+    /// values of the `// @param` tunables of `shader_buffer` by name, missing ones take
+    /// the declared default
+    pub shader_values: BTreeMap<String, f32>,
     pub well_depth: f32,
     pub attract_coeff: f32,
     pub repulse_coeff: f32,
@@ -46,25 +308,46 @@ pub struct Params {
     pub num_grids_side: u32,
     pub play: bool,
     pub particle_radius: f32,
+    pub seed: u64,
+    pub spawn: SpawnLayout,
+    pub boundary: BoundaryMode,
+    /// build the neighbour lists in a fixed order so a seed replays bit for bit
+    pub deterministic: bool,
+    /// reorder the particle buffers along a Morton curve every `sort_every` steps, 0 disables it
+    pub sort_every: u32,
+    pub algorithm: Algorithm,
+    /// Barnes-Hut opening angle: a node is used whole once its size over its distance drops below this
+    pub theta: f32,
+    /// gravitational constant of the Barnes-Hut and particle-mesh solvers, softened by `particle_radius`
+    pub gravity: f32,
+    pub integrator: Integrator,
+    /// pick every step's dt from the fastest and most accelerated particle instead of using `dt`
+    pub adaptive_dt: bool,
+    /// bounds of the adaptive dt
+    pub dt_min: f32,
+    pub dt_max: f32,
+    /// CFL-like safety factor of the adaptive dt, in particle radii moved per step
+    pub courant: f32,
+    /// sRGB colour of each particle type; types past its end take the classic palette's
+    pub type_colours: Vec<[f32; 3]>,
 }
 
 impl Params {
+    /// default parameters with a fresh random seed
     pub fn new() -> Self {
-        let mut attraction_matrix: Vec<f32> = Vec::new();
-        let mut rng = rand::thread_rng();
-        let mut unif = || (rng.gen::<f32>() * 2f32 - 1f32);
-        let num_types: u32 = 1;
-        for i in 0..num_types.pow(2) {
-            attraction_matrix.extend_from_slice(&[unif(), 0.0, 0.0, 0.0]);
-        }
+        Self::with_seed(Self::random_seed())
+    }
 
-        println!("{:?}", attraction_matrix);
-        Params {
-            num_types: num_types,
-            attraction_matrix: attraction_matrix,
+    /// default parameters whose attraction matrix and spawn positions all follow from `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        let mut params = Params {
+            num_types: 1,
+            attraction_matrix: Vec::new(),
             dt: 0.001, //0.001,
             num_particles: 100,
             shader_buffer: DEFAULT_COMPUTE_SHADER.to_string(),
+            shader_name: DEFAULT_COMPUTE_SHADER_NAME.to_string(),
+            shader_values: BTreeMap::new(),
             world_size: 50.0,
             well_depth: 500.0,
             attract_coeff: 1.0,
@@ -73,22 +356,50 @@ impl Params {
             num_grids_side: 50,
             play: false,
             particle_radius: 1.0,
-        }
+            seed,
+            spawn: SpawnLayout::Uniform,
+            boundary: BoundaryMode::Reflect,
+            deterministic: false,
+            sort_every: DEFAULT_SORT_EVERY,
+            algorithm: Algorithm::UniformGrid,
+            theta: DEFAULT_THETA,
+            gravity: 1.0,
+            integrator: Integrator::Trapezoidal,
+            adaptive_dt: false,
+            dt_min: DEFAULT_DT_MIN,
+            dt_max: DEFAULT_DT_MAX,
+            courant: DEFAULT_COURANT,
+            type_colours: Vec::new(),
+        };
+        params.randomize_matrix();
+        params
     }
 
-    pub fn randomize_matrix(&mut self) {
-        let mut attraction_matrix: Vec<f32> = Vec::new();
-        let mut rng = rand::thread_rng();
-        let mut unif = || (rng.gen::<f32>() * 2f32 - 1f32);
+    /// kept small so it is easy to read back and fits in a TOML integer
+    pub fn random_seed() -> u64 {
+        rand::thread_rng().gen::<u32>() as u64
+    }
 
-        for i in 0..self.num_types.pow(2) {
-            attraction_matrix.extend_from_slice(&[unif(), 0.0, 0.0, 0.0]);
-        }
+    /// picks a new random seed; the matrix is left alone until `randomize_matrix` is called
+    pub fn reseed(&mut self) {
+        self.seed = Self::random_seed();
+    }
 
-        self.attraction_matrix = attraction_matrix;
+    /// regenerates the attraction matrix from `seed`, so the same seed and
+    /// number of types always give the same matrix
+    pub fn randomize_matrix(&mut self) {
+        self.generate_matrix(MatrixGenerator::Random);
+    }
+
+    /// regenerates the attraction matrix with `generator`, drawing from `seed` like
+    /// [`Params::randomize_matrix`]
+    pub fn generate_matrix(&mut self, generator: MatrixGenerator) {
+        let mut rng = StdRng::seed_from_u64(self.seed ^ MATRIX_SEED_STREAM);
+        let rows = generator.generate(self.num_types as usize, &mut rng);
+        self.set_matrix_rows(&rows);
     }
 
-    pub fn to_slice(&self) -> [f32; 7] {
+    pub fn to_slice(&self) -> [f32; 8] {
         [
             self.world_size,
             self.dt,
@@ -97,62 +408,231 @@ impl Params {
             self.repulse_coeff,
             self.friction_coeff,
             (self.world_size / self.num_grids_side as f32),
+            self.boundary as u32 as f32,
         ]
     }
 
+    /// contents of the `DtBounds` uniform of timestep.wgsl
+    pub fn dt_bounds_slice(&self) -> [f32; 4] {
+        [self.dt_min, self.dt_max, self.courant, self.particle_radius]
+    }
+
+    /// the fields that differ from `other` and only take effect once the simulation is
+    /// rebuilt from `other`; the rest are written into the running one by
+    /// [`Simulation::update_params`]. `seed` and `spawn` only shape the next run
+    pub fn structural_changes(&self, other: &Params) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        let mut check = |field, differs: bool| {
+            if differs {
+                changed.push(field);
+            }
+        };
+        check("num_particles", self.num_particles != other.num_particles);
+        check("num_types", self.num_types != other.num_types);
+        check(
+            "num_grids_side",
+            self.num_grids_side != other.num_grids_side,
+        );
+        check("world_size", self.world_size != other.world_size);
+        check("shader", self.shader_buffer != other.shader_buffer);
+        check("algorithm", self.algorithm != other.algorithm);
+        // the particle mesh is padded to twice the grid unless it wraps around
+        check(
+            "boundary",
+            self.algorithm == Algorithm::ParticleMesh
+                && mesh::mesh_side(self) != mesh::mesh_side(other),
+        );
+        check("integrator", self.integrator != other.integrator);
+        check("adaptive_dt", self.adaptive_dt != other.adaptive_dt);
+        check(
+            "attraction_matrix",
+            self.attraction_matrix.len() != other.attraction_matrix.len(),
+        );
+        changed
+    }
+
+    /// the `// @param` tunables `shader_buffer` declares
+    pub fn shader_params(&self) -> Result<Vec<shader_params::ShaderParam>, String> {
+        shader_params::parse(&self.shader_buffer)
+    }
+
+    /// contents of the `shader_params` uniform, see [`shader_params::pack`]
+    pub fn shader_param_slice(&self) -> Vec<f32> {
+        shader_params::pack(
+            &self.shader_params().unwrap_or_default(),
+            &self.shader_values,
+        )
+    }
+
+    /// the field and the reason when the adaptive dt settings cannot be used
+    pub fn check_dt_bounds(&self) -> Result<(), (&'static str, String)> {
+        if self.dt_min.is_nan() || self.dt_min <= 0.0 {
+            return Err(("dt_min", format!("must be positive, got {}", self.dt_min)));
+        }
+        if !self.dt_max.is_finite() || self.dt_max < self.dt_min {
+            return Err((
+                "dt_max",
+                format!(
+                    "must be at least dt_min ({}), got {}",
+                    self.dt_min, self.dt_max
+                ),
+            ));
+        }
+        if !self.courant.is_finite() || self.courant <= 0.0 {
+            return Err(("courant", format!("must be positive, got {}", self.courant)));
+        }
+        Ok(())
+    }
+
+    /// the attraction matrix as `num_types` rows, without the vec4 padding
+    pub fn matrix_rows(&self) -> Vec<Vec<f32>> {
+        let n = self.num_types as usize;
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| self.attraction_matrix[(i * n + j) * 4])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// replaces the attraction matrix from square rows, padding each entry out to a vec4
+    pub fn set_matrix_rows(&mut self, rows: &[Vec<f32>]) {
+        self.attraction_matrix = rows
+            .iter()
+            .flatten()
+            .flat_map(|&value| [value, 0.0, 0.0, 0.0])
+            .collect();
+    }
+
+    /// sRGB colour particles of type `index` are drawn in
+    pub fn type_colour(&self, index: usize) -> [f32; 3] {
+        self.type_colours
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| palette::Palette::Classic.colour(index, self.num_types as usize))
+    }
+
+    /// contents of the `TypeColours` uniform of draw.wgsl: the number of types in a vec4,
+    /// then the linear colour of each of the first [`palette::MAX_TYPE_COLOURS`] types
+    pub fn colour_slice(&self) -> Vec<f32> {
+        let mut slice = vec![self.num_types as f32, 0.0, 0.0, 0.0];
+        for index in 0..palette::MAX_TYPE_COLOURS {
+            let colour = self.type_colour(index).map(palette::srgb_to_linear);
+            slice.extend_from_slice(&[colour[0], colour[1], colour[2], 1.0]);
+        }
+        slice
+    }
+
     pub fn attraction_matrix_slice(&self) -> &[f32] {
         self.attraction_matrix.as_slice()
     }
+
+    /// initial particles, the same for every run with the same seed
+    pub fn spawn_particles(&self) -> Vec<Particle> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let side = (self.num_particles as f32).sqrt().ceil().max(1.0) as u32;
+        let centre = self.world_size / 2.0;
+
+        (0..self.num_particles)
+            .map(|i| {
+                let mut particle = Particle::from_rng(self, &mut rng);
+                particle.id = i;
+                match self.spawn {
+                    SpawnLayout::Uniform => {}
+                    SpawnLayout::Disk { radius } => {
+                        // sqrt keeps the density uniform over the disk
+                        let r = radius * rng.gen::<f32>().sqrt();
+                        let theta = rng.gen::<f32>() * std::f32::consts::TAU;
+                        particle.pos = [centre + r * theta.cos(), centre + r * theta.sin()];
+                    }
+                    SpawnLayout::Grid => {
+                        let spacing = self.world_size / side as f32;
+                        particle.pos = [
+                            ((i % side) as f32 + 0.5) * spacing,
+                            ((i / side) as f32 + 0.5) * spacing,
+                        ];
+                    }
+                }
+                particle
+            })
+            .collect()
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Particle {
-    pos: (f32, f32),
-    vel: (f32, f32),
+    pub pos: [f32; 2],
+    pub vel: [f32; 2],
     pub mass: f32,
-    kind: f32,
-    pub fptr: f32,
+    pub kind: f32,
+    /// index the particle was spawned with, kept when the simulation reorders its buffers
+    pub id: u32,
     pub bptr: f32,
     pub debug: f32,
+    // WGSL rounds the struct up to its 8 byte alignment, so the array stride is 40 bytes
+    _pad: f32,
 }
 
 impl Particle {
     pub fn to_slice(&self) -> [f32; 9] {
         [
-            self.pos.0, self.pos.1, self.vel.0, self.vel.1, self.mass, self.kind, self.fptr,
-            self.bptr, self.debug,
+            self.pos[0],
+            self.pos[1],
+            self.vel[0],
+            self.vel[1],
+            self.mass,
+            self.kind,
+            self.id as f32,
+            self.bptr,
+            self.debug,
         ]
     }
-    pub fn new_random(params: &Params) -> Self {
-        let mut rng = rand::thread_rng();
-        let mut unif = || (rng.gen::<f32>()) * params.world_size;
+    pub fn from_rng<R: Rng + ?Sized>(params: &Params, rng: &mut R) -> Self {
         let max_types: f32 = f32::sqrt(params.attraction_matrix.len() as f32 / 4.0);
-        let mut rng = rand::thread_rng();
+        let pos = [
+            rng.gen::<f32>() * params.world_size,
+            rng.gen::<f32>() * params.world_size,
+        ];
 
         Self {
-            pos: (unif(), unif()),
-            vel: (0.0, 0.0),
+            pos,
+            vel: [0.0, 0.0],
             mass: 1.0,
-            kind: (rng.gen_range(0..max_types as u32) as f32) / (max_types as f32),
+            kind: (rng.gen_range(0..max_types as u32) as f32) / max_types,
+            id: 0,
             bptr: -1.0,
-            fptr: -1.0,
-            debug: -1.0
+            debug: 1.0,
+            _pad: 0.0,
         }
     }
     pub fn new() -> Self {
         Self {
-            pos: (0.0, 0.0),
-            vel: (0.0, 0.0),
+            pos: [0.0, 0.0],
+            vel: [0.0, 0.0],
             mass: 100.0,
             kind: 0.01,
-            fptr: -1.0,
+            id: 0,
             bptr: -1.0,
-            debug: 0.0
+            debug: 1.0,
+            _pad: 0.0,
         }
     }
 }
 
+impl Default for Particle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn generate_circle(radius: f32) -> [f32; (CIRCLE_RES * 8) as usize] {
     use std::convert::TryInto;
     use std::f64::consts::PI;
@@ -170,9 +650,8 @@ pub fn generate_circle(radius: f32) -> [f32; (CIRCLE_RES * 8) as usize] {
         coords.push(radius * ((2.0 * PI * (i + 1) as f64 / CIRCLE_RES as f64) as f32).sin());
     }
 
-    for i in 0..CIRCLE_RES {
-        coords.push(0.01);
-        coords.push(0.0)
+    for _ in 0..CIRCLE_RES {
+        coords.extend_from_slice(&[0.01, 0.0]);
     }
 
     coords.try_into().unwrap_or_else(|v: Vec<f32>| {
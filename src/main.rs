@@ -1,8 +1,51 @@
+mod capture;
+mod cli;
 mod gui;
+mod headless;
 mod sim;
 // mod stage;
 mod state;
 
+use clap::Parser;
+
+use cli::{Cli, Command};
+
 fn main() {
-    sim::run("particles");
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        None => {
+            let sim = cli::SimArgs::default();
+            sim.start()
+                .map(|start| sim::run("particles", start, sim.shader_registry(), 1920, 1080))
+        }
+        Some(Command::Run { sim, width, height }) => sim
+            .start()
+            .map(|start| sim::run("particles", start, sim.shader_registry(), width, height)),
+        Some(Command::Headless {
+            sim,
+            steps,
+            save_snapshot,
+            trajectory,
+            every,
+        }) => sim.start().and_then(|start| {
+            headless::headless(
+                start,
+                steps,
+                save_snapshot.as_deref(),
+                trajectory.as_deref().map(|path| (path, every)),
+            )
+        }),
+        Some(Command::Bench { sim, steps, warmup }) => sim
+            .start()
+            .and_then(|start| headless::bench(start, steps, warmup)),
+        Some(Command::Export { sim, steps, output }) => sim
+            .start()
+            .and_then(|start| headless::export(start, steps, &output)),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,196 @@
+//! Ways to fill the attraction matrix besides uniform noise, the edits the
+//! matrix editor offers on the whole matrix, and the plain-text form it is
+//! copied and pasted as.
+//!
+//! Everything here works on square rows, as returned by [`Params::matrix_rows`]
+//! and taken by [`Params::set_matrix_rows`].
+//!
+//! [`Params::matrix_rows`]: crate::Params::matrix_rows
+//! [`Params::set_matrix_rows`]: crate::Params::set_matrix_rows
+
+use std::fmt;
+
+use rand::Rng;
+
+/// chance of an entry being non-zero in a [`MatrixGenerator::Sparse`] matrix
+pub const SPARSE_DENSITY: f64 = 0.25;
+/// standard deviation of the entries of a [`MatrixGenerator::Gaussian`] matrix
+pub const GAUSSIAN_SIGMA: f32 = 0.4;
+/// how strongly each type of a [`MatrixGenerator::Snakes`] matrix chases the next one
+const SNAKE_CHASE: f32 = 0.5;
+
+/// a recipe for an attraction matrix, all entries within -1..=1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatrixGenerator {
+    /// every entry uniform, what [`Params::randomize_matrix`](crate::Params::randomize_matrix) does
+    #[default]
+    Random,
+    /// uniform with `m[i][j] == m[j][i]`, so every pair pulls on each other equally
+    Symmetric,
+    /// uniform with `m[i][j] == -m[j][i]` and a zero diagonal: one chases, the other flees
+    Antisymmetric,
+    /// a cyclic chain: each type clumps with its own and chases the next, the last chasing the first
+    Snakes,
+    /// types in groups of about √n that attract within the group and ignore the others
+    BlockDiagonal,
+    /// uniform entries, each kept with probability [`SPARSE_DENSITY`] and zero otherwise
+    Sparse,
+    /// normally distributed entries with [`GAUSSIAN_SIGMA`], clamped to -1..=1
+    Gaussian,
+}
+
+impl MatrixGenerator {
+    pub const ALL: [MatrixGenerator; 7] = [
+        MatrixGenerator::Random,
+        MatrixGenerator::Symmetric,
+        MatrixGenerator::Antisymmetric,
+        MatrixGenerator::Snakes,
+        MatrixGenerator::BlockDiagonal,
+        MatrixGenerator::Sparse,
+        MatrixGenerator::Gaussian,
+    ];
+
+    /// an `n` by `n` matrix drawn from `rng`
+    pub fn generate(&self, n: usize, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+        let mut unif = || rng.gen::<f32>() * 2.0 - 1.0;
+        let mut rows = vec![vec![0.0; n]; n];
+        match self {
+            MatrixGenerator::Random => {
+                for value in rows.iter_mut().flatten() {
+                    *value = unif();
+                }
+            }
+            MatrixGenerator::Symmetric | MatrixGenerator::Antisymmetric => {
+                let sign = if *self == MatrixGenerator::Symmetric {
+                    1.0
+                } else {
+                    -1.0
+                };
+                for (i, row) in rows.iter_mut().enumerate() {
+                    for (j, value) in row.iter_mut().enumerate().skip(i) {
+                        *value = if i == j && sign < 0.0 { 0.0 } else { unif() };
+                    }
+                }
+                // the lower triangle mirrors the upper one
+                let upper = rows.clone();
+                for (i, row) in rows.iter_mut().enumerate() {
+                    for (j, value) in row.iter_mut().enumerate().take(i) {
+                        *value = sign * upper[j][i];
+                    }
+                }
+            }
+            MatrixGenerator::Snakes => {
+                for (i, row) in rows.iter_mut().enumerate() {
+                    row[i] = 1.0;
+                    if n > 1 {
+                        row[(i + 1) % n] = SNAKE_CHASE;
+                    }
+                }
+            }
+            MatrixGenerator::BlockDiagonal => {
+                let block = (n as f32).sqrt().ceil().max(1.0) as usize;
+                for (i, row) in rows.iter_mut().enumerate() {
+                    for (j, value) in row.iter_mut().enumerate() {
+                        if i / block == j / block {
+                            *value = unif().abs();
+                        }
+                    }
+                }
+            }
+            MatrixGenerator::Sparse => {
+                for value in rows.iter_mut().flatten() {
+                    let kept = rng.gen_bool(SPARSE_DENSITY);
+                    let drawn = rng.gen::<f32>() * 2.0 - 1.0;
+                    *value = if kept { drawn } else { 0.0 };
+                }
+            }
+            MatrixGenerator::Gaussian => {
+                for value in rows.iter_mut().flatten() {
+                    // Box-Muller, 1 - u keeps the logarithm finite
+                    let u = 1.0 - rng.gen::<f32>();
+                    let v = rng.gen::<f32>();
+                    let normal = (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos();
+                    *value = (normal * GAUSSIAN_SIGMA).clamp(-1.0, 1.0);
+                }
+            }
+        }
+        rows
+    }
+}
+
+impl fmt::Display for MatrixGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatrixGenerator::Random => "random",
+            MatrixGenerator::Symmetric => "symmetric",
+            MatrixGenerator::Antisymmetric => "antisymmetric",
+            MatrixGenerator::Snakes => "snakes",
+            MatrixGenerator::BlockDiagonal => "block_diagonal",
+            MatrixGenerator::Sparse => "sparse",
+            MatrixGenerator::Gaussian => "gaussian",
+        })
+    }
+}
+
+/// `rows` mirrored along the diagonal, so what `i` felt from `j` is what `j` feels from `i`
+pub fn transpose(rows: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    (0..rows.len())
+        .map(|i| rows.iter().map(|row| row[i]).collect())
+        .collect()
+}
+
+/// attraction turned into repulsion and back
+pub fn negate(rows: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    rows.iter()
+        .map(|row| row.iter().map(|value| -value).collect())
+        .collect()
+}
+
+/// one row per line, entries separated by spaces, which [`parse`] reads back
+pub fn to_text(rows: &[Vec<f32>]) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// a square matrix from one row per line. Entries may be separated by spaces or commas
+/// and brackets are ignored, so a TOML or Python list of rows pastes as well
+pub fn parse(text: &str) -> Result<Vec<Vec<f32>>, String> {
+    let mut rows = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let row = line
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']' | ';'))
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.parse::<f32>() {
+                Ok(value) if value.is_finite() => Ok(value),
+                _ => Err(format!(
+                    "line {}: `{}` is not a finite number",
+                    index + 1,
+                    entry
+                )),
+            })
+            .collect::<Result<Vec<f32>, String>>()?;
+        if !row.is_empty() {
+            rows.push(row);
+        }
+    }
+    if rows.is_empty() {
+        return Err("no matrix entries".to_string());
+    }
+    let n = rows.len();
+    if let Some(row) = rows.iter().position(|row| row.len() != n) {
+        return Err(format!(
+            "{} rows but row {} has {} entries, the matrix must be square",
+            n,
+            row + 1,
+            rows[row].len()
+        ));
+    }
+    Ok(rows)
+}
@@ -0,0 +1,144 @@
+//! CPU side of the particle-mesh solver in particlemesh.wgsl: the mesh size, the
+//! field kernel uploaded when a simulation is built, and an FFT that runs the
+//! same Stockham passes as the shader.
+//!
+//! Mass is deposited onto one node per grid cell with cloud-in-cell weights.
+//! The field `ax + i ay` is then the inverse transform of the mass spectrum
+//! times [`field_kernel`]:
+//!
+//! * with a periodic boundary the kernel solves `∇²φ = 2πGρ` with the
+//!   five-point Laplacian and takes `a = -∇φ` by central differences, so the
+//!   pull between two bodies falls off as `G m / r` like 2D gravity should;
+//! * otherwise the mesh is zero-padded to twice the grid and the kernel is the
+//!   transform of the softened pairwise field `-G r / (r² + ε²)`, which keeps
+//!   the periodic images of the FFT from pulling on the system.
+
+use std::f64::consts::TAU;
+
+use crate::{BoundaryMode, Params};
+
+/// `(re, im)`, the layout of a `vec2<f32>` in the mesh buffers
+pub type Complex = [f32; 2];
+
+/// nodes per side of the mesh the FFT runs over
+pub fn mesh_side(params: &Params) -> u32 {
+    match params.boundary {
+        BoundaryMode::Periodic => params.num_grids_side,
+        _ => 2 * params.num_grids_side,
+    }
+}
+
+/// `(axis, span)` of every pass of a forward or inverse transform, in order: rows then columns
+pub fn fft_passes(side: u32) -> Vec<(u32, u32)> {
+    let spans: Vec<u32> = (0..side.trailing_zeros()).map(|bit| 1 << bit).collect();
+    [0, 1]
+        .iter()
+        .flat_map(|&axis| spans.iter().map(move |&span| (axis, span)))
+        .collect()
+}
+
+pub fn complex_mul(a: Complex, b: Complex) -> Complex {
+    [a[0] * b[0] - a[1] * b[1], a[0] * b[1] + a[1] * b[0]]
+}
+
+/// `fft_pass` in particlemesh.wgsl
+pub fn fft_pass(
+    src: &[Complex],
+    dst: &mut [Complex],
+    side: u32,
+    axis: u32,
+    span: u32,
+    inverse: bool,
+) {
+    let side = side as usize;
+    let span = span as usize;
+    let half_side = side / 2;
+    let element = |line: usize, n: usize| {
+        if axis == 0 {
+            line * side + n
+        } else {
+            n * side + line
+        }
+    };
+    let direction: f32 = if inverse { 1.0 } else { -1.0 };
+
+    for line in 0..side {
+        for j in 0..half_side {
+            let a = src[element(line, j)];
+            let k = j % span;
+            let angle = direction * 6.2831855 * k as f32 / (2 * span) as f32;
+            let b = complex_mul(
+                src[element(line, j + half_side)],
+                [angle.cos(), angle.sin()],
+            );
+
+            let dst_index = (j / span) * 2 * span + k;
+            dst[element(line, dst_index)] = [a[0] + b[0], a[1] + b[1]];
+            dst[element(line, dst_index + span)] = [a[0] - b[0], a[1] - b[1]];
+        }
+    }
+}
+
+/// unnormalised 2D transform of a `side`×`side` mesh, `side` a power of two
+pub fn fft(data: &mut Vec<Complex>, side: u32, inverse: bool) {
+    let mut scratch = vec![[0.0; 2]; data.len()];
+    for (axis, span) in fft_passes(side) {
+        fft_pass(data, &mut scratch, side, axis, span, inverse);
+        std::mem::swap(data, &mut scratch);
+    }
+}
+
+/// spectrum that turns deposited mass into the field `ax + i ay`, with the `1 / side²`
+/// of the inverse transform folded in
+pub fn field_kernel(params: &Params) -> Vec<Complex> {
+    let side = mesh_side(params);
+    let n = side as usize;
+    let h = (params.world_size / params.num_grids_side as f32) as f64;
+    let gravity = params.gravity as f64;
+    let norm = 1.0 / (n * n) as f64;
+
+    if params.boundary == BoundaryMode::Periodic {
+        let mut kernel = Vec::with_capacity(n * n);
+        for ky in 0..n {
+            for kx in 0..n {
+                let theta = [TAU * kx as f64 / n as f64, TAU * ky as f64 / n as f64];
+                let laplacian =
+                    4.0 * ((theta[0] / 2.0).sin().powi(2) + (theta[1] / 2.0).sin().powi(2));
+                if laplacian == 0.0 {
+                    kernel.push([0.0, 0.0]);
+                    continue;
+                }
+                let phi = -TAU * gravity / laplacian;
+                kernel.push([
+                    (theta[1].sin() * phi / h * norm) as f32,
+                    (-theta[0].sin() * phi / h * norm) as f32,
+                ]);
+            }
+        }
+        return kernel;
+    }
+
+    let softening = params.particle_radius as f64;
+    let offset = |i: usize| {
+        let i = i as f64;
+        if i < n as f64 / 2.0 {
+            i * h
+        } else {
+            (i - n as f64) * h
+        }
+    };
+    let mut kernel = Vec::with_capacity(n * n);
+    for y in 0..n {
+        for x in 0..n {
+            let r = [offset(x), offset(y)];
+            if r == [0.0, 0.0] {
+                kernel.push([0.0, 0.0]);
+                continue;
+            }
+            let scale = -gravity * norm / (r[0] * r[0] + r[1] * r[1] + softening * softening);
+            kernel.push([(scale * r[0]) as f32, (scale * r[1]) as f32]);
+        }
+    }
+    fft(&mut kernel, side, false);
+    kernel
+}
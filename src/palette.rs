@@ -0,0 +1,124 @@
+//! Colours of the particle types.
+//!
+//! Each type's colour is kept in [`Params::type_colours`] as sRGB, the way
+//! colour pickers and hex codes give it, and converted to linear for the
+//! `TypeColours` uniform of draw.wgsl, whose sRGB target encodes it back.
+//! A palette fills the table for any number of types; the qualitative ones
+//! repeat once they run out of colours.
+//!
+//! [`Params::type_colours`]: crate::Params::type_colours
+
+use std::fmt;
+
+/// entries of the `TypeColours` uniform, types past it reuse its colours
+pub const MAX_TYPE_COLOURS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+    /// red through cyan by type, what eden has always drawn
+    #[default]
+    Classic,
+    /// Tableau 10
+    Tableau,
+    /// Okabe and Ito's colour-blind-safe set, without its black
+    OkabeIto,
+    /// Paul Tol's colour-blind-safe bright scheme
+    TolBright,
+    /// samples of the perceptually uniform, colour-blind-safe viridis map
+    Viridis,
+}
+
+const TABLEAU: [u32; 10] = [
+    0x4e79a7, 0xf28e2b, 0xe15759, 0x76b7b2, 0x59a14f, 0xedc948, 0xb07aa1, 0xff9da7, 0x9c755f,
+    0xbab0ac,
+];
+const OKABE_ITO: [u32; 7] = [
+    0xe69f00, 0x56b4e9, 0x009e73, 0xf0e442, 0x0072b2, 0xd55e00, 0xcc79a7,
+];
+const TOL_BRIGHT: [u32; 7] = [
+    0x4477aa, 0xee6677, 0x228833, 0xccbb44, 0x66ccee, 0xaa3377, 0xbbbbbb,
+];
+/// viridis at eight even steps, interpolated in between
+const VIRIDIS: [u32; 8] = [
+    0x440154, 0x46327e, 0x365c8d, 0x277f8e, 0x1fa187, 0x4ac16d, 0xa0da39, 0xfde725,
+];
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Classic,
+        Palette::Tableau,
+        Palette::OkabeIto,
+        Palette::TolBright,
+        Palette::Viridis,
+    ];
+
+    /// whether the colours stay apart for the common kinds of colour blindness
+    pub fn colour_blind_safe(&self) -> bool {
+        matches!(
+            self,
+            Palette::OkabeIto | Palette::TolBright | Palette::Viridis
+        )
+    }
+
+    /// the sRGB colour of type `index` out of `num_types`
+    pub fn colour(&self, index: usize, num_types: usize) -> [f32; 3] {
+        // where the type sits between the first and the last
+        let t = index as f32 / num_types.max(1) as f32;
+        match self {
+            Palette::Classic => {
+                [1.0 - t, t, t].map(|channel| linear_to_srgb(channel.clamp(0.0, 1.0)))
+            }
+            Palette::Tableau => hex(TABLEAU[index % TABLEAU.len()]),
+            Palette::OkabeIto => hex(OKABE_ITO[index % OKABE_ITO.len()]),
+            Palette::TolBright => hex(TOL_BRIGHT[index % TOL_BRIGHT.len()]),
+            Palette::Viridis => {
+                let t = index as f32 / (num_types.max(2) - 1) as f32;
+                let x = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f32;
+                let below = (x.floor() as usize).min(VIRIDIS.len() - 2);
+                let (a, b) = (hex(VIRIDIS[below]), hex(VIRIDIS[below + 1]));
+                let f = x - below as f32;
+                [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * f)
+            }
+        }
+    }
+
+    /// a colour for each of `num_types` types
+    pub fn colours(&self, num_types: usize) -> Vec<[f32; 3]> {
+        (0..num_types)
+            .map(|index| self.colour(index, num_types))
+            .collect()
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Palette::Classic => "classic",
+            Palette::Tableau => "tableau",
+            Palette::OkabeIto => "okabe_ito",
+            Palette::TolBright => "tol_bright",
+            Palette::Viridis => "viridis",
+        })
+    }
+}
+
+fn hex(rgb: u32) -> [f32; 3] {
+    [16, 8, 0].map(|shift| ((rgb >> shift) & 0xff) as f32 / 255.0)
+}
+
+/// the sRGB transfer function, from light intensity to the value stored in an 8-bit image
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(srgb: f32) -> f32 {
+    if srgb <= 0.040_45 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,634 @@
+//! CPU reference versions of the force kernels in `src/shaders` and the integrators in
+//! integrate.wgsl.
+//!
+//! Everything here is a plain O(N²) loop in f32 that follows the WGSL line by
+//! line, so running it next to a [`Simulation`] tells a physics regression
+//! apart from a neighbour-search or rendering change.
+
+use std::fmt;
+
+use crate::{mesh, Algorithm, BoundaryMode, Integrator, Params, Particle, Simulation};
+
+/// force law of one of the bucket-layout compute shaders
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForceModel {
+    /// the `force` module in experimental.wgsl
+    ParticleLife,
+    /// the `force` module in lennardjones.wgsl, over every pair
+    LennardJones,
+    /// softened gravity summed directly, what barneshut.wgsl approximates (exactly at theta = 0)
+    Gravity,
+    /// the mesh solve of particlemesh.wgsl, see [`particle_mesh_accels`]
+    ParticleMesh,
+}
+
+impl ForceModel {
+    pub const ALL: [ForceModel; 4] = [
+        ForceModel::ParticleLife,
+        ForceModel::LennardJones,
+        ForceModel::Gravity,
+        ForceModel::ParticleMesh,
+    ];
+
+    /// the shader the GPU runs for this model
+    pub fn shader_source(&self) -> &'static str {
+        match self {
+            ForceModel::ParticleLife => include_str!("shaders/experimental.wgsl"),
+            ForceModel::LennardJones => include_str!("shaders/lennardjones.wgsl"),
+            ForceModel::Gravity => include_str!("shaders/barneshut.wgsl"),
+            ForceModel::ParticleMesh => include_str!("shaders/particlemesh.wgsl"),
+        }
+    }
+
+    /// the algorithm the GPU runs this model with
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            ForceModel::ParticleLife => Algorithm::UniformGrid,
+            ForceModel::LennardJones => Algorithm::BruteForce,
+            ForceModel::Gravity => Algorithm::BarnesHut,
+            ForceModel::ParticleMesh => Algorithm::ParticleMesh,
+        }
+    }
+
+    /// acceleration of every particle, as one force pass computes them
+    pub fn accels(&self, params: &Params, particles: &[Particle]) -> Vec<[f32; 2]> {
+        match self {
+            ForceModel::ParticleMesh => particle_mesh_accels(params, particles),
+            _ => (0..particles.len())
+                .map(|i| self.accel(params, particles, i))
+                .collect(),
+        }
+    }
+
+    /// total acceleration on `particles[index]` from every other particle
+    pub fn accel(&self, params: &Params, particles: &[Particle], index: usize) -> [f32; 2] {
+        if *self == ForceModel::ParticleMesh {
+            return particle_mesh_accels(params, particles)[index];
+        }
+        let max_types = f32::sqrt((params.attraction_matrix.len() / 4) as f32) as u32;
+        let mut accum = [0.0f32; 2];
+
+        for (i, other) in particles.iter().enumerate() {
+            if i == index {
+                continue;
+            }
+            let accel = match self {
+                ForceModel::ParticleLife => {
+                    let grid_size_side = params.world_size / params.num_grids_side as f32;
+                    let accel = particle_life_accel(params, max_types, &particles[index], other);
+                    [grid_size_side * accel[0], grid_size_side * accel[1]]
+                }
+                ForceModel::LennardJones => {
+                    lennard_jones_accel(params, max_types, &particles[index], other)
+                }
+                ForceModel::Gravity => gravity_accel(params, &particles[index], other),
+                ForceModel::ParticleMesh => {
+                    unreachable!("the mesh is solved for all particles at once")
+                }
+            };
+            accum[0] += accel[0];
+            accum[1] += accel[1];
+        }
+
+        accum
+    }
+}
+
+impl fmt::Display for ForceModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForceModel::ParticleLife => write!(f, "particle-life"),
+            ForceModel::LennardJones => write!(f, "lennard-jones"),
+            ForceModel::Gravity => write!(f, "gravity"),
+            ForceModel::ParticleMesh => write!(f, "particle-mesh"),
+        }
+    }
+}
+
+fn kind_index(particle: &Particle, max_types: u32) -> u32 {
+    (particle.kind * max_types as f32) as u32
+}
+
+fn matrix_entry(params: &Params, max_types: u32, a: &Particle, b: &Particle) -> f32 {
+    let mat_index = kind_index(a, max_types) * max_types + kind_index(b, max_types);
+    params.attraction_matrix[mat_index as usize * 4]
+}
+
+/// `force` in experimental.wgsl, without its grid_size_side factor
+pub fn particle_life_accel(
+    params: &Params,
+    max_types: u32,
+    this: &Particle,
+    other: &Particle,
+) -> [f32; 2] {
+    let grid_size_side = params.world_size / params.num_grids_side as f32;
+    let distance_vector = separation(params, this.pos, other.pos);
+    let distance_squared =
+        distance_vector[0] * distance_vector[0] + distance_vector[1] * distance_vector[1];
+    let dist = distance_squared.sqrt() / grid_size_side;
+    let beta = 1.0 / grid_size_side;
+
+    let mag = if dist < beta {
+        dist / beta - 1.0
+    } else if dist > beta && dist < 1.0 {
+        matrix_entry(params, max_types, this, other)
+            * (1.0 - ((2.0 * dist) - 1.0 - beta).abs() / (1.0 - beta))
+    } else {
+        return [0.0, 0.0];
+    };
+
+    let norm = (distance_squared + 0.0000000000001).sqrt();
+    [
+        params.well_depth * (distance_vector[0] / norm) * mag,
+        params.well_depth * (distance_vector[1] / norm) * mag,
+    ]
+}
+
+/// `force` in lennardjones.wgsl
+pub fn lennard_jones_accel(
+    params: &Params,
+    max_types: u32,
+    this: &Particle,
+    other: &Particle,
+) -> [f32; 2] {
+    let distance_vector = separation(params, this.pos, other.pos);
+    let distance_squared =
+        distance_vector[0] * distance_vector[0] + distance_vector[1] * distance_vector[1];
+    let dist = distance_squared.sqrt();
+    let col_length: f32 = 1.0;
+    let col_dist = dist / col_length;
+    let z = (col_dist + 10.22462) / 10.0;
+
+    let mag = if col_dist <= 1.0 {
+        params.repulse_coeff * (params.well_depth * col_dist - params.well_depth)
+    } else {
+        let term_1 = col_length.powf(6.0) / z.powf(7.0);
+        -params.attract_coeff
+            * params.well_depth
+            * matrix_entry(params, max_types, this, other)
+            * term_1
+            * (term_1 * z - 0.5)
+    };
+
+    let norm = (distance_squared + 0.0000000000001).sqrt();
+    [
+        (distance_vector[0] / norm) * mag / this.mass,
+        (distance_vector[1] / norm) * mag / this.mass,
+    ]
+}
+
+/// `gravity` in barneshut.wgsl for a single particle, softened by `particle_radius`
+pub fn gravity_accel(params: &Params, this: &Particle, other: &Particle) -> [f32; 2] {
+    let offset = separation(params, this.pos, other.pos);
+    let r2 = offset[0] * offset[0]
+        + offset[1] * offset[1]
+        + params.particle_radius * params.particle_radius;
+    let scale = params.gravity * other.mass / (r2 * r2.sqrt());
+    [scale * offset[0], scale * offset[1]]
+}
+
+/// `deposit`, the FFT passes and the interpolation in `main` of particlemesh.wgsl
+pub fn particle_mesh_accels(params: &Params, particles: &[Particle]) -> Vec<[f32; 2]> {
+    let grid = params.num_grids_side as i64;
+    let side = mesh::mesh_side(params);
+    let n = side as usize;
+    let periodic = params.boundary == BoundaryMode::Periodic;
+    let grid_size_side = params.world_size / params.num_grids_side as f32;
+
+    // the four nodes around a particle and their cloud-in-cell weights
+    let stencil = |pos: [f32; 2]| {
+        let f = pos.map(|x| {
+            let f = x / grid_size_side - 0.5;
+            if periodic {
+                f
+            } else {
+                f.clamp(0.0, grid as f32 - 1.0)
+            }
+        });
+        let base = f.map(f32::floor);
+        let t = [f[0] - base[0], f[1] - base[1]];
+        let node = |dx: i64, dy: i64| {
+            let mut x = base[0] as i64 + dx;
+            let mut y = base[1] as i64 + dy;
+            if periodic {
+                x = x.rem_euclid(grid);
+                y = y.rem_euclid(grid);
+            }
+            y as usize * n + x as usize
+        };
+        [
+            (node(0, 0), (1.0 - t[0]) * (1.0 - t[1])),
+            (node(1, 0), t[0] * (1.0 - t[1])),
+            (node(0, 1), (1.0 - t[0]) * t[1]),
+            (node(1, 1), t[0] * t[1]),
+        ]
+    };
+
+    let mut nodes = vec![[0.0f32; 2]; n * n];
+    for particle in particles {
+        for (node, weight) in stencil(particle.pos) {
+            nodes[node][0] += particle.mass * weight;
+        }
+    }
+    mesh::fft(&mut nodes, side, false);
+    for (node, kernel) in nodes.iter_mut().zip(mesh::field_kernel(params)) {
+        *node = mesh::complex_mul(*node, kernel);
+    }
+    mesh::fft(&mut nodes, side, true);
+
+    particles
+        .iter()
+        .map(|particle| {
+            stencil(particle.pos)
+                .iter()
+                .fold([0.0, 0.0], |accel, &(node, weight)| {
+                    [
+                        accel[0] + nodes[node][0] * weight,
+                        accel[1] + nodes[node][1] * weight,
+                    ]
+                })
+        })
+        .collect()
+}
+
+/// `particle` moved to `pos` and `vel`: `write_step` in integrate.wgsl when `last`,
+/// otherwise `write_stage`, which only wraps a periodic world
+fn moved(
+    params: &Params,
+    particle: &Particle,
+    pos: [f32; 2],
+    vel: [f32; 2],
+    last: bool,
+) -> Particle {
+    let mut next = *particle;
+    next.pos = pos;
+    next.vel = vel;
+    if last || params.boundary == BoundaryMode::Periodic {
+        apply_boundary(params, &mut next);
+    }
+    next
+}
+
+/// `choose_dt` in timestep.wgsl, from the velocities and accelerations at the start of a step
+pub fn adaptive_dt(params: &Params, particles: &[Particle], accels: &[[f32; 2]]) -> f32 {
+    let length = |v: [f32; 2]| (v[0] * v[0] + v[1] * v[1]).sqrt();
+    let fastest = particles.iter().map(|p| length(p.vel)).fold(0.0, f32::max);
+    let hardest = accels.iter().copied().map(length).fold(0.0, f32::max);
+    let by_velocity = params.particle_radius / fastest.max(1e-20);
+    let by_acceleration = (params.particle_radius / hardest.max(1e-20)).sqrt();
+    (params.courant * by_velocity.min(by_acceleration)).clamp(params.dt_min, params.dt_max)
+}
+
+/// one step of `dt` with `params.integrator` under `model`, the passes of integrate.wgsl in order
+pub fn integrate(
+    params: &Params,
+    model: ForceModel,
+    particles: &[Particle],
+    dt: f32,
+) -> Vec<Particle> {
+    let friction = params.friction_coeff;
+
+    match params.integrator {
+        Integrator::Euler | Integrator::Trapezoidal => {
+            let accels = model.accels(params, particles);
+            particles
+                .iter()
+                .zip(accels)
+                .map(|(p, a)| {
+                    let vel = [0, 1].map(|i| (p.vel[i] + a[i] * dt) * friction);
+                    let pos = [0, 1].map(|i| match params.integrator {
+                        Integrator::Euler => p.pos[i] + vel[i] * dt,
+                        _ => p.pos[i] + (p.vel[i] + vel[i]) / 2.0 * dt,
+                    });
+                    moved(params, p, pos, vel, true)
+                })
+                .collect()
+        }
+        Integrator::VelocityVerlet => {
+            let accels = model.accels(params, particles);
+            let stage: Vec<Particle> = particles
+                .iter()
+                .zip(accels)
+                .map(|(p, a)| {
+                    let half_vel = [0, 1].map(|i| p.vel[i] + 0.5 * dt * a[i]);
+                    let pos = [0, 1].map(|i| p.pos[i] + half_vel[i] * dt);
+                    moved(params, p, pos, half_vel, false)
+                })
+                .collect();
+
+            let accels = model.accels(params, &stage);
+            stage
+                .iter()
+                .zip(accels)
+                .map(|(s, a)| {
+                    let vel = [0, 1].map(|i| (s.vel[i] + 0.5 * dt * a[i]) * friction);
+                    moved(params, s, s.pos, vel, true)
+                })
+                .collect()
+        }
+        Integrator::Leapfrog => {
+            let stage: Vec<Particle> = particles
+                .iter()
+                .map(|p| {
+                    let pos = [0, 1].map(|i| p.pos[i] + 0.5 * dt * p.vel[i]);
+                    moved(params, p, pos, p.vel, false)
+                })
+                .collect();
+
+            let accels = model.accels(params, &stage);
+            particles
+                .iter()
+                .zip(&stage)
+                .zip(accels)
+                .map(|((p, s), a)| {
+                    let vel = [0, 1].map(|i| (p.vel[i] + dt * a[i]) * friction);
+                    let pos = [0, 1].map(|i| s.pos[i] + 0.5 * dt * vel[i]);
+                    moved(params, p, pos, vel, true)
+                })
+                .collect()
+        }
+        Integrator::Rk4 => {
+            // (weight, fraction) of the first three stages, the fourth has weight 1/6
+            let stages = [(1.0 / 6.0, 0.5), (1.0 / 3.0, 0.5), (1.0 / 3.0, 1.0)];
+            let mut current = particles.to_vec();
+            let mut sums = vec![[0.0f32; 4]; particles.len()];
+            for (n, &(weight, fraction)) in stages.iter().enumerate() {
+                let accels = model.accels(params, &current);
+                let h = fraction * dt;
+                current = particles
+                    .iter()
+                    .zip(&current)
+                    .zip(accels)
+                    .zip(&mut sums)
+                    .map(|(((p, c), a), sum)| {
+                        let k = [c.vel[0], c.vel[1], a[0], a[1]];
+                        *sum = [0, 1, 2, 3].map(|i| {
+                            if n == 0 {
+                                weight * k[i]
+                            } else {
+                                sum[i] + weight * k[i]
+                            }
+                        });
+                        let pos = [0, 1].map(|i| p.pos[i] + h * k[i]);
+                        let vel = [0, 1].map(|i| p.vel[i] + h * k[2 + i]);
+                        moved(params, p, pos, vel, false)
+                    })
+                    .collect();
+            }
+
+            let accels = model.accels(params, &current);
+            particles
+                .iter()
+                .zip(&current)
+                .zip(accels)
+                .zip(sums)
+                .map(|(((p, c), a), sum)| {
+                    let k = [c.vel[0], c.vel[1], a[0], a[1]];
+                    let d = [0, 1, 2, 3].map(|i| sum[i] + 1.0 / 6.0 * k[i]);
+                    let vel = [0, 1].map(|i| (p.vel[i] + dt * d[2 + i]) * friction);
+                    let pos = [0, 1].map(|i| p.pos[i] + dt * d[i]);
+                    moved(params, p, pos, vel, true)
+                })
+                .collect()
+        }
+    }
+}
+
+/// `separation` in the force shaders: `b - a`, through the edges for a periodic world
+pub fn separation(params: &Params, a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let mut d = [b[0] - a[0], b[1] - a[1]];
+    if params.boundary == BoundaryMode::Periodic {
+        for d in &mut d {
+            *d -= params.world_size * (*d / params.world_size).round_ties_even();
+        }
+    }
+    d
+}
+
+/// `apply_boundary` in integrate.wgsl
+pub fn apply_boundary(params: &Params, particle: &mut Particle) {
+    let world_size = params.world_size;
+    match params.boundary {
+        BoundaryMode::Open => {}
+        BoundaryMode::Periodic => {
+            for pos in &mut particle.pos {
+                *pos -= world_size * (*pos / world_size).floor();
+                if *pos >= world_size {
+                    *pos = 0.0;
+                }
+            }
+        }
+        BoundaryMode::Reflect | BoundaryMode::Absorb => {
+            let fudge = 0.00001;
+            let mut hit = [false; 2];
+            for (pos, hit) in particle.pos.iter_mut().zip(&mut hit) {
+                *hit = *pos < fudge || *pos > world_size - fudge;
+                *pos = pos.clamp(fudge, world_size - fudge);
+            }
+            if params.boundary == BoundaryMode::Absorb {
+                if hit[0] || hit[1] {
+                    particle.vel = [0.0, 0.0];
+                }
+            } else {
+                for (vel, hit) in particle.vel.iter_mut().zip(hit) {
+                    if hit {
+                        *vel = -*vel;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// CPU counterpart of [`Simulation`] for a single force model
+#[derive(Clone, Debug)]
+pub struct CpuSimulation {
+    pub params: Params,
+    pub particles: Vec<Particle>,
+    pub model: ForceModel,
+    pub step_count: u64,
+    /// dt of the last step
+    pub dt: f32,
+    /// time simulated so far
+    pub time: f64,
+}
+
+impl CpuSimulation {
+    pub fn new(params: Params, particles: Vec<Particle>, model: ForceModel) -> Self {
+        CpuSimulation {
+            params,
+            particles,
+            model,
+            step_count: 0,
+            dt: 0.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn step(&mut self, steps: u32) {
+        for _ in 0..steps {
+            self.dt = if self.params.adaptive_dt {
+                let accels = self.model.accels(&self.params, &self.particles);
+                adaptive_dt(&self.params, &self.particles, &accels)
+            } else {
+                self.params.dt
+            };
+            // every particle reads the previous state, like the src / dst buffers on the GPU
+            self.particles = integrate(&self.params, self.model, &self.particles, self.dt);
+            self.time += self.dt as f64;
+            self.step_count += 1;
+        }
+    }
+}
+
+/// distance between the CPU and GPU copies of one particle
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParticleDivergence {
+    pub position: f32,
+    pub velocity: f32,
+}
+
+pub fn divergence(cpu: &[Particle], gpu: &[Particle]) -> Vec<ParticleDivergence> {
+    assert_eq!(cpu.len(), gpu.len(), "particle counts differ");
+    let distance =
+        |a: [f32; 2], b: [f32; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+
+    cpu.iter()
+        .zip(gpu)
+        .map(|(c, g)| ParticleDivergence {
+            position: distance(c.pos, g.pos),
+            velocity: distance(c.vel, g.vel),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct DivergenceReport {
+    pub model: ForceModel,
+    pub seed: u64,
+    pub steps: u32,
+    /// divergence of every particle after the last step
+    pub per_particle: Vec<ParticleDivergence>,
+    /// largest position divergence after each step
+    pub max_position_per_step: Vec<f32>,
+}
+
+impl DivergenceReport {
+    /// NaN on either side counts as infinitely far apart
+    pub fn max_position(&self) -> f32 {
+        self.per_particle
+            .iter()
+            .map(|d| {
+                if d.position.is_nan() {
+                    f32::INFINITY
+                } else {
+                    d.position
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    pub fn max_velocity(&self) -> f32 {
+        self.per_particle
+            .iter()
+            .map(|d| {
+                if d.velocity.is_nan() {
+                    f32::INFINITY
+                } else {
+                    d.velocity
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    pub fn mean_position(&self) -> f32 {
+        self.per_particle.iter().map(|d| d.position).sum::<f32>() / self.per_particle.len() as f32
+    }
+
+    /// index of the particle that drifted furthest from the reference
+    pub fn worst_particle(&self) -> Option<usize> {
+        (0..self.per_particle.len()).max_by(|&a, &b| {
+            self.per_particle[a]
+                .position
+                .total_cmp(&self.per_particle[b].position)
+        })
+    }
+
+    /// first step at which the position divergence exceeded `tolerance`
+    pub fn first_step_above(&self, tolerance: f32) -> Option<u32> {
+        self.max_position_per_step
+            .iter()
+            .position(|&d| d.is_nan() || d > tolerance)
+            .map(|step| step as u32 + 1)
+    }
+}
+
+impl fmt::Display for DivergenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} seed {} after {} steps ({} particles)",
+            self.model,
+            self.seed,
+            self.steps,
+            self.per_particle.len()
+        )?;
+        writeln!(f, "  max position divergence:  {}", self.max_position())?;
+        writeln!(f, "  mean position divergence: {}", self.mean_position())?;
+        writeln!(f, "  max velocity divergence:  {}", self.max_velocity())?;
+        if let Some(worst) = self.worst_particle() {
+            writeln!(f, "  worst particle: {}", worst)?;
+        }
+        Ok(())
+    }
+}
+
+/// runs `steps` steps of `model` on the GPU and on the CPU from the same
+/// params and seed, reading the GPU state back after every step
+pub fn compare_with_gpu(
+    params: &Params,
+    model: ForceModel,
+    seed: u64,
+    steps: u32,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> DivergenceReport {
+    let mut params = params.clone();
+    params.shader_buffer = model.shader_source().to_string();
+    params.algorithm = model.algorithm();
+    params.seed = seed;
+
+    let particles = params.spawn_particles();
+    let mut gpu = Simulation::try_from_particles(params.clone(), &particles, device)
+        .expect("the reference models' shaders build");
+    let mut cpu = CpuSimulation::new(params, particles, model);
+
+    let mut max_position_per_step = Vec::with_capacity(steps as usize);
+    let mut per_particle = Vec::new();
+    for _ in 0..steps {
+        gpu.step(device, queue, 1);
+        cpu.step(1);
+
+        per_particle = divergence(&cpu.particles, &gpu.read_particles(device, queue));
+        max_position_per_step.push(
+            per_particle
+                .iter()
+                .map(|d| {
+                    if d.position.is_nan() {
+                        f32::INFINITY
+                    } else {
+                        d.position
+                    }
+                })
+                .fold(0.0, f32::max),
+        );
+    }
+
+    DivergenceReport {
+        model,
+        seed,
+        steps,
+        per_particle,
+        max_position_per_step,
+    }
+}
@@ -0,0 +1,130 @@
+//! The buffers a compute shader binds, read from its WGSL with naga, so a
+//! shader can be checked against what eden provides before wgpu is asked to
+//! build a pipeline for it.
+
+use naga::{valid, AddressSpace, ArraySize, StorageAccess, TypeInner};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingSpace {
+    Uniform,
+    Storage { read_only: bool },
+}
+
+/// a buffer binding one of the shader's entry points reads or writes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderBinding {
+    pub group: u32,
+    pub binding: u32,
+    /// the global variable the shader binds it to
+    pub name: String,
+    pub space: BindingSpace,
+    /// bytes before a runtime-sized array, or the size of the whole binding
+    pub size: u32,
+    /// element stride of the runtime-sized array, if the binding is or ends in one
+    pub stride: Option<u32>,
+    /// the entry points that read or write it
+    pub used_by: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShaderInterface {
+    pub entry_points: Vec<String>,
+    /// ordered by group and binding
+    pub bindings: Vec<ShaderBinding>,
+}
+
+impl ShaderInterface {
+    /// drops the bindings only other entry points use
+    pub fn retain_entry_point(&mut self, entry_point: &str) {
+        self.bindings
+            .retain(|b| b.used_by.iter().any(|ep| ep == entry_point));
+    }
+
+    pub fn binding(&self, group: u32, binding: u32) -> Option<&ShaderBinding> {
+        self.bindings
+            .iter()
+            .find(|b| b.group == group && b.binding == binding)
+    }
+}
+
+/// parses and validates `source`, then collects the buffers its entry points use;
+/// errors are naga's, rendered against the source with `path` and line numbers
+pub fn reflect(source: &str, path: &str) -> Result<ShaderInterface, String> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| e.emit_to_string_with_path(source, path))?;
+    let info = valid::Validator::new(valid::ValidationFlags::all(), valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|e| e.emit_to_string_with_path(source, path))?;
+    let mut layouter = naga::proc::Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .map_err(|e| e.to_string())?;
+
+    let mut bindings = Vec::new();
+    for (handle, var) in module.global_variables.iter() {
+        let Some(binding) = &var.binding else {
+            continue;
+        };
+        let used_by: Vec<String> = module
+            .entry_points
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !info.get_entry_point(i)[handle].is_empty())
+            .map(|(_, ep)| ep.name.clone())
+            .collect();
+        if used_by.is_empty() {
+            continue;
+        }
+        let name = var.name.clone().unwrap_or_default();
+        let space = match var.space {
+            AddressSpace::Uniform => BindingSpace::Uniform,
+            AddressSpace::Storage { access } => BindingSpace::Storage {
+                read_only: !access.contains(StorageAccess::STORE),
+            },
+            _ => {
+                return Err(format!(
+                    "`{}` at binding {} is not a buffer, eden only binds buffers",
+                    name, binding.binding
+                ))
+            }
+        };
+        let (size, stride) = match &module.types[var.ty].inner {
+            TypeInner::Array {
+                size: ArraySize::Dynamic,
+                stride,
+                ..
+            } => (0, Some(*stride)),
+            TypeInner::Struct { members, span } => match members.last() {
+                Some(last) => match module.types[last.ty].inner {
+                    TypeInner::Array {
+                        size: ArraySize::Dynamic,
+                        stride,
+                        ..
+                    } => (last.offset, Some(stride)),
+                    _ => (*span, None),
+                },
+                None => (*span, None),
+            },
+            _ => (layouter[var.ty].size, None),
+        };
+        bindings.push(ShaderBinding {
+            group: binding.group,
+            binding: binding.binding,
+            name,
+            space,
+            size,
+            stride,
+            used_by,
+        });
+    }
+    bindings.sort_by_key(|b| (b.group, b.binding));
+
+    Ok(ShaderInterface {
+        entry_points: module
+            .entry_points
+            .iter()
+            .map(|ep| ep.name.clone())
+            .collect(),
+        bindings,
+    })
+}
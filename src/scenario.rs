@@ -0,0 +1,448 @@
+//! Human-editable scenario files.
+//!
+//! A scenario is a TOML file holding everything needed to recreate a run: the
+//! scalar `Params`, the attraction matrix as a plain N×N table, the compute
+//! shader by file name and the initial spawn layout. The shader is looked up
+//! next to the scenario first, then in a [`ShaderRegistry`].
+//!
+//! ```toml
+//! world_size = 50.0
+//! dt = 0.001
+//! num_particles = 1000
+//! num_types = 2
+//! num_grids_side = 50
+//! well_depth = 500.0
+//! attract_coeff = 1.0
+//! repulse_coeff = 1.0
+//! friction_coeff = 0.9
+//! particle_radius = 1.0
+//! seed = 42
+//! boundary = "periodic"
+//! deterministic = true
+//! sort_every = 16
+//! algorithm = "uniform_grid"
+//! integrator = "velocity_verlet"
+//! adaptive_dt = true
+//! dt_min = 0.000001
+//! dt_max = 0.01
+//! courant = 0.2
+//! shader = "expernew.wgsl"
+//! attraction_matrix = [
+//!     [0.5, -0.2],
+//!     [0.1, 0.8],
+//! ]
+//!
+//! [spawn]
+//! kind = "disk"
+//! radius = 10.0
+//!
+//! [shader_params]
+//! search_grids = 12.0
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    shader_registry::ShaderRegistry, Algorithm, BoundaryMode, Integrator, Params, SpawnLayout,
+    DEFAULT_COURANT, DEFAULT_DT_MAX, DEFAULT_DT_MIN, DEFAULT_SORT_EVERY, DEFAULT_THETA,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub world_size: f32,
+    pub dt: f32,
+    pub num_particles: u32,
+    pub num_types: u32,
+    pub num_grids_side: u32,
+    pub well_depth: f32,
+    pub attract_coeff: f32,
+    pub repulse_coeff: f32,
+    pub friction_coeff: f32,
+    pub particle_radius: f32,
+    /// a fresh random seed is picked when left out
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// file name of the compute shader, looked up next to the scenario, in `shaders/` and then
+    /// among the built-in shaders
+    pub shader: String,
+    /// `attraction_matrix[i][j]` is how strongly type `i` is pulled towards type `j`
+    pub attraction_matrix: Vec<Vec<f32>>,
+    #[serde(default)]
+    pub spawn: SpawnLayout,
+    /// `reflect`, `periodic`, `absorb` or `open`
+    #[serde(default)]
+    pub boundary: BoundaryMode,
+    /// fixed-order neighbour search, for bit-identical replays of a seed
+    #[serde(default)]
+    pub deterministic: bool,
+    /// steps between Morton reorders of the particle buffers, 0 never reorders
+    #[serde(default = "default_sort_every")]
+    pub sort_every: u32,
+    /// `brute_force` and `uniform_grid` run `shader` over every pair or over the cell list,
+    /// `barnes_hut` and `particle_mesh` the built-in gravity solvers
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    /// Barnes-Hut opening angle, 0 sums every pair exactly
+    #[serde(default = "default_theta")]
+    pub theta: f32,
+    /// gravitational constant for `barnes_hut` and `particle_mesh`
+    #[serde(default = "default_gravity")]
+    pub gravity: f32,
+    /// `euler`, `trapezoidal`, `velocity_verlet`, `leapfrog` or `rk4`
+    #[serde(default)]
+    pub integrator: Integrator,
+    /// pick dt every step between `dt_min` and `dt_max` instead of using `dt`
+    #[serde(default)]
+    pub adaptive_dt: bool,
+    #[serde(default = "default_dt_min")]
+    pub dt_min: f32,
+    #[serde(default = "default_dt_max")]
+    pub dt_max: f32,
+    /// particle radii a particle may move in one adaptive step
+    #[serde(default = "default_courant")]
+    pub courant: f32,
+    /// values of the `// @param` tunables `shader` declares, the rest keep their defaults
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shader_params: BTreeMap<String, f32>,
+    /// sRGB colour of each type as `[r, g, b]` between 0 and 1, types past the list
+    /// take the classic colours
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub type_colours: Vec<[f32; 3]>,
+}
+
+fn default_sort_every() -> u32 {
+    DEFAULT_SORT_EVERY
+}
+
+fn default_theta() -> f32 {
+    DEFAULT_THETA
+}
+
+fn default_gravity() -> f32 {
+    1.0
+}
+
+fn default_dt_min() -> f32 {
+    DEFAULT_DT_MIN
+}
+
+fn default_dt_max() -> f32 {
+    DEFAULT_DT_MAX
+}
+
+fn default_courant() -> f32 {
+    DEFAULT_COURANT
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(PathBuf, io::Error),
+    Parse(String),
+    Serialize(String),
+    /// a field parsed but holds a value the simulation cannot run with
+    Invalid {
+        field: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ScenarioError::Parse(e) => write!(f, "invalid scenario: {}", e),
+            ScenarioError::Serialize(e) => write!(f, "could not serialize scenario: {}", e),
+            ScenarioError::Invalid { field, reason } => {
+                write!(f, "invalid scenario field `{}`: {}", field, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+fn invalid(field: impl Into<String>, reason: impl Into<String>) -> ScenarioError {
+    ScenarioError::Invalid {
+        field: field.into(),
+        reason: reason.into(),
+    }
+}
+
+impl Scenario {
+    pub fn from_params(params: &Params) -> Self {
+        Scenario {
+            world_size: params.world_size,
+            dt: params.dt,
+            num_particles: params.num_particles,
+            num_types: params.num_types,
+            num_grids_side: params.num_grids_side,
+            well_depth: params.well_depth,
+            attract_coeff: params.attract_coeff,
+            repulse_coeff: params.repulse_coeff,
+            friction_coeff: params.friction_coeff,
+            particle_radius: params.particle_radius,
+            seed: Some(params.seed),
+            shader: params.shader_name.clone(),
+            attraction_matrix: params.matrix_rows(),
+            spawn: params.spawn,
+            boundary: params.boundary,
+            deterministic: params.deterministic,
+            sort_every: params.sort_every,
+            algorithm: params.algorithm,
+            theta: params.theta,
+            gravity: params.gravity,
+            integrator: params.integrator,
+            adaptive_dt: params.adaptive_dt,
+            dt_min: params.dt_min,
+            dt_max: params.dt_max,
+            courant: params.courant,
+            // only the tunables of the current shader, not those left over from others
+            shader_params: match params.shader_params() {
+                Ok(declared) => declared
+                    .iter()
+                    .filter_map(|param| {
+                        let value = params.shader_values.get(&param.name)?;
+                        Some((param.name.clone(), *value))
+                    })
+                    .collect(),
+                Err(_) => params.shader_values.clone(),
+            },
+            type_colours: params.type_colours.clone(),
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, ScenarioError> {
+        let scenario: Scenario =
+            toml::from_str(source).map_err(|e| ScenarioError::Parse(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// the TOML of a scenario that passes [`Scenario::validate`], so whatever is written
+    /// can be loaded again
+    pub fn to_toml(&self) -> Result<String, ScenarioError> {
+        self.validate()?;
+        if let Some(seed) = self.seed.filter(|&seed| seed > i64::MAX as u64) {
+            return Err(invalid(
+                "seed",
+                format!("{} does not fit in a TOML integer", seed),
+            ));
+        }
+        toml::to_string_pretty(self).map_err(|e| ScenarioError::Serialize(e.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let source =
+            fs::read_to_string(path).map_err(|e| ScenarioError::Io(path.to_path_buf(), e))?;
+        Self::from_toml(&source)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ScenarioError> {
+        fs::write(path, self.to_toml()?).map_err(|e| ScenarioError::Io(path.to_path_buf(), e))
+    }
+
+    /// checks every field, reporting the first one that is out of range
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        let positive = |field: &str, value: f32| {
+            if value.is_finite() && value > 0.0 {
+                Ok(())
+            } else {
+                Err(invalid(field, format!("must be positive, got {}", value)))
+            }
+        };
+        let finite = |field: &str, value: f32| {
+            if value.is_finite() {
+                Ok(())
+            } else {
+                Err(invalid(
+                    field,
+                    format!("must be a finite number, got {}", value),
+                ))
+            }
+        };
+
+        positive("world_size", self.world_size)?;
+        positive("dt", self.dt)?;
+        positive("particle_radius", self.particle_radius)?;
+        finite("well_depth", self.well_depth)?;
+        finite("attract_coeff", self.attract_coeff)?;
+        finite("repulse_coeff", self.repulse_coeff)?;
+        if !(0.0..=1.0).contains(&self.friction_coeff) {
+            return Err(invalid(
+                "friction_coeff",
+                format!("must be between 0 and 1, got {}", self.friction_coeff),
+            ));
+        }
+        if self.num_particles == 0 {
+            return Err(invalid("num_particles", "must be at least 1"));
+        }
+        if self.num_grids_side == 0 {
+            return Err(invalid("num_grids_side", "must be at least 1"));
+        }
+        self.algorithm
+            .check_grids_side(self.num_grids_side)
+            .map_err(|reason| invalid("num_grids_side", reason))?;
+        if !(self.theta.is_finite() && self.theta >= 0.0) {
+            return Err(invalid(
+                "theta",
+                format!("must be zero or positive, got {}", self.theta),
+            ));
+        }
+        finite("gravity", self.gravity)?;
+        positive("dt_min", self.dt_min)?;
+        positive("dt_max", self.dt_max)?;
+        if self.dt_max < self.dt_min {
+            return Err(invalid(
+                "dt_max",
+                format!(
+                    "must be at least dt_min ({}), got {}",
+                    self.dt_min, self.dt_max
+                ),
+            ));
+        }
+        positive("courant", self.courant)?;
+        if self.num_types == 0 {
+            return Err(invalid("num_types", "must be at least 1"));
+        }
+        if self.shader.trim().is_empty() {
+            return Err(invalid("shader", "must name a compute shader file"));
+        }
+        for (name, value) in &self.shader_params {
+            finite(&format!("shader_params.{}", name), *value)?;
+        }
+        for (i, colour) in self.type_colours.iter().enumerate() {
+            if !colour.iter().all(|channel| (0.0..=1.0).contains(channel)) {
+                return Err(invalid(
+                    format!("type_colours[{}]", i),
+                    format!("channels must be between 0 and 1, got {:?}", colour),
+                ));
+            }
+        }
+
+        let n = self.num_types as usize;
+        if self.attraction_matrix.len() != n {
+            return Err(invalid(
+                "attraction_matrix",
+                format!(
+                    "expected {} rows for num_types = {}, found {}",
+                    n,
+                    n,
+                    self.attraction_matrix.len()
+                ),
+            ));
+        }
+        for (i, row) in self.attraction_matrix.iter().enumerate() {
+            if row.len() != n {
+                return Err(invalid(
+                    format!("attraction_matrix[{}]", i),
+                    format!("expected {} entries, found {}", n, row.len()),
+                ));
+            }
+            for (j, value) in row.iter().enumerate() {
+                finite(&format!("attraction_matrix[{}][{}]", i, j), *value)?;
+            }
+        }
+
+        if let SpawnLayout::Disk { radius } = self.spawn {
+            positive("spawn.radius", radius)?;
+        }
+
+        Ok(())
+    }
+
+    /// builds `Params`, resolving `shader` relative to `base_dir` (usually the scenario's
+    /// folder), then among the user and built-in shaders of `shaders`
+    pub fn to_params(
+        &self,
+        base_dir: Option<&Path>,
+        shaders: &ShaderRegistry,
+    ) -> Result<Params, ScenarioError> {
+        self.to_params_with_shader(resolve_shader(&self.shader, base_dir, shaders)?)
+    }
+
+    /// builds `Params` around shader source that was stored elsewhere, e.g. in a snapshot
+    pub fn to_params_with_shader(&self, shader_buffer: String) -> Result<Params, ScenarioError> {
+        self.validate()?;
+
+        let mut params = Params::new();
+        params.world_size = self.world_size;
+        params.dt = self.dt;
+        params.num_particles = self.num_particles;
+        params.num_types = self.num_types;
+        params.num_grids_side = self.num_grids_side;
+        params.well_depth = self.well_depth;
+        params.attract_coeff = self.attract_coeff;
+        params.repulse_coeff = self.repulse_coeff;
+        params.friction_coeff = self.friction_coeff;
+        params.particle_radius = self.particle_radius;
+        if let Some(seed) = self.seed {
+            params.seed = seed;
+        }
+        params.spawn = self.spawn;
+        params.boundary = self.boundary;
+        params.deterministic = self.deterministic;
+        params.sort_every = self.sort_every;
+        params.algorithm = self.algorithm;
+        params.theta = self.theta;
+        params.gravity = self.gravity;
+        params.integrator = self.integrator;
+        params.adaptive_dt = self.adaptive_dt;
+        params.dt_min = self.dt_min;
+        params.dt_max = self.dt_max;
+        params.courant = self.courant;
+        params.set_matrix_rows(&self.attraction_matrix);
+        params.shader_buffer = shader_buffer;
+        params.shader_name = self.shader.clone();
+        params.shader_values = self.shader_params.clone();
+        params.type_colours = self.type_colours.clone();
+
+        Ok(params)
+    }
+}
+
+fn resolve_shader(
+    name: &str,
+    base_dir: Option<&Path>,
+    shaders: &ShaderRegistry,
+) -> Result<String, ScenarioError> {
+    if let Some(dir) = base_dir {
+        if let Ok(source) = fs::read_to_string(dir.join(name)) {
+            return Ok(source);
+        }
+    }
+    if let Some(entry) = shaders.get(name) {
+        return Ok(entry.source.clone());
+    }
+
+    let searched: Vec<String> = base_dir
+        .iter()
+        .chain(shaders.user_dir.as_deref().iter())
+        .map(|dir| dir.display().to_string())
+        .collect();
+    let looked_in = if searched.is_empty() {
+        "the built-in shaders".to_string()
+    } else {
+        format!("{} and the built-in shaders", searched.join(", "))
+    };
+    Err(invalid(
+        "shader",
+        format!("no shader named {:?} (looked in {})", name, looked_in),
+    ))
+}
+
+/// loads a scenario file straight into `Params`, its shader resolved as by
+/// [`Scenario::to_params`]
+pub fn load_params(path: &Path, shaders: &ShaderRegistry) -> Result<Params, ScenarioError> {
+    Scenario::load(path)?.to_params(path.parent(), shaders)
+}
+
+pub fn save_params(params: &Params, path: &Path) -> Result<(), ScenarioError> {
+    Scenario::from_params(params).save(path)
+}
@@ -0,0 +1,121 @@
+//! Tunables a force shader declares for itself.
+//!
+//! A line comment of the form
+//!
+//! ```wgsl
+//! // @param search_grids 2 32 8
+//! ```
+//!
+//! declares a parameter `search_grids` ranging from 2 to 32 and starting at 8.
+//! [`declarations`] turns the parameters of a shader into a `ShaderParams`
+//! uniform at `@group(0) @binding(10)`, which [`force::compose`] adds to the
+//! shader, so the force code reads it as `shader_params.search_grids`. The
+//! values live in [`Params::shader_values`] by name, and the GUI shows a
+//! slider for each parameter.
+//!
+//! [`force::compose`]: crate::force::compose
+//! [`Params::shader_values`]: crate::Params::shader_values
+
+use std::collections::BTreeMap;
+
+/// binding of the `ShaderParams` uniform in the force pass
+pub const BINDING: u32 = 10;
+
+/// marks a parameter declaration in a line comment
+const ANNOTATION: &str = "@param";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderParam {
+    pub name: String,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+/// every `// @param name min max default` line of `source`, in order
+pub fn parse(source: &str) -> Result<Vec<ShaderParam>, String> {
+    let mut declared: Vec<ShaderParam> = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let Some(comment) = line.trim_start().strip_prefix("//") else {
+            continue;
+        };
+        let Some(fields) = comment.trim_start().strip_prefix(ANNOTATION) else {
+            continue;
+        };
+        let fail = |reason: String| Err(format!("line {}: {}", index + 1, reason));
+
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        let [name, min, max, default] = fields[..] else {
+            return fail(format!(
+                "expected `{} name min max default`, found {} fields",
+                ANNOTATION,
+                fields.len()
+            ));
+        };
+        if !is_identifier(name) {
+            return fail(format!("`{}` is not a WGSL identifier", name));
+        }
+        if declared.iter().any(|param| param.name == name) {
+            return fail(format!("`{}` is declared twice", name));
+        }
+        let mut bounds = [0.0; 3];
+        for (value, field) in bounds.iter_mut().zip([min, max, default]) {
+            match field.parse::<f32>() {
+                Ok(parsed) if parsed.is_finite() => *value = parsed,
+                _ => return fail(format!("`{}` of `{}` is not a finite number", field, name)),
+            }
+        }
+        let [min, max, default] = bounds;
+        if min > max {
+            return fail(format!("`{}` has min {} above max {}", name, min, max));
+        }
+        if !(min..=max).contains(&default) {
+            return fail(format!(
+                "`{}` defaults to {}, outside {}..={}",
+                name, default, min, max
+            ));
+        }
+        declared.push(ShaderParam {
+            name: name.to_string(),
+            min,
+            max,
+            default,
+        });
+    }
+    Ok(declared)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+        && !name.starts_with("__")
+}
+
+/// the WGSL struct and uniform binding holding `declared`, empty when nothing is declared
+pub fn declarations(declared: &[ShaderParam]) -> String {
+    if declared.is_empty() {
+        return String::new();
+    }
+    let mut wgsl = String::from("\nstruct ShaderParams {\n");
+    for param in declared {
+        wgsl.push_str(&format!("  {}: f32,\n", param.name));
+    }
+    wgsl.push_str(&format!(
+        "}};\n\n@group(0) @binding({}) var<uniform> shader_params : ShaderParams;\n",
+        BINDING
+    ));
+    wgsl
+}
+
+/// the uniform contents for `declared`: the value in `values`, or the default, padded to
+/// whole vec4s so the buffer is never empty
+pub fn pack(declared: &[ShaderParam], values: &BTreeMap<String, f32>) -> Vec<f32> {
+    let mut slice: Vec<f32> = declared
+        .iter()
+        .map(|param| values.get(&param.name).copied().unwrap_or(param.default))
+        .collect();
+    slice.resize(declared.len().div_ceil(4).max(1) * 4, 0.0);
+    slice
+}
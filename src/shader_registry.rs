@@ -0,0 +1,182 @@
+//! The compute shaders eden can run, by name.
+//!
+//! The shaders eden ships are compiled into the binary, so they are there
+//! whatever directory eden is started from and in the wasm build, where there
+//! is no file system to read them from. A user directory adds the `.wgsl`
+//! files in it on top; one named like a built-in shader replaces it.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{force, Algorithm, DEFAULT_COMPUTE_SHADER, DEFAULT_COMPUTE_SHADER_NAME};
+
+/// where user shaders are looked for when no directory is given
+pub const DEFAULT_USER_DIR: &str = "shaders";
+
+/// a shader shipped inside the binary
+#[derive(Clone, Copy, Debug)]
+pub struct BuiltinShader {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub source: &'static str,
+}
+
+/// the force shaders offered by default. The old full shaders in `src/shaders` that
+/// predate the current particle layout are left out, they no longer build
+pub const BUILTIN_SHADERS: &[BuiltinShader] = &[
+    BuiltinShader {
+        name: DEFAULT_COMPUTE_SHADER_NAME,
+        description: "particle life: short-range repulsion, the attraction matrix out to one cell",
+        source: DEFAULT_COMPUTE_SHADER,
+    },
+    BuiltinShader {
+        name: "expernew.wgsl",
+        description: "particle life with a search radius set by the search_grids @param",
+        source: include_str!("shaders/expernew.wgsl"),
+    },
+    BuiltinShader {
+        name: "lennardjones.wgsl",
+        description: "Lennard-Jones-like well scaled by the attraction matrix, without a cutoff",
+        source: include_str!("shaders/lennardjones.wgsl"),
+    },
+    BuiltinShader {
+        name: "buggy.wgsl",
+        description: "particle life that only searches a particle's own cell, kept for comparison",
+        source: include_str!("shaders/buggy.wgsl"),
+    },
+];
+
+/// the built-in shader called `name`
+pub fn builtin(name: &str) -> Option<&'static BuiltinShader> {
+    BUILTIN_SHADERS.iter().find(|shader| shader.name == name)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderEntry {
+    /// file name, as stored in [`Params::shader_name`](crate::Params::shader_name)
+    pub name: String,
+    pub description: String,
+    /// the algorithm it needs, `None` when it runs with any that uses the shader buffer.
+    /// Complete compute shaders bring their own loop over the cell list
+    pub algorithm: Option<Algorithm>,
+    pub source: String,
+    /// the file a user shader was read from, `None` for built-in ones
+    pub path: Option<PathBuf>,
+}
+
+impl ShaderEntry {
+    fn new(name: String, description: String, source: String, path: Option<PathBuf>) -> Self {
+        let algorithm = (!force::is_module(&source)).then_some(Algorithm::UniformGrid);
+        ShaderEntry {
+            name,
+            description,
+            algorithm,
+            source,
+            path,
+        }
+    }
+
+    /// whether the shader can be the force pass of `algorithm`
+    pub fn runs_with(&self, algorithm: Algorithm) -> bool {
+        algorithm.uses_shader_buffer()
+            && !matches!(self.algorithm, Some(needed) if needed != algorithm)
+    }
+}
+
+/// the built-in shaders and those of the user directory, sorted by name
+#[derive(Clone, Debug)]
+pub struct ShaderRegistry {
+    pub user_dir: Option<PathBuf>,
+    entries: Vec<ShaderEntry>,
+}
+
+impl ShaderRegistry {
+    /// the built-in shaders, then every `.wgsl` file in `user_dir` that can be read.
+    /// A directory that does not exist adds nothing
+    pub fn new(user_dir: Option<PathBuf>) -> Self {
+        let mut registry = ShaderRegistry {
+            user_dir: None,
+            entries: Vec::new(),
+        };
+        for shader in BUILTIN_SHADERS {
+            registry.insert(ShaderEntry::new(
+                shader.name.to_string(),
+                shader.description.to_string(),
+                shader.source.to_string(),
+                None,
+            ));
+        }
+        if let Some(dir) = &user_dir {
+            let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("wgsl"))
+                .collect();
+            paths.sort();
+            for path in paths {
+                if let Ok(source) = fs::read_to_string(&path) {
+                    registry.insert_file(&path, source);
+                }
+            }
+        }
+        registry.user_dir = user_dir;
+        registry
+    }
+
+    pub fn entries(&self) -> &[ShaderEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ShaderEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// adds the shader read from `path`, or replaces the one of the same name.
+    /// Its description is the line comment it starts with
+    pub fn insert_file(&mut self, path: &Path, source: String) -> &ShaderEntry {
+        let name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let description = leading_comment(&source);
+        self.insert(ShaderEntry::new(
+            name,
+            description,
+            source,
+            Some(path.to_path_buf()),
+        ))
+    }
+
+    fn insert(&mut self, entry: ShaderEntry) -> &ShaderEntry {
+        let index = match self
+            .entries
+            .binary_search_by(|other| other.name.as_str().cmp(&entry.name))
+        {
+            Ok(index) => {
+                self.entries[index] = entry;
+                index
+            }
+            Err(index) => {
+                self.entries.insert(index, entry);
+                index
+            }
+        };
+        &self.entries[index]
+    }
+}
+
+/// the first run of `//` lines at the top of `source`, joined into one line
+fn leading_comment(source: &str) -> String {
+    source
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .map_while(|line| line.strip_prefix("//"))
+        .map(str::trim)
+        .filter(|line| !line.starts_with("@param"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,200 @@
+// Barnes-Hut gravity over a complete quadtree laid on top of the cell list.
+//
+//   leaf_nodes    mass and mass-weighted position of every grid cell, the tree's deepest level
+//   reduce_nodes  one level up: each node sums its four children, run from the leaves to the root
+//   main          walks the tree per particle, opening nodes that look larger than `theta`
+//
+// Level l has 2^l x 2^l nodes stored row-major from offset (4^l - 1) / 3. The leaves are
+// 2^depth per side with depth = ceil(log2(num_grids_side)); leaves past the grid stay empty.
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+struct TreeParams {
+  theta: f32,
+  gravity: f32,
+  softening: f32,
+  depth: u32,
+  // the level a reduce_nodes dispatch writes
+  level: u32,
+};
+
+// mass, then the mass-weighted position sum
+struct Node {
+  mass: f32,
+  moment: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particlesSrc : array<Particle>;
+@group(0) @binding(2) var<storage, read_write> accelerations : array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read> cell_starts : array<u32>;
+@group(0) @binding(5) var<storage, read> cell_counts : array<u32>;
+@group(0) @binding(6) var<storage, read> sorted_indices : array<u32>;
+@group(0) @binding(7) var<storage, read_write> nodes : array<Node>;
+@group(0) @binding(8) var<uniform> tree : TreeParams;
+
+const STACK_SIZE: u32 = 64u;
+
+fn level_offset(level: u32) -> u32 {
+  return ((1u << (2u * level)) - 1u) / 3u;
+}
+
+fn node_index(level: u32, cell: vec2<u32>) -> u32 {
+  return level_offset(level) + cell.y * (1u << level) + cell.x;
+}
+
+fn num_grids_side() -> u32 {
+  return u32(round(params.world_size / params.grid_size_side));
+}
+
+@compute
+@workgroup_size(64)
+fn leaf_nodes(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let side = 1u << tree.depth;
+  let index = global_invocation_id.x;
+  if (index >= side * side) {
+    return;
+  }
+
+  let cell = vec2<u32>(index % side, index / side);
+  let n = num_grids_side();
+  var node = Node(0.0, vec2<f32>(0.0));
+  if (cell.x < n && cell.y < n) {
+    let grid_cell = cell.y * n + cell.x;
+    let start = cell_starts[grid_cell];
+    let end = start + cell_counts[grid_cell];
+    for (var j = start; j < end; j++) {
+      let other = particlesSrc[sorted_indices[j]];
+      node.mass = node.mass + other.mass;
+      node.moment = node.moment + other.mass * other.pos;
+    }
+  }
+  nodes[node_index(tree.depth, cell)] = node;
+}
+
+@compute
+@workgroup_size(64)
+fn reduce_nodes(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let side = 1u << tree.level;
+  let index = global_invocation_id.x;
+  if (index >= side * side) {
+    return;
+  }
+
+  let cell = vec2<u32>(index % side, index / side);
+  var node = Node(0.0, vec2<f32>(0.0));
+  for (var child = 0u; child < 4u; child++) {
+    let child_cell = 2u * cell + vec2<u32>(child & 1u, child >> 1u);
+    let c = nodes[node_index(tree.level + 1u, child_cell)];
+    node.mass = node.mass + c.mass;
+    node.moment = node.moment + c.moment;
+  }
+  nodes[node_index(tree.level, cell)] = node;
+}
+
+// softened point-mass attraction of `mass` at `offset` from the particle
+fn gravity(offset: vec2<f32>, mass: f32) -> vec2<f32> {
+  let r2 = dot(offset, offset) + tree.softening * tree.softening;
+  return tree.gravity * mass * offset / (r2 * sqrt(r2));
+}
+
+@compute
+@workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particlesSrc);
+  let index = global_invocation_id.x;
+  if (index >= total) {
+    return;
+  }
+
+  var vPos : vec2<f32> = particlesSrc[index].pos;
+  var aAccum : vec2<f32> = vec2<f32>(0.0, 0.0);
+
+  let n = num_grids_side();
+  let own_leaf = vec2<u32>(clamp(vec2<i32>(floor(vPos / params.grid_size_side)), vec2<i32>(0), vec2<i32>(i32(n) - 1)));
+
+  // nodes are pushed as (level, x, y) packed into 5 + 13 + 13 bits
+  var stack : array<u32, 64>;
+  var top = 1u;
+  stack[0] = 0u;
+  loop {
+    if (top == 0u) {
+      break;
+    }
+    top = top - 1u;
+    let entry = stack[top];
+    let level = entry >> 26u;
+    let cell = vec2<u32>((entry >> 13u) & 0x1fffu, entry & 0x1fffu);
+    let node = nodes[node_index(level, cell)];
+    if (node.mass <= 0.0) {
+      continue;
+    }
+
+    // a node holding the particle is always opened, so it never pulls on itself
+    let shift = tree.depth - level;
+    let contains_self = all((own_leaf >> vec2<u32>(shift)) == cell);
+    let size = params.grid_size_side * f32(1u << shift);
+    let offset = separation(vPos, node.moment / node.mass);
+    if (!contains_self && size < tree.theta * length(offset)) {
+      aAccum = aAccum + gravity(offset, node.mass);
+      continue;
+    }
+
+    if (level == tree.depth) {
+      if (cell.x < n && cell.y < n) {
+        let grid_cell = cell.y * n + cell.x;
+        let start = cell_starts[grid_cell];
+        let end = start + cell_counts[grid_cell];
+        for (var j = start; j < end; j++) {
+          let other = sorted_indices[j];
+          if (other == index) {
+            continue;
+          }
+          aAccum = aAccum + gravity(separation(vPos, particlesSrc[other].pos), particlesSrc[other].mass);
+        }
+      }
+      continue;
+    }
+
+    for (var child = 0u; child < 4u && top < STACK_SIZE; child++) {
+      let child_cell = 2u * cell + vec2<u32>(child & 1u, child >> 1u);
+      stack[top] = ((level + 1u) << 26u) | (child_cell.x << 13u) | child_cell.y;
+      top = top + 1u;
+    }
+  }
+
+  accelerations[index] = aAccum;
+}
+
+const BOUNDARY_REFLECT: u32 = 0u;
+const BOUNDARY_PERIODIC: u32 = 1u;
+const BOUNDARY_ABSORB: u32 = 2u;
+const BOUNDARY_OPEN: u32 = 3u;
+
+// shortest vector from a to b, through the edges when the world wraps around
+fn separation(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+  let d = b - a;
+  if (u32(params.boundary) == BOUNDARY_PERIODIC) {
+    return d - params.world_size * round(d / params.world_size);
+  }
+  return d;
+}
@@ -0,0 +1,144 @@
+// Core of the uniform-grid force pass, see force.rs.
+//
+// A force module is spliced in after this file and supplies
+//
+//   fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32>
+//   fn interaction_radius() -> f32     (optional, one cell when left out)
+//
+// `force` returns the acceleration `other` gives `particle`, `r` being the shortest
+// vector from particle to other. `main` walks the cells within the interaction
+// radius of every particle, sums `force` over their particles and leaves the total
+// in `accelerations` for the integration passes. `brute_force` sums over every
+// particle instead and ignores the cell list.
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+struct AttractionMatrixEntry {
+  elem: f32,
+  _pad1: f32,
+  _pad2: f32,
+  _pad3: f32,
+}
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particlesSrc : array<Particle>;
+@group(0) @binding(2) var<storage, read_write> accelerations : array<vec2<f32>>;
+@group(0) @binding(3) var<storage, read> attraction_matrix : array<AttractionMatrixEntry>;
+@group(0) @binding(4) var<storage, read> cell_starts : array<u32>;
+@group(0) @binding(5) var<storage, read> cell_counts : array<u32>;
+@group(0) @binding(6) var<storage, read> sorted_indices : array<u32>;
+
+
+@compute
+@workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particlesSrc);
+  let index = global_invocation_id.x;
+  if (index >= total) {
+    return;
+  }
+
+  let particle = particlesSrc[index];
+  var aAccum : vec2<f32> = vec2<f32>(0.0, 0.0);
+
+  let num_grids_side: i32 = i32(round(params.world_size / params.grid_size_side));
+  let reach = i32(ceil(interaction_radius() / params.grid_size_side));
+  let cell = clamp(vec2<i32>(floor(particle.pos / params.grid_size_side)), vec2<i32>(0), vec2<i32>(num_grids_side - 1));
+
+  // cells within reach, across the edges when the world wraps around; when the reach
+  // covers the whole side, every cell is visited once instead of some twice
+  var lo = max(cell - vec2<i32>(reach), vec2<i32>(0));
+  var hi = min(cell + vec2<i32>(reach), vec2<i32>(num_grids_side - 1));
+  if (u32(params.boundary) == BOUNDARY_PERIODIC && 2 * reach + 1 < num_grids_side) {
+    lo = cell - vec2<i32>(reach);
+    hi = cell + vec2<i32>(reach);
+  }
+
+  for(var y_bucket = lo.y; y_bucket <= hi.y; y_bucket++) {
+    for(var x_bucket = lo.x; x_bucket <= hi.x; x_bucket++) {
+      let wrapped = (vec2<i32>(x_bucket, y_bucket) + num_grids_side) % num_grids_side;
+      let newBucket = u32(wrapped.y * num_grids_side + wrapped.x);
+      let start = cell_starts[newBucket];
+      let end = start + cell_counts[newBucket];
+
+      for(var j = start; j < end; j++) {
+        let other = sorted_indices[j];
+        if (other == index) {
+          continue;
+        }
+        let neighbour = particlesSrc[other];
+        aAccum = aAccum + force(particle, neighbour, separation(particle.pos, neighbour.pos));
+      }
+    }
+  }
+
+  accelerations[index] = aAccum;
+}
+
+// the same sum over every other particle, for Algorithm::BruteForce
+@compute
+@workgroup_size(64)
+fn brute_force(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particlesSrc);
+  let index = global_invocation_id.x;
+  if (index >= total) {
+    return;
+  }
+
+  let particle = particlesSrc[index];
+  var aAccum : vec2<f32> = vec2<f32>(0.0, 0.0);
+  for (var other = 0u; other < total; other++) {
+    if (other == index) {
+      continue;
+    }
+    let neighbour = particlesSrc[other];
+    aAccum = aAccum + force(particle, neighbour, separation(particle.pos, neighbour.pos));
+  }
+
+  accelerations[index] = aAccum;
+}
+
+fn num_types() -> u32 {
+  return u32(sqrt(f32(arrayLength(&attraction_matrix))));
+}
+
+fn kind_index(particle: Particle) -> u32 {
+  return u32(particle.kind * f32(num_types()));
+}
+
+// attraction matrix entry for how `other`'s kind pulls on `particle`'s
+fn attraction(particle: Particle, other: Particle) -> f32 {
+  return attraction_matrix[kind_index(particle) * num_types() + kind_index(other)].elem;
+}
+
+const BOUNDARY_REFLECT: u32 = 0u;
+const BOUNDARY_PERIODIC: u32 = 1u;
+const BOUNDARY_ABSORB: u32 = 2u;
+const BOUNDARY_OPEN: u32 = 3u;
+
+// shortest vector from a to b, through the edges when the world wraps around
+fn separation(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+  let d = b - a;
+  if (u32(params.boundary) == BOUNDARY_PERIODIC) {
+    return d - params.world_size * round(d / params.world_size);
+  }
+  return d;
+}
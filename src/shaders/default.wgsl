@@ -45,7 +45,7 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
      let distance_vector: vec2<f32> = pos - vPos;
      
 //     let vel = particlesSrc[i].vel;
-     var distance = pow(distance_vector, vec2<f32>(2.0, 2.0));
+     var distance = distance_vector * distance_vector;
      var distance_squared: f32 = distance.x + distance.y; 
      var dist = sqrt(distance_squared);
       if (dist < 0.1 ) {
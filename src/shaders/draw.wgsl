@@ -12,6 +12,18 @@ struct Camera {
 // }
 @group(0) @binding(0) var<uniform> camera : Camera;
 
+// linear colour of each particle type, see palette.rs
+struct TypeColours {
+    num_types : f32,
+    colours : array<vec4<f32>, 64>,
+}
+@group(0) @binding(1) var<uniform> type_colours : TypeColours;
+
+struct VertexOutput {
+    @builtin(position) clip_position : vec4<f32>,
+    @location(0) colour : vec3<f32>,
+}
+
 @vertex
 fn main_vs(
     @location(0) particle_pos: vec2<f32>,
@@ -20,7 +32,7 @@ fn main_vs(
     @location(3) kind: f32,
     @location(4) circle_coord: vec2<f32>,
     //@location(2) mass: f32,
-) -> @builtin(position) vec4<f32> {
+) -> VertexOutput {
     let camera_pos_vec = vec2<f32>(camera.x, camera.y);
     // var new_pos: vec2<f32> = vec2<f32>(particle_pos.x + (circle_coord.x * sqrt(mass)), particle_pos.y + (circle_coord.y * sqrt(mass)));
 
@@ -31,11 +43,17 @@ fn main_vs(
         new_pos = vec2<f32>(new_pos.x, new_pos.y / camera.aspect_ratio);
 
     }
-    return vec4<f32>((new_pos - camera_pos_vec) * camera.zoom, kind, 1.0);
+    // kind is the type index over the number of types
+    let index = u32(round(kind * type_colours.num_types)) % 64u;
+
+    var out : VertexOutput;
+    out.clip_position = vec4<f32>((new_pos - camera_pos_vec) * camera.zoom, kind, 1.0);
+    out.colour = type_colours.colours[index].rgb;
+    return out;
 
 }
 
 @fragment
-fn main_fs(@builtin(position) clip_position: vec4<f32>) -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0 - clip_position.z, clip_position.z, clip_position.z, 0.1);
+fn main_fs(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.colour, 0.1);
 }
@@ -0,0 +1,186 @@
+// Cell list for the neighbour search, rebuilt every step:
+//
+//   count_cells       cell id per particle, and its rank inside that cell
+//   scan_blocks       exclusive prefix sum of cell_counts inside each 256-cell block
+//   scan_block_sums   prefix sum of the block totals, run as a single workgroup
+//   add_block_offsets adds each block's offset, giving cell_starts
+//   scatter           sorted_indices[cell_starts[cell] + rank] = particle
+//   sort_cells        (deterministic mode) orders each cell by particle index
+//
+// Force shaders then walk sorted_indices[cell_starts[c] .. cell_starts[c] + cell_counts[c]].
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particles : array<Particle>;
+@group(0) @binding(2) var<storage, read_write> particle_cells : array<u32>;
+@group(0) @binding(3) var<storage, read_write> particle_ranks : array<u32>;
+@group(0) @binding(4) var<storage, read_write> cell_counts : array<atomic<u32>>;
+@group(0) @binding(5) var<storage, read_write> cell_starts : array<u32>;
+@group(0) @binding(6) var<storage, read_write> block_sums : array<u32>;
+@group(0) @binding(7) var<storage, read_write> sorted_indices : array<u32>;
+
+const BLOCK_SIZE: u32 = 256u;
+
+var<workgroup> scratch : array<u32, 256>;
+
+fn num_cells_side() -> u32 {
+  return u32(round(params.world_size / params.grid_size_side));
+}
+
+// particles outside the world are clamped into the border cells
+fn cell_of(position: vec2<f32>) -> u32 {
+  let n = i32(num_cells_side());
+  let cell = clamp(vec2<i32>(floor(position / params.grid_size_side)), vec2<i32>(0), vec2<i32>(n - 1));
+  return u32(cell.y * n + cell.x);
+}
+
+// inclusive Hillis-Steele scan of scratch; every invocation of the workgroup must call it
+fn scan_scratch(local: u32) {
+  for (var offset = 1u; offset < BLOCK_SIZE; offset = offset * 2u) {
+    var add = 0u;
+    if (local >= offset) {
+      add = scratch[local - offset];
+    }
+    workgroupBarrier();
+    scratch[local] = scratch[local] + add;
+    workgroupBarrier();
+  }
+}
+
+@compute
+@workgroup_size(64)
+fn count_cells(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particles)) {
+    return;
+  }
+
+  let cell = cell_of(particles[index].pos);
+  particle_cells[index] = cell;
+  particle_ranks[index] = atomicAdd(&cell_counts[cell], 1u);
+}
+
+@compute
+@workgroup_size(256)
+fn scan_blocks(
+  @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+  @builtin(local_invocation_id) local_invocation_id: vec3<u32>,
+  @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+  let index = global_invocation_id.x;
+  let local = local_invocation_id.x;
+  let total = arrayLength(&cell_starts);
+
+  var value = 0u;
+  if (index < total) {
+    value = atomicLoad(&cell_counts[index]);
+  }
+  scratch[local] = value;
+  workgroupBarrier();
+
+  scan_scratch(local);
+
+  if (index < total) {
+    cell_starts[index] = scratch[local] - value;
+  }
+  if (local == BLOCK_SIZE - 1u) {
+    block_sums[workgroup_id.x] = scratch[local];
+  }
+}
+
+@compute
+@workgroup_size(256)
+fn scan_block_sums(@builtin(local_invocation_id) local_invocation_id: vec3<u32>) {
+  let local = local_invocation_id.x;
+  let total = arrayLength(&block_sums);
+
+  var carry = 0u;
+  for (var base = 0u; base < total; base = base + BLOCK_SIZE) {
+    let index = base + local;
+    var value = 0u;
+    if (index < total) {
+      value = block_sums[index];
+    }
+    scratch[local] = value;
+    workgroupBarrier();
+
+    scan_scratch(local);
+
+    if (index < total) {
+      block_sums[index] = carry + scratch[local] - value;
+    }
+    carry = carry + scratch[BLOCK_SIZE - 1u];
+    workgroupBarrier();
+  }
+}
+
+@compute
+@workgroup_size(256)
+fn add_block_offsets(
+  @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+  @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&cell_starts)) {
+    return;
+  }
+  cell_starts[index] = cell_starts[index] + block_sums[workgroup_id.x];
+}
+
+@compute
+@workgroup_size(64)
+fn scatter(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particles)) {
+    return;
+  }
+
+  let cell = particle_cells[index];
+  sorted_indices[cell_starts[cell] + particle_ranks[index]] = index;
+}
+
+// the ranks handed out by atomicAdd depend on scheduling; sorting each cell makes the
+// order, and so the floating point summation order in the force shaders, reproducible
+@compute
+@workgroup_size(64)
+fn sort_cells(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let cell = global_invocation_id.x;
+  if (cell >= arrayLength(&cell_starts)) {
+    return;
+  }
+
+  let start = cell_starts[cell];
+  let end = start + atomicLoad(&cell_counts[cell]);
+  for (var i = start + 1u; i < end; i = i + 1u) {
+    let key = sorted_indices[i];
+    var j = i;
+    loop {
+      if (j <= start || sorted_indices[j - 1u] <= key) {
+        break;
+      }
+      sorted_indices[j] = sorted_indices[j - 1u];
+      j = j - 1u;
+    }
+    sorted_indices[j] = key;
+  }
+}
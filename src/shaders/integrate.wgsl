@@ -0,0 +1,226 @@
+// Time integration, kept apart from the force shaders. A force pass writes the
+// acceleration of every particle to `accelerations`; the passes here turn those
+// into the next state. Every scheme reads the state at the start of the step
+// from particlesSrc and writes the end of the step to particlesDst. Schemes with
+// more than one force evaluation keep the state the next force pass reads in
+// particlesStage.
+//
+//   euler            v' = (v + a dt) f, x' = x + v' dt
+//   trapezoidal      v' = (v + a dt) f, x' = x + (v + v') / 2 dt
+//   verlet_drift     x' = x + v dt + a dt² / 2, half kick into the stage   (velocity Verlet)
+//   verlet_kick      second half kick with the forces at x'
+//   leapfrog_drift   half drift into the stage                             (leapfrog)
+//   leapfrog_kick    full kick with the forces at the half step, second half drift
+//   rk4_stage        adds one weighted derivative to `derivatives`, moves the stage to the next point
+//   rk4_finish       adds the last derivative and takes the full step
+//
+// f is friction_coeff, applied once per step to the final velocity. The edges of
+// the world are only applied to the final state; stage states are wrapped into
+// the world when it is periodic so the cell list and `separation` stay valid.
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+// one Runge-Kutta stage
+struct StageParams {
+  // weight of this stage's derivative in the final step
+  weight: f32,
+  // how far along the step the next stage sits, as a fraction of dt
+  fraction: f32,
+  // 1 when the forces were taken at particlesSrc rather than the stage, which also restarts the sum
+  first: u32,
+  _pad: u32,
+};
+
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particlesSrc : array<Particle>;
+@group(0) @binding(2) var<storage, read_write> particlesStage : array<Particle>;
+@group(0) @binding(3) var<storage, read_write> particlesDst : array<Particle>;
+@group(0) @binding(4) var<storage, read> accelerations : array<vec2<f32>>;
+// (dx/dt, dv/dt) summed over the stages so far
+@group(0) @binding(5) var<storage, read_write> derivatives : array<vec4<f32>>;
+@group(0) @binding(6) var<uniform> stage : StageParams;
+
+fn write_stage(index: u32, pos: vec2<f32>, vel: vec2<f32>) {
+  var wrapped = pos;
+  if (u32(params.boundary) == BOUNDARY_PERIODIC) {
+    wrapped = apply_boundary(pos, vel).pos;
+  }
+  let p = particlesSrc[index];
+  particlesStage[index] = Particle(wrapped, vel, p.mass, p.kind, p.id, p.bptr, p.debug);
+}
+
+fn write_step(index: u32, pos: vec2<f32>, vel: vec2<f32>) {
+  let bounded = apply_boundary(pos, vel);
+  let p = particlesSrc[index];
+  particlesDst[index] = Particle(bounded.pos, bounded.vel, p.mass, p.kind, p.id, p.bptr, p.debug);
+}
+
+@compute
+@workgroup_size(64)
+fn euler(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let p = particlesSrc[index];
+  let vel = (p.vel + accelerations[index] * params.dt) * params.friction_coeff;
+  write_step(index, p.pos + vel * params.dt, vel);
+}
+
+@compute
+@workgroup_size(64)
+fn trapezoidal(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let p = particlesSrc[index];
+  let vel = (p.vel + accelerations[index] * params.dt) * params.friction_coeff;
+  write_step(index, p.pos + (p.vel + vel) / 2.0 * params.dt, vel);
+}
+
+@compute
+@workgroup_size(64)
+fn verlet_drift(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let p = particlesSrc[index];
+  let half_vel = p.vel + 0.5 * params.dt * accelerations[index];
+  write_stage(index, p.pos + half_vel * params.dt, half_vel);
+}
+
+@compute
+@workgroup_size(64)
+fn verlet_kick(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let s = particlesStage[index];
+  let vel = (s.vel + 0.5 * params.dt * accelerations[index]) * params.friction_coeff;
+  write_step(index, s.pos, vel);
+}
+
+@compute
+@workgroup_size(64)
+fn leapfrog_drift(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let p = particlesSrc[index];
+  write_stage(index, p.pos + 0.5 * params.dt * p.vel, p.vel);
+}
+
+@compute
+@workgroup_size(64)
+fn leapfrog_kick(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let p = particlesSrc[index];
+  let vel = (p.vel + params.dt * accelerations[index]) * params.friction_coeff;
+  write_step(index, particlesStage[index].pos + 0.5 * params.dt * vel, vel);
+}
+
+// derivative at the state the last force pass read
+fn derivative(index: u32) -> vec4<f32> {
+  var vel = particlesStage[index].vel;
+  if (stage.first == 1u) {
+    vel = particlesSrc[index].vel;
+  }
+  return vec4<f32>(vel, accelerations[index]);
+}
+
+@compute
+@workgroup_size(64)
+fn rk4_stage(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let k = derivative(index);
+  var sum = stage.weight * k;
+  if (stage.first == 0u) {
+    sum = derivatives[index] + sum;
+  }
+  derivatives[index] = sum;
+
+  let p = particlesSrc[index];
+  let h = stage.fraction * params.dt;
+  write_stage(index, p.pos + h * k.xy, p.vel + h * k.zw);
+}
+
+@compute
+@workgroup_size(64)
+fn rk4_finish(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+
+  let d = derivatives[index] + stage.weight * derivative(index);
+  let p = particlesSrc[index];
+  let vel = (p.vel + params.dt * d.zw) * params.friction_coeff;
+  write_step(index, p.pos + params.dt * d.xy, vel);
+}
+
+const BOUNDARY_REFLECT: u32 = 0u;
+const BOUNDARY_PERIODIC: u32 = 1u;
+const BOUNDARY_ABSORB: u32 = 2u;
+const BOUNDARY_OPEN: u32 = 3u;
+
+struct Kinematics {
+  pos: vec2<f32>,
+  vel: vec2<f32>,
+};
+
+fn apply_boundary(pos: vec2<f32>, vel: vec2<f32>) -> Kinematics {
+  let mode = u32(params.boundary);
+  if (mode == BOUNDARY_OPEN) {
+    return Kinematics(pos, vel);
+  }
+  if (mode == BOUNDARY_PERIODIC) {
+    var wrapped = pos - params.world_size * floor(pos / params.world_size);
+    // tiny negative positions round up to exactly world_size
+    wrapped = select(wrapped, vec2<f32>(0.0), wrapped >= vec2<f32>(params.world_size));
+    return Kinematics(wrapped, vel);
+  }
+
+  let fudge = 0.00001;
+  let hit = pos < vec2<f32>(fudge) | pos > vec2<f32>(params.world_size - fudge);
+  let clamped = clamp(pos, vec2<f32>(fudge), vec2<f32>(params.world_size - fudge));
+  if (mode == BOUNDARY_ABSORB) {
+    return Kinematics(clamped, select(vel, vec2<f32>(0.0), any(hit)));
+  }
+  return Kinematics(clamped, select(vel, -vel, hit));
+}
@@ -0,0 +1,197 @@
+// Reorders the particles along a Z-order curve over the grid cells, so particles that are
+// close in space are also close in memory and the neighbour loops read nearby cache lines.
+//
+//   morton_keys     key = interleaved bits of the cell coordinates, value = particle index
+//   histogram       per 256-key block count of the current 4 bit digit
+//   scan_histogram  exclusive prefix sum over the digit-major histogram, single workgroup
+//   scatter         stable scatter of keys and values by digit
+//   reorder         particlesDst[i] = particlesSrc[values[i]]
+//
+// histogram / scan_histogram / scatter run once per digit (an LSD radix sort), with the
+// digit's bit offset in `sort_pass.shift`. Keys and values ping-pong between the in and out buffers.
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+struct SortPass {
+  shift: u32,
+};
+
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particlesSrc : array<Particle>;
+@group(0) @binding(2) var<storage, read_write> particlesDst : array<Particle>;
+@group(0) @binding(3) var<storage, read> keys_in : array<u32>;
+@group(0) @binding(4) var<storage, read> values_in : array<u32>;
+@group(0) @binding(5) var<storage, read_write> keys_out : array<u32>;
+@group(0) @binding(6) var<storage, read_write> values_out : array<u32>;
+@group(0) @binding(7) var<storage, read_write> histograms : array<u32>;
+@group(1) @binding(0) var<uniform> sort_pass : SortPass;
+
+const BLOCK_SIZE: u32 = 256u;
+const RADIX: u32 = 16u;
+
+var<workgroup> scratch : array<u32, 256>;
+var<workgroup> digit_counts : array<atomic<u32>, 16>;
+
+// spreads the low 16 bits of x over the even bits
+fn part_by_one(x: u32) -> u32 {
+  var v = x & 0x0000ffffu;
+  v = (v | (v << 8u)) & 0x00ff00ffu;
+  v = (v | (v << 4u)) & 0x0f0f0f0fu;
+  v = (v | (v << 2u)) & 0x33333333u;
+  v = (v | (v << 1u)) & 0x55555555u;
+  return v;
+}
+
+fn morton_code(position: vec2<f32>) -> u32 {
+  let n = i32(round(params.world_size / params.grid_size_side));
+  let cell = clamp(vec2<i32>(floor(position / params.grid_size_side)), vec2<i32>(0), vec2<i32>(n - 1));
+  return part_by_one(u32(cell.x)) | (part_by_one(u32(cell.y)) << 1u);
+}
+
+fn digit_of(key: u32) -> u32 {
+  return (key >> sort_pass.shift) & (RADIX - 1u);
+}
+
+fn num_blocks() -> u32 {
+  return arrayLength(&histograms) / RADIX;
+}
+
+// inclusive Hillis-Steele scan of scratch; every invocation of the workgroup must call it
+fn scan_scratch(local: u32) {
+  for (var offset = 1u; offset < BLOCK_SIZE; offset = offset * 2u) {
+    var add = 0u;
+    if (local >= offset) {
+      add = scratch[local - offset];
+    }
+    workgroupBarrier();
+    scratch[local] = scratch[local] + add;
+    workgroupBarrier();
+  }
+}
+
+@compute
+@workgroup_size(64)
+fn morton_keys(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesSrc)) {
+    return;
+  }
+  keys_out[index] = morton_code(particlesSrc[index].pos);
+  values_out[index] = index;
+}
+
+@compute
+@workgroup_size(256)
+fn histogram(
+  @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+  @builtin(local_invocation_id) local_invocation_id: vec3<u32>,
+  @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+  let index = global_invocation_id.x;
+  let local = local_invocation_id.x;
+
+  if (local < RADIX) {
+    atomicStore(&digit_counts[local], 0u);
+  }
+  workgroupBarrier();
+
+  if (index < arrayLength(&keys_in)) {
+    atomicAdd(&digit_counts[digit_of(keys_in[index])], 1u);
+  }
+  workgroupBarrier();
+
+  if (local < RADIX) {
+    histograms[local * num_blocks() + workgroup_id.x] = atomicLoad(&digit_counts[local]);
+  }
+}
+
+@compute
+@workgroup_size(256)
+fn scan_histogram(@builtin(local_invocation_id) local_invocation_id: vec3<u32>) {
+  let local = local_invocation_id.x;
+  let total = arrayLength(&histograms);
+
+  var carry = 0u;
+  for (var base = 0u; base < total; base = base + BLOCK_SIZE) {
+    let index = base + local;
+    var value = 0u;
+    if (index < total) {
+      value = histograms[index];
+    }
+    scratch[local] = value;
+    workgroupBarrier();
+
+    scan_scratch(local);
+
+    if (index < total) {
+      histograms[index] = carry + scratch[local] - value;
+    }
+    carry = carry + scratch[BLOCK_SIZE - 1u];
+    workgroupBarrier();
+  }
+}
+
+@compute
+@workgroup_size(256)
+fn scatter(
+  @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+  @builtin(local_invocation_id) local_invocation_id: vec3<u32>,
+  @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+  let index = global_invocation_id.x;
+  let local = local_invocation_id.x;
+  let total = arrayLength(&keys_in);
+
+  // digits past the end get a value no real digit has, so they never count towards a rank
+  var digit = RADIX;
+  if (index < total) {
+    digit = digit_of(keys_in[index]);
+  }
+  scratch[local] = digit;
+  workgroupBarrier();
+
+  if (index >= total) {
+    return;
+  }
+
+  // keys of the same digit keep their order, which is what makes the radix sort correct
+  var rank = 0u;
+  for (var i = 0u; i < local; i = i + 1u) {
+    if (scratch[i] == digit) {
+      rank = rank + 1u;
+    }
+  }
+
+  let dst = histograms[digit * num_blocks() + workgroup_id.x] + rank;
+  keys_out[dst] = keys_in[index];
+  values_out[dst] = values_in[index];
+}
+
+@compute
+@workgroup_size(64)
+fn reorder(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= arrayLength(&particlesDst)) {
+    return;
+  }
+  particlesDst[index] = particlesSrc[values_in[index]];
+}
@@ -0,0 +1,200 @@
+// Particle-mesh gravity: the field is solved on a mesh whose nodes sit at the cell centres.
+//
+//   deposit     cloud-in-cell mass of every node, gathered from the 3x3 cells around it
+//   fft_pass    one radix-2 Stockham pass over every row or column of the mesh
+//   apply_kernel  multiplies the mass spectrum by the field kernel (see mesh.rs)
+//   main        interpolates the field back to each particle with the same weights
+//
+// The mesh is `side` nodes per side: the grid itself when the world wraps around,
+// or the grid zero-padded to twice its size for an isolated system. It holds two
+// complex halves that the FFT passes ping-pong between; after the inverse passes
+// the field is (ax, ay) in the real and imaginary parts of half `field_half`.
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+struct MeshPass {
+  // nodes per side of the padded mesh and of the grid it covers
+  side: u32,
+  grid: u32,
+  // size of the sub-transforms this FFT pass merges
+  span: u32,
+  // 0 transforms rows, 1 columns
+  axis: u32,
+  inverse: u32,
+  // the half a pass reads from
+  src_half: u32,
+  // the half holding the field once every pass has run
+  field_half: u32,
+  _pad: u32,
+};
+
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particlesSrc : array<Particle>;
+@group(0) @binding(2) var<storage, read_write> accelerations : array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read> cell_starts : array<u32>;
+@group(0) @binding(5) var<storage, read> cell_counts : array<u32>;
+@group(0) @binding(6) var<storage, read> sorted_indices : array<u32>;
+@group(0) @binding(7) var<storage, read_write> nodes : array<vec2<f32>>;
+@group(0) @binding(8) var<uniform> pass_params : MeshPass;
+@group(0) @binding(9) var<storage, read> field_kernel : array<vec2<f32>>;
+
+fn periodic() -> bool {
+  return u32(params.boundary) == BOUNDARY_PERIODIC;
+}
+
+// position in node units; without wrap-around, the outer half cells collapse onto the edge nodes
+fn mesh_coord(pos: vec2<f32>) -> vec2<f32> {
+  let f = pos / params.grid_size_side - 0.5;
+  if (periodic()) {
+    return f;
+  }
+  return clamp(f, vec2<f32>(0.0), vec2<f32>(f32(pass_params.grid) - 1.0));
+}
+
+fn half_offset(which: u32) -> u32 {
+  return which * pass_params.side * pass_params.side;
+}
+
+@compute
+@workgroup_size(64)
+fn deposit(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let grid = pass_params.grid;
+  let index = global_invocation_id.x;
+  if (index >= grid * grid) {
+    return;
+  }
+
+  let node = vec2<u32>(index % grid, index / grid);
+  var mass = 0.0;
+  for (var dy = -1; dy <= 1; dy++) {
+    for (var dx = -1; dx <= 1; dx++) {
+      var cell = vec2<i32>(node) + vec2<i32>(dx, dy);
+      if (periodic()) {
+        cell = (cell + i32(grid)) % i32(grid);
+      } else if (any(cell < vec2<i32>(0)) || any(cell >= vec2<i32>(i32(grid)))) {
+        continue;
+      }
+
+      let grid_cell = u32(cell.y) * grid + u32(cell.x);
+      let start = cell_starts[grid_cell];
+      let end = start + cell_counts[grid_cell];
+      for (var j = start; j < end; j++) {
+        let other = particlesSrc[sorted_indices[j]];
+        var d = mesh_coord(other.pos) - vec2<f32>(node);
+        if (periodic()) {
+          d = d - f32(grid) * round(d / f32(grid));
+        }
+        let w = max(vec2<f32>(0.0), 1.0 - abs(d));
+        mass = mass + other.mass * w.x * w.y;
+      }
+    }
+  }
+  nodes[node.y * pass_params.side + node.x] = vec2<f32>(mass, 0.0);
+}
+
+fn complex_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+  return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// element `n` of mesh line `line` along the pass axis
+fn line_element(line: u32, n: u32) -> u32 {
+  if (pass_params.axis == 0u) {
+    return line * pass_params.side + n;
+  }
+  return n * pass_params.side + line;
+}
+
+@compute
+@workgroup_size(64)
+fn fft_pass(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let side = pass_params.side;
+  let half_side = side / 2u;
+  let index = global_invocation_id.x;
+  if (index >= side * half_side) {
+    return;
+  }
+
+  let line = index / half_side;
+  let j = index % half_side;
+  let span = pass_params.span;
+  let src = half_offset(pass_params.src_half);
+  let dst = half_offset(1u - pass_params.src_half);
+
+  let a = nodes[src + line_element(line, j)];
+  var b = nodes[src + line_element(line, j + half_side)];
+  let k = j % span;
+  let direction = select(-1.0, 1.0, pass_params.inverse == 1u);
+  let angle = direction * 6.2831855 * f32(k) / f32(2u * span);
+  b = complex_mul(b, vec2<f32>(cos(angle), sin(angle)));
+
+  let dst_index = (j / span) * 2u * span + k;
+  nodes[dst + line_element(line, dst_index)] = a + b;
+  nodes[dst + line_element(line, dst_index + span)] = a - b;
+}
+
+@compute
+@workgroup_size(64)
+fn apply_kernel(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = global_invocation_id.x;
+  if (index >= pass_params.side * pass_params.side) {
+    return;
+  }
+
+  let offset = half_offset(pass_params.src_half);
+  nodes[offset + index] = complex_mul(nodes[offset + index], field_kernel[index]);
+}
+
+fn field_at(node: vec2<i32>) -> vec2<f32> {
+  var wrapped = node;
+  if (periodic()) {
+    wrapped = (node + i32(pass_params.grid)) % i32(pass_params.grid);
+  }
+  return nodes[half_offset(pass_params.field_half) + u32(wrapped.y) * pass_params.side + u32(wrapped.x)];
+}
+
+@compute
+@workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particlesSrc);
+  let index = global_invocation_id.x;
+  if (index >= total) {
+    return;
+  }
+
+  var vPos : vec2<f32> = particlesSrc[index].pos;
+
+  let f = mesh_coord(vPos);
+  let base = floor(f);
+  let t = f - base;
+  let node = vec2<i32>(base);
+  let aAccum = field_at(node) * (1.0 - t.x) * (1.0 - t.y)
+    + field_at(node + vec2<i32>(1, 0)) * t.x * (1.0 - t.y)
+    + field_at(node + vec2<i32>(0, 1)) * (1.0 - t.x) * t.y
+    + field_at(node + vec2<i32>(1, 1)) * t.x * t.y;
+
+  accelerations[index] = aAccum;
+}
+
+const BOUNDARY_REFLECT: u32 = 0u;
+const BOUNDARY_PERIODIC: u32 = 1u;
+const BOUNDARY_ABSORB: u32 = 2u;
+const BOUNDARY_OPEN: u32 = 3u;
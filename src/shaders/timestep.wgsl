@@ -0,0 +1,127 @@
+// Adaptive timestep and the simulated clock.
+//
+//   reduce_maxima  largest |v| and |a| of each 256-particle block, into `maxima`
+//   choose_dt      reduces the block maxima, picks dt and advances the clock
+//   advance_clock  advances the clock by params.dt when dt is fixed
+//
+// The adaptive dt is a CFL-like bound: no particle may move more than `courant`
+// particle radii in one step, whether carried by its velocity or by its acceleration,
+//
+//   dt = courant * min(radius / max|v|, sqrt(radius / max|a|))
+//
+// clamped to [dt_min, dt_max]. The simulation copies `clock.dt` into params.dt
+// before the integration passes read it.
+
+struct Particle {
+  pos : vec2<f32>,
+  vel : vec2<f32>,
+  mass: f32,
+  kind: f32,
+  id: u32,
+  bptr: f32,
+  debug: f32,
+};
+
+struct SimParams {
+  world_size: f32,
+  dt : f32,
+  well_depth : f32,
+  attract_coeff : f32,
+  repulse_coeff: f32,
+  friction_coeff: f32,
+  grid_size_side: f32,
+  boundary: f32,
+};
+
+struct DtBounds {
+  dt_min: f32,
+  dt_max: f32,
+  courant: f32,
+  radius: f32,
+};
+
+// dt of the last step and the time simulated so far, summed with Kahan compensation
+// so small steps still add up over long runs
+struct Clock {
+  dt: f32,
+  time: f32,
+  compensation: f32,
+  _pad: f32,
+};
+
+@group(0) @binding(0) var<uniform> params : SimParams;
+@group(0) @binding(1) var<storage, read> particles : array<Particle>;
+@group(0) @binding(2) var<storage, read> accelerations : array<vec2<f32>>;
+// (max |v|, max |a|) of each block
+@group(0) @binding(3) var<storage, read_write> maxima : array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read_write> clock : Clock;
+@group(0) @binding(5) var<uniform> bounds : DtBounds;
+
+const BLOCK_SIZE: u32 = 256u;
+
+var<workgroup> scratch : array<vec2<f32>, 256>;
+
+// tree reduction of scratch into scratch[0]; every invocation of the workgroup must call it
+fn reduce_scratch(local: u32) {
+  for (var stride = BLOCK_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+    workgroupBarrier();
+    if (local < stride) {
+      scratch[local] = max(scratch[local], scratch[local + stride]);
+    }
+  }
+  workgroupBarrier();
+}
+
+@compute
+@workgroup_size(256)
+fn reduce_maxima(
+  @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+  @builtin(local_invocation_id) local_invocation_id: vec3<u32>,
+  @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+  let index = global_invocation_id.x;
+  let local = local_invocation_id.x;
+  var value = vec2<f32>(0.0);
+  if (index < arrayLength(&particles)) {
+    value = vec2<f32>(length(particles[index].vel), length(accelerations[index]));
+  }
+  scratch[local] = value;
+  reduce_scratch(local);
+  if (local == 0u) {
+    maxima[workgroup_id.x] = scratch[0];
+  }
+}
+
+fn advance(dt: f32) {
+  let y = dt - clock.compensation;
+  let t = clock.time + y;
+  clock.compensation = (t - clock.time) - y;
+  clock.time = t;
+  clock.dt = dt;
+}
+
+@compute
+@workgroup_size(256)
+fn choose_dt(@builtin(local_invocation_id) local_invocation_id: vec3<u32>) {
+  let local = local_invocation_id.x;
+  let blocks = (arrayLength(&particles) + BLOCK_SIZE - 1u) / BLOCK_SIZE;
+  var value = vec2<f32>(0.0);
+  for (var block = local; block < blocks; block = block + BLOCK_SIZE) {
+    value = max(value, maxima[block]);
+  }
+  scratch[local] = value;
+  reduce_scratch(local);
+
+  if (local == 0u) {
+    let fastest = scratch[0];
+    let by_velocity = bounds.radius / max(fastest.x, 1e-20);
+    let by_acceleration = sqrt(bounds.radius / max(fastest.y, 1e-20));
+    advance(clamp(bounds.courant * min(by_velocity, by_acceleration), bounds.dt_min, bounds.dt_max));
+  }
+}
+
+@compute
+@workgroup_size(1)
+fn advance_clock() {
+  advance(params.dt);
+}
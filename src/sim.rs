@@ -11,11 +11,14 @@ use winit::{
     event_loop::{ControlFlow, EventLoop},
 };
 
+/// how often the shader folder is checked for saved shaders
+const SHADER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
 #[allow(dead_code)]
 pub fn cast_slice<T>(data: &[T]) -> &[u8] {
-    use std::{mem::size_of, slice::from_raw_parts};
+    use std::slice::from_raw_parts;
 
-    unsafe { from_raw_parts(data.as_ptr() as *const u8, data.len() * size_of::<T>()) }
+    unsafe { from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
 }
 
 #[allow(dead_code)]
@@ -24,8 +27,12 @@ pub enum ShaderStage {
     Fragment,
     Compute,
 }
-use crate::state::State;
+use eden::shader_registry::ShaderRegistry;
+use eden::trajectory::{TrajectoryFormat, TrajectoryRecorder};
+use eden::watch::ShaderWatcher;
 
+use super::capture::{self, CaptureTarget, PngSequence};
+use super::cli::Start;
 use super::gui;
 use super::state;
 
@@ -40,7 +47,7 @@ struct Setup {
     queue: wgpu::Queue,
 }
 
-async fn setup(_title: &str) -> Setup {
+async fn setup(_title: &str, width: u32, height: u32) -> Setup {
     #[cfg(not(target_arch = "wasm32"))]
     {
         env_logger::init();
@@ -56,12 +63,9 @@ async fn setup(_title: &str) -> Setup {
         .with_visible(true)
         .with_title("The universe, with a heck of a lot of rounding errors")
         //   .with_fullscreen(video_mode.map(|vm| winit::window::Fullscreen::Exclusive(vm)));
-        .with_inner_size(winit::dpi::PhysicalSize {
-            width: 1920,
-            height: 1080,
-        });
+        .with_inner_size(winit::dpi::PhysicalSize { width, height });
 
-    #[cfg(windows_OFF)] // TODO
+    #[cfg(any())] // TODO: windows only
     {
         use winit::platform::windows::WindowBuilderExtWindows;
         builder = builder.with_no_redirection_bitmap(true);
@@ -102,7 +106,7 @@ async fn setup(_title: &str) -> Setup {
         required_features - adapter_features
     );
 
-    let required_downlevel_capabilities = State::required_downlevel_capabilities();
+    let required_downlevel_capabilities = eden::Simulation::required_downlevel_capabilities();
     let downlevel_capabilities = adapter.get_downlevel_capabilities();
     assert!(
         downlevel_capabilities.shader_model >= required_downlevel_capabilities.shader_model,
@@ -118,7 +122,7 @@ async fn setup(_title: &str) -> Setup {
     );
 
     // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the surface.
-    let needed_limits = state::State::required_limits().using_resolution(adapter.limits());
+    let needed_limits = eden::Simulation::required_limits().using_resolution(adapter.limits());
 
     let trace_dir = std::env::var("WGPU_TRACE");
     let (device, queue) = adapter
@@ -156,6 +160,8 @@ fn start(
         device,
         queue,
     }: Setup,
+    start: Start,
+    shaders: ShaderRegistry,
 ) {
     let _spawner = Spawner::new();
     let mut config = wgpu::SurfaceConfiguration {
@@ -169,21 +175,56 @@ fn start(
     };
     surface.configure(&device, &config);
 
-    let mut test_ui = gui::Gui::new(&window, &device, &config);
     log::info!("Initializing the example...");
 
-    let params: eden::Params = eden::Params::new();
+    // a registry without a user directory has nothing to watch
+    let shader_dir = shaders.user_dir.clone().unwrap_or_default();
+    let (mut test_ui, mut example) = match start {
+        Start::Fresh(params) => {
+            let state = match state::State::try_init(params.clone(), &config, &device) {
+                Ok(state) => state,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let ui = gui::Gui::new(&window, &device, &config, params, shaders);
+            (ui, state)
+        }
+        Start::Resume(snapshot) => {
+            let sim = match snapshot.to_simulation(&device, &queue) {
+                Ok(sim) => sim,
+                Err(e) => {
+                    eprintln!("error: could not resume: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let ui = gui::Gui::new(&window, &device, &config, snapshot.params.clone(), shaders);
+            let mut state = state::State::from_simulation(sim, &config, &device);
+            if let Some(camera) = snapshot.camera {
+                state.set_camera(camera, &queue);
+            }
+            (ui, state)
+        }
+    };
 
-    let mut example = state::State::init(params, &config, &adapter, &device, &queue);
+    let mut recorder: Option<TrajectoryRecorder> = None;
+    let mut capture_target: Option<CaptureTarget> = None;
+    let mut png_sequence: Option<PngSequence> = None;
+    let mut last_captured_step: Option<u64> = None;
 
     let mut last_frame_inst = Instant::now();
     let (mut frame_count, mut accum_time) = (0, 0.0);
+    // the clock is copied back in the background, the panel shows the last one that arrived
+    let (clock_sender, clock_receiver) = std::sync::mpsc::channel();
+    let mut shader_watcher = ShaderWatcher::new(shader_dir);
+    let mut last_shader_poll = Instant::now();
     let _frame_rate: f32 = 0.0;
 
     log::info!("Entering render loop...");
 
-    let mut mouseState: bool = false;
-    let mut lastMousePosition: PhysicalPosition<f64> = PhysicalPosition { x: -1.0, y: -1.0 };
+    let mut mouse_state: bool = false;
+    let mut last_mouse_position: PhysicalPosition<f64> = PhysicalPosition { x: -1.0, y: -1.0 };
 
     //antialiasing
     //let mut smaa_target = SmaaTarget::new(&device, &queue, size.width.max(1), size.height.max(1), config.format, smaa::SmaaMode::Smaa1X);
@@ -221,8 +262,12 @@ fn start(
                             ..
                         },
                     ..
+                } => {
+                    if !test_ui.platform.context().wants_keyboard_input() {
+                        *control_flow = ControlFlow::Exit;
+                    }
                 }
-                | WindowEvent::CloseRequested => {
+                WindowEvent::CloseRequested => {
                     *control_flow = ControlFlow::Exit;
                 }
                 WindowEvent::KeyboardInput {
@@ -234,38 +279,51 @@ fn start(
                         },
                     ..
                 } => {
-                    let params = test_ui.gen_params();
-                    example = state::State::init(params, &config, &adapter, &device, &queue);
+                    if !test_ui.platform.context().wants_keyboard_input() {
+                        stop_recording(&mut recorder, &mut test_ui, &device);
+                        reload(&mut example, &mut test_ui, &config, &device);
+                    }
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::P),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    if !test_ui.platform.context().wants_keyboard_input() {
+                        test_ui.state = gui::OutputState::Screenshot;
+                    }
                 }
 
                 WindowEvent::MouseWheel { delta, .. } => {
-                    match delta {
-                        event::MouseScrollDelta::LineDelta(_x, y) => {
-                            example.camera.zoom *= f32::powf(1.25, y);
-                            //println!("New camera zoom: {:?}", example.camera.zoom);
-                            queue.write_buffer(
-                                &(example.camera_uniform_buffer),
-                                0,
-                                bytemuck::cast_slice(&[example.camera.to_slice()]),
-                            );
-                        }
-                        _ => {}
+                    if let event::MouseScrollDelta::LineDelta(_x, y) = delta {
+                        example.camera.zoom *= f32::powf(1.25, y);
+                        //println!("New camera zoom: {:?}", example.camera.zoom);
+                        queue.write_buffer(
+                            &(example.camera_uniform_buffer),
+                            0,
+                            bytemuck::cast_slice(&[example.camera.to_slice()]),
+                        );
                     }
                 }
                 WindowEvent::MouseInput {
                     device_id: _,
                     state,
                     button,
-                    modifiers: _,
+                    ..
                 } => {
                     match button {
                         MouseButton::Right => match state {
                             ElementState::Pressed => {
-                                mouseState = true;
+                                mouse_state = true;
                             }
                             ElementState::Released => {
-                                mouseState = false;
-                                lastMousePosition = PhysicalPosition::<f64> { x: -1.0, y: 0.0 };
+                                mouse_state = false;
+                                last_mouse_position = PhysicalPosition::<f64> { x: -1.0, y: 0.0 };
                             }
                         },
                         MouseButton::Left => {
@@ -277,19 +335,19 @@ fn start(
                 WindowEvent::CursorMoved {
                     device_id: _,
                     position,
-                    modifiers: _,
+                    ..
                 } => {
-                    if mouseState {
-                        if lastMousePosition.x != -1.0 {
-                            let deltaPosition = PhysicalPosition::<f64> {
-                                x: (position.x - lastMousePosition.x) / (config.width as f64),
-                                y: (position.y - lastMousePosition.y) / (config.height as f64),
+                    if mouse_state {
+                        if last_mouse_position.x != -1.0 {
+                            let delta_position = PhysicalPosition::<f64> {
+                                x: (position.x - last_mouse_position.x) / (config.width as f64),
+                                y: (position.y - last_mouse_position.y) / (config.height as f64),
                             };
 
                             example.camera.x -=
-                                (deltaPosition.x as f32 / example.camera.zoom) * 2.0;
+                                (delta_position.x as f32 / example.camera.zoom) * 2.0;
                             example.camera.y +=
-                                (deltaPosition.y as f32 / example.camera.zoom) * 2.0;
+                                (delta_position.y as f32 / example.camera.zoom) * 2.0;
 
                             queue.write_buffer(
                                 &(example.camera_uniform_buffer),
@@ -297,9 +355,9 @@ fn start(
                                 bytemuck::cast_slice(&[example.camera.to_slice()]),
                             );
 
-                            lastMousePosition = position;
+                            last_mouse_position = position;
                         } else {
-                            lastMousePosition = position;
+                            last_mouse_position = position;
                         }
                     }
                 }
@@ -315,6 +373,10 @@ fn start(
                     frame_count += 1;
                     if frame_count == 10 {
                         test_ui.frame_rate = accum_time * 1000.0 / frame_count as f32;
+                        let sender = clock_sender.clone();
+                        example.sim.request_clock(&device, &queue, move |clock| {
+                            let _ = sender.send(clock);
+                        });
 
                         // println!(
                         //     "Avg frame time {}ms",
@@ -323,6 +385,17 @@ fn start(
                         accum_time = 0.0;
                         frame_count = 0;
                     }
+                    device.poll(wgpu::Maintain::Poll);
+                    while let Ok(clock) = clock_receiver.try_recv() {
+                        test_ui.effective_dt = clock.dt;
+                        test_ui.simulated_time = clock.time;
+                    }
+                }
+                if last_shader_poll.elapsed() >= SHADER_POLL_INTERVAL {
+                    last_shader_poll = Instant::now();
+                    for path in shader_watcher.poll() {
+                        hot_reload(&path, &mut example, &mut test_ui, &device);
+                    }
                 }
 
                 let frame = match surface.get_current_texture() {
@@ -357,13 +430,131 @@ fn start(
 
                 let msaaview = depthframe.create_view(&wgpu::TextureViewDescriptor::default());
 
+                // sliders take effect on the running simulation, the rest waits for a restart
+                test_ui.pending_restart = example.sim.update_params(test_ui.params(), &queue);
+                example.update_drawing(&queue);
+
                 match test_ui.state {
                     gui::OutputState::ReloadRequired => {
-                        let params = test_ui.gen_params();
-                        example = state::State::init(params, &config, &adapter, &device, &queue);
+                        stop_recording(&mut recorder, &mut test_ui, &device);
+                        reload(&mut example, &mut test_ui, &config, &device);
+                    }
+                    gui::OutputState::ApplyShader => {
+                        let source = test_ui.editor_source.clone();
+                        match example.sim.try_set_shader(source, &device) {
+                            Ok(()) => {
+                                test_ui.shader_applied();
+                                test_ui.scenario_status = format!(
+                                    "applied the edited shader at step {}",
+                                    example.sim.step_count
+                                );
+                            }
+                            Err(reason) => test_ui.shader_error = Some(reason),
+                        }
+                    }
+                    gui::OutputState::SaveSnapshot => {
+                        let path = std::path::Path::new(&test_ui.snapshot_path);
+                        let snapshot = eden::snapshot::Snapshot::capture(
+                            &example.sim,
+                            Some(example.camera),
+                            &device,
+                            &queue,
+                        );
+                        test_ui.snapshot_status = match snapshot.save(path) {
+                            Ok(()) => {
+                                format!("saved step {} to {}", snapshot.step_count, path.display())
+                            }
+                            Err(e) => e.to_string(),
+                        };
+                    }
+                    gui::OutputState::LoadSnapshot => {
+                        stop_recording(&mut recorder, &mut test_ui, &device);
+                        let path = std::path::Path::new(&test_ui.snapshot_path);
+                        let loaded = eden::snapshot::Snapshot::load(path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|snapshot| {
+                                let sim = snapshot.to_simulation(&device, &queue)?;
+                                Ok((snapshot, sim))
+                            });
+                        // the running simulation stays when the snapshot cannot be resumed
+                        test_ui.snapshot_status = match loaded {
+                            Ok((snapshot, sim)) => {
+                                example = state::State::from_simulation(sim, &config, &device);
+                                if let Some(camera) = snapshot.camera {
+                                    example.set_camera(camera, &queue);
+                                }
+                                let status = format!("resumed at step {}", snapshot.step_count);
+                                test_ui.set_params(snapshot.params);
+                                status
+                            }
+                            Err(e) => format!("{}: {}", path.display(), e),
+                        };
+                    }
+                    gui::OutputState::ToggleRecording => {
+                        if recorder.is_some() {
+                            stop_recording(&mut recorder, &mut test_ui, &device);
+                        } else {
+                            let path = std::path::PathBuf::from(&test_ui.trajectory_path);
+                            test_ui.trajectory_status = match TrajectoryFormat::from_path(&path) {
+                                Some(format) => match TrajectoryRecorder::create(
+                                    &path,
+                                    format,
+                                    test_ui.trajectory_every,
+                                    &example.sim,
+                                ) {
+                                    Ok(new_recorder) => {
+                                        recorder = Some(new_recorder);
+                                        test_ui.recording = true;
+                                        format!("recording to {}", path.display())
+                                    }
+                                    Err(e) => format!("{}: {}", path.display(), e),
+                                },
+                                None => String::from("use a .csv, .xyz or .npy file"),
+                            };
+                        }
+                    }
+                    gui::OutputState::Screenshot => {
+                        let target = capture_target_for(
+                            &mut capture_target,
+                            &device,
+                            test_ui.capture_width,
+                            test_ui.capture_height,
+                        );
+                        let image = example.capture(target, &device, &queue);
+                        test_ui.capture_status = match capture::save_screenshot(
+                            std::path::Path::new(&test_ui.capture_dir),
+                            example.sim.step_count,
+                            &image,
+                        ) {
+                            Ok(path) => format!("saved {}", path.display()),
+                            Err(e) => e,
+                        };
+                    }
+                    gui::OutputState::ToggleFrameRecording => {
+                        if let Some(sequence) = png_sequence.take() {
+                            let dir = sequence.dir.clone();
+                            test_ui.capture_status = match sequence.finish() {
+                                Ok(frames) => {
+                                    format!("wrote {} frames to {}", frames, dir.display())
+                                }
+                                Err(e) => e,
+                            };
+                            test_ui.recording_frames = false;
+                        } else {
+                            match PngSequence::start(std::path::Path::new(&test_ui.capture_dir)) {
+                                Ok(sequence) => {
+                                    test_ui.capture_status =
+                                        format!("recording frames to {}", sequence.dir.display());
+                                    png_sequence = Some(sequence);
+                                    test_ui.recording_frames = true;
+                                    last_captured_step = None;
+                                }
+                                Err(e) => test_ui.capture_status = e,
+                            }
+                        }
                     }
                     gui::OutputState::TogglePlay => {
-                        example.params.play = !(example.params.play);
+                        example.sim.params.play = !(example.sim.params.play);
                     }
                     gui::OutputState::Debug => {
                         example.debug(&device, &queue);
@@ -378,7 +569,7 @@ fn start(
                     }
                     gui::OutputState::None => {
                         if SAMPLE_COUNT == 1 {
-                            example.render(&view, None, &device, &queue, example.params.play);
+                            example.render(&view, None, &device, &queue, example.sim.params.play);
                         // test_ui.render(&window, &device, &view, None, &queue);
                         } else {
                             example.render(
@@ -386,11 +577,40 @@ fn start(
                                 Some(&view),
                                 &device,
                                 &queue,
-                                example.params.play,
+                                example.sim.params.play,
                             );
                         }
                     }
                 }
+                // one png per simulation step, so pausing does not fill the folder with duplicates
+                if let Some(sequence) = &mut png_sequence {
+                    if last_captured_step != Some(example.sim.step_count) {
+                        last_captured_step = Some(example.sim.step_count);
+                        let target = capture_target_for(
+                            &mut capture_target,
+                            &device,
+                            test_ui.capture_width,
+                            test_ui.capture_height,
+                        );
+                        let image = example.capture(target, &device, &queue);
+                        if let Err(e) = sequence.push(image) {
+                            test_ui.capture_status = format!("recording stopped: {}", e);
+                            test_ui.recording_frames = false;
+                            png_sequence = None;
+                        }
+                    }
+                }
+
+                if let Some(active) = &mut recorder {
+                    active.record(&example.sim, &device, &queue);
+                    device.poll(wgpu::Maintain::Poll);
+                    if let Err(e) = active.drain() {
+                        test_ui.trajectory_status = format!("recording stopped: {}", e);
+                        test_ui.recording = false;
+                        recorder = None;
+                    }
+                }
+
                 test_ui.render(&window, &device, &view, &queue);
 
                 frame.present();
@@ -415,6 +635,82 @@ fn start(
     });
 }
 
+/// reuses the offscreen target unless the requested resolution changed
+fn capture_target_for<'a>(
+    target: &'a mut Option<CaptureTarget>,
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+) -> &'a CaptureTarget {
+    if !matches!(target, Some(t) if t.width == width && t.height == height) {
+        *target = Some(CaptureTarget::new(device, width.max(1), height.max(1)));
+    }
+    target.as_ref().unwrap()
+}
+
+/// rebuilds the simulation from the editor's params, keeping the running one and
+/// reporting why when they do not build, e.g. a shader written for another layout
+fn reload(
+    example: &mut state::State,
+    ui: &mut gui::Gui,
+    config: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+) {
+    match state::State::try_init(ui.gen_params(), config, device) {
+        Ok(state) => *example = state,
+        Err(reason) => ui.scenario_status = reason,
+    }
+}
+
+/// carries the running simulation on with a shader that was just saved, if it is the one
+/// running; when it does not build the error is shown and the last good shader keeps running
+fn hot_reload(
+    path: &std::path::Path,
+    example: &mut state::State,
+    ui: &mut gui::Gui,
+    device: &wgpu::Device,
+) {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return;
+    };
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            ui.shader_error = Some(format!("could not read {}: {}", path.display(), e));
+            return;
+        }
+    };
+    ui.shader_saved(path, &source);
+
+    let params = &example.sim.params;
+    if name != params.shader_name || !params.algorithm.uses_shader_buffer() {
+        return;
+    }
+    match example.sim.try_set_shader(source, device) {
+        Ok(()) => {
+            ui.scenario_status = format!("reloaded {} at step {}", name, example.sim.step_count);
+            ui.shader_error = None;
+        }
+        Err(reason) => ui.shader_error = Some(reason),
+    }
+}
+
+/// flushes an active trajectory recording and reports how many frames it wrote
+fn stop_recording(
+    recorder: &mut Option<TrajectoryRecorder>,
+    ui: &mut gui::Gui,
+    device: &wgpu::Device,
+) {
+    if let Some(active) = recorder.take() {
+        let path = active.path.clone();
+        ui.trajectory_status = match active.finish(device) {
+            Ok(frames) => format!("wrote {} frames to {}", frames, path.display()),
+            Err(e) => format!("{}: {}", path.display(), e),
+        };
+    }
+    ui.recording = false;
+}
+
 pub struct Spawner<'a> {
     executor: async_executor::LocalExecutor<'a>,
 }
@@ -431,6 +727,7 @@ impl<'a> Spawner<'a> {
         self.executor.spawn(future).detach();
     }
 
+    #[allow(dead_code)]
     fn run_until_stalled(&self) {
         while self.executor.try_tick() {}
     }
@@ -452,19 +749,19 @@ impl Spawner {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub fn run(title: &str) {
-    let setup = pollster::block_on(setup(title));
-    start(setup);
+pub fn run(title: &str, start_from: Start, shaders: ShaderRegistry, width: u32, height: u32) {
+    let setup = pollster::block_on(setup(title, width, height));
+    start(setup, start_from, shaders);
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn run(title: &str) {
+pub fn run(title: &str, start_from: Start, shaders: ShaderRegistry, width: u32, height: u32) {
     use wasm_bindgen::{prelude::*, JsCast};
 
     let title = title.to_owned();
     wasm_bindgen_futures::spawn_local(async move {
-        let setup = setup(&title).await;
-        let start_closure = Closure::once_into_js(move || start(setup));
+        let setup = setup(&title, width, height).await;
+        let start_closure = Closure::once_into_js(move || start(setup, start_from, shaders));
 
         // make sure to handle JS exceptions thrown inside start.
         // Otherwise wasm_bindgen_futures Queue would break and never handle any tasks again.
@@ -0,0 +1,1992 @@
+use std::{borrow::Cow, mem};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    force, mesh,
+    reflect::{self, BindingSpace, ShaderInterface},
+    shader_params, Algorithm, Integrator, Params, Particle,
+};
+
+/// work group size of the per-particle passes, matches `@workgroup_size(64)` in the shaders
+const PARTICLES_PER_GROUP: u32 = 64;
+/// cells handled by one work group of the prefix sum in grid.wgsl
+const CELLS_PER_SCAN_BLOCK: u32 = 256;
+/// keys handled by one work group of the radix sort in morton.wgsl
+const KEYS_PER_SORT_BLOCK: u32 = 256;
+/// bits sorted per radix sort pass, `RADIX` in morton.wgsl is `1 << RADIX_BITS`
+const RADIX_BITS: u32 = 4;
+/// largest grid the Barnes-Hut tree is built over, keeps the leaf dispatch under the
+/// 65535 work group limit
+pub const MAX_TREE_GRIDS_SIDE: u32 = 1024;
+/// largest grid of the particle-mesh solver, whose padded mesh is twice as wide
+pub const MAX_MESH_GRIDS_SIDE: u32 = 1024;
+/// index of the stage buffer's bind groups, after the two particle buffers
+const STAGE: usize = 2;
+/// size of `StageParams` in integrate.wgsl
+const STAGE_PARAMS_SIZE: u64 = 16;
+/// particles reduced by one work group of `reduce_maxima` in timestep.wgsl
+const PARTICLES_PER_DT_BLOCK: u32 = 256;
+/// byte offset of `dt` in the params uniform, see `Params::to_slice`
+const DT_OFFSET: u64 = 4;
+
+/// The compute half of eden: two ping-ponged particle buffers, the cell list
+/// built by grid.wgsl and the force pipeline. Needs a device but no surface.
+///
+/// Every step sorts the particles into `num_grids_side²` cells with a counting
+/// sort (count, prefix sum, scatter), so the force shader can walk the
+/// particles of a cell as the contiguous range
+/// `sorted_indices[cell_starts[c] .. cell_starts[c] + cell_counts[c]]`.
+///
+/// Every `params.sort_every` steps the particles themselves are reordered by
+/// the Morton code of their cell (see morton.wgsl), so the particles a cell
+/// range points at also sit close together in memory. Use [`Particle::id`] to
+/// follow a particle across reorders; [`Simulation::read_particles`] returns
+/// them in id order.
+///
+/// With [`Algorithm::UniformGrid`] the force pass is `params.shader_buffer`, a
+/// force module spliced into core.wgsl by [`force::compose`];
+/// [`Algorithm::BruteForce`] runs the same module over every pair and skips
+/// the cell list. With [`Algorithm::BarnesHut`] it is barneshut.wgsl instead,
+/// and a quadtree is reduced from the cell list before it on every step.
+/// [`Algorithm::ParticleMesh`] runs particlemesh.wgsl, depositing mass onto a
+/// mesh and solving for the field with FFT passes.
+///
+/// The force shader is reflected with naga before anything is built, see
+/// [`Simulation::check_shader`], and its bind group layout only holds the
+/// buffers it uses.
+///
+/// Force passes only write accelerations; integrate.wgsl then advances the
+/// particles with `params.integrator`. Schemes that need the forces at more
+/// than one point per step write those intermediate states to a stage buffer,
+/// and the cell list and solver passes are rerun on each of them.
+///
+/// With `params.adaptive_dt` each step first evaluates the forces on its
+/// starting state, then timestep.wgsl reduces the largest velocity and
+/// acceleration and copies the dt it picks into the params uniform before any
+/// integration pass runs. [`Simulation::read_clock`] returns that dt and the
+/// time simulated so far.
+#[derive(Debug)]
+pub struct Simulation {
+    /// indexed by the state the force pass reads: particle buffer 0, 1 or `STAGE`
+    particle_bind_groups: Vec<wgpu::BindGroup>,
+    /// indexed by `src`, reading that particle buffer and writing the other
+    integrate_bind_groups: Vec<wgpu::BindGroup>,
+    /// indexed by `src * 2 + k`, reading keys and values from buffer `k` and writing the other
+    morton_bind_groups: Vec<wgpu::BindGroup>,
+    sort_pass_bind_group: wgpu::BindGroup,
+    pub particle_buffers: Vec<wgpu::Buffer>,
+    pub sim_param_buffer: wgpu::Buffer,
+    pub attraction_matrix_buffer: wgpu::Buffer,
+    /// values of the shader's `// @param` tunables, see [`shader_params`]
+    pub shader_param_buffer: wgpu::Buffer,
+    /// number of particles in each cell
+    pub cell_counts_buffer: wgpu::Buffer,
+    /// offset of each cell's first particle in `sorted_indices_buffer`
+    pub cell_starts_buffer: wgpu::Buffer,
+    /// particle indices ordered by cell
+    pub sorted_indices_buffer: wgpu::Buffer,
+    /// acceleration of every particle from the last force pass
+    pub accelerations_buffer: wgpu::Buffer,
+    /// intermediate state of multi-stage integrators, the force pass reads it at `STAGE`
+    stage_buffer: wgpu::Buffer,
+    compute_pipeline: wgpu::ComputePipeline,
+    morton_keys_pipeline: wgpu::ComputePipeline,
+    histogram_pipeline: wgpu::ComputePipeline,
+    scan_histogram_pipeline: wgpu::ComputePipeline,
+    radix_scatter_pipeline: wgpu::ComputePipeline,
+    reorder_pipeline: wgpu::ComputePipeline,
+    /// force evaluations and integration passes of one step
+    step_passes: Vec<StepPass>,
+    /// one per `StepPass::Integrate` in `step_passes`, in order
+    integrate_pipelines: Vec<wgpu::ComputePipeline>,
+    /// distance between the per-pass entries of the stage uniform buffer
+    stage_stride: u32,
+    timestep: DtControl,
+    work_group_count: u32,
+    sort_block_count: u32,
+    /// radix sort passes needed to cover every bit of the largest Morton code
+    sort_passes: u32,
+    /// distance between the per-pass entries of the sort pass uniform buffer
+    sort_pass_stride: u32,
+    /// built for every algorithm but `Algorithm::BruteForce`
+    cell_list: Option<CellList>,
+    /// only built for `Algorithm::BarnesHut`
+    tree: Option<Tree>,
+    /// only built for `Algorithm::ParticleMesh`
+    mesh: Option<Mesh>,
+    /// index of the particle buffer holding the current state, the other one is written next
+    src: usize,
+    pub step_count: u64,
+    pub params: Params,
+}
+
+/// one pass of a step, as laid out for an integrator by [`StepPass::schedule`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StepPass {
+    /// rebuilds the cell list and runs the force pass on the step's state, or on the stage
+    Forces { stage: bool },
+    /// picks dt from the forces just evaluated, or advances the clock by a fixed dt
+    Timestep,
+    /// an entry point of integrate.wgsl and the `StageParams` it runs with
+    Integrate {
+        entry_point: &'static str,
+        weight: f32,
+        fraction: f32,
+        first: bool,
+    },
+}
+
+impl StepPass {
+    fn integrate(entry_point: &'static str) -> Self {
+        StepPass::Integrate {
+            entry_point,
+            weight: 0.0,
+            fraction: 0.0,
+            first: false,
+        }
+    }
+
+    fn rk4(entry_point: &'static str, weight: f32, fraction: f32, first: bool) -> Self {
+        StepPass::Integrate {
+            entry_point,
+            weight,
+            fraction,
+            first,
+        }
+    }
+
+    /// the passes of one step; an adaptive dt needs the forces on the starting state
+    /// before anything integrates, which costs leapfrog an extra force pass
+    fn schedule(integrator: Integrator, adaptive_dt: bool) -> Vec<StepPass> {
+        let forces = StepPass::Forces { stage: false };
+        let stage_forces = StepPass::Forces { stage: true };
+        let mut passes = match integrator {
+            Integrator::Euler => vec![forces, StepPass::integrate("euler")],
+            Integrator::Trapezoidal => vec![forces, StepPass::integrate("trapezoidal")],
+            Integrator::VelocityVerlet => vec![
+                forces,
+                StepPass::integrate("verlet_drift"),
+                stage_forces,
+                StepPass::integrate("verlet_kick"),
+            ],
+            Integrator::Leapfrog => vec![
+                StepPass::integrate("leapfrog_drift"),
+                stage_forces,
+                StepPass::integrate("leapfrog_kick"),
+            ],
+            Integrator::Rk4 => vec![
+                forces,
+                StepPass::rk4("rk4_stage", 1.0 / 6.0, 0.5, true),
+                stage_forces,
+                StepPass::rk4("rk4_stage", 1.0 / 3.0, 0.5, false),
+                stage_forces,
+                StepPass::rk4("rk4_stage", 1.0 / 3.0, 1.0, false),
+                stage_forces,
+                StepPass::rk4("rk4_finish", 1.0 / 6.0, 0.0, false),
+            ],
+        };
+        if !adaptive_dt {
+            passes.push(StepPass::Timestep);
+        } else if passes[0] == forces {
+            passes.insert(1, StepPass::Timestep);
+        } else {
+            passes.splice(0..0, [forces, StepPass::Timestep]);
+        }
+        passes
+    }
+}
+
+/// dt of the last step and the time simulated since the simulation was built,
+/// the `Clock` that timestep.wgsl keeps on the GPU
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Clock {
+    pub dt: f32,
+    pub time: f32,
+    compensation: f32,
+    _pad: f32,
+}
+
+/// the passes of timestep.wgsl, run once per step
+#[derive(Debug)]
+struct DtControl {
+    reduce_maxima_pipeline: wgpu::ComputePipeline,
+    choose_dt_pipeline: wgpu::ComputePipeline,
+    advance_clock_pipeline: wgpu::ComputePipeline,
+    /// indexed by `src`, reading the velocities of that particle buffer
+    bind_groups: Vec<wgpu::BindGroup>,
+    clock_buffer: wgpu::Buffer,
+    /// `DtBounds` of timestep.wgsl
+    bounds_buffer: wgpu::Buffer,
+    block_count: u32,
+    adaptive: bool,
+}
+
+impl DtControl {
+    /// picks dt and hands it to the integration passes, or only advances the clock
+    fn encode(&self, encoder: &mut wgpu::CommandEncoder, src: usize, params: &wgpu::Buffer) {
+        {
+            let mut tpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Timestep Pass"),
+            });
+            tpass.set_bind_group(0, &self.bind_groups[src], &[]);
+            if self.adaptive {
+                tpass.set_pipeline(&self.reduce_maxima_pipeline);
+                tpass.dispatch_workgroups(self.block_count, 1, 1);
+                tpass.set_pipeline(&self.choose_dt_pipeline);
+                tpass.dispatch_workgroups(1, 1, 1);
+            } else {
+                tpass.set_pipeline(&self.advance_clock_pipeline);
+                tpass.dispatch_workgroups(1, 1, 1);
+            }
+        }
+        if self.adaptive {
+            encoder.copy_buffer_to_buffer(
+                &self.clock_buffer,
+                0,
+                params,
+                DT_OFFSET,
+                mem::size_of::<f32>() as u64,
+            );
+        }
+    }
+}
+
+/// the counting sort of grid.wgsl that buckets the particles into cells before the force pass
+#[derive(Debug)]
+struct CellList {
+    count_cells_pipeline: wgpu::ComputePipeline,
+    scan_blocks_pipeline: wgpu::ComputePipeline,
+    scan_block_sums_pipeline: wgpu::ComputePipeline,
+    add_block_offsets_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+    sort_cells_pipeline: wgpu::ComputePipeline,
+    /// indexed like `Simulation::particle_bind_groups`
+    bind_groups: Vec<wgpu::BindGroup>,
+    scan_block_count: u32,
+    cell_work_group_count: u32,
+}
+
+impl CellList {
+    /// buckets the particles of `state` into the cleared `cell_counts`, sorting each cell's
+    /// range by particle id when `deterministic`
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        state: usize,
+        work_group_count: u32,
+        deterministic: bool,
+    ) {
+        let mut gpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cell List Pass"),
+        });
+        gpass.set_bind_group(0, &self.bind_groups[state], &[]);
+
+        gpass.set_pipeline(&self.count_cells_pipeline);
+        gpass.dispatch_workgroups(work_group_count, 1, 1);
+
+        gpass.set_pipeline(&self.scan_blocks_pipeline);
+        gpass.dispatch_workgroups(self.scan_block_count, 1, 1);
+        gpass.set_pipeline(&self.scan_block_sums_pipeline);
+        gpass.dispatch_workgroups(1, 1, 1);
+        gpass.set_pipeline(&self.add_block_offsets_pipeline);
+        gpass.dispatch_workgroups(self.scan_block_count, 1, 1);
+
+        gpass.set_pipeline(&self.scatter_pipeline);
+        gpass.dispatch_workgroups(work_group_count, 1, 1);
+
+        if deterministic {
+            gpass.set_pipeline(&self.sort_cells_pipeline);
+            gpass.dispatch_workgroups(self.cell_work_group_count, 1, 1);
+        }
+    }
+}
+
+/// the passes that reduce the Barnes-Hut quadtree before the force pass
+#[derive(Debug)]
+struct Tree {
+    leaf_nodes_pipeline: wgpu::ComputePipeline,
+    reduce_nodes_pipeline: wgpu::ComputePipeline,
+    /// the leaves are `2^depth` per side
+    depth: u32,
+    /// distance between the per-level entries of the tree uniform buffer
+    level_stride: u32,
+    /// the tree uniform, rewritten when theta, gravity or the softening change
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl Tree {
+    /// node count of a complete quadtree whose leaves are `2^depth` per side
+    fn node_count(depth: u32) -> u32 {
+        ((1 << (2 * (depth + 1))) - 1) / 3
+    }
+
+    /// dynamic offset of the tree uniform for passes working on `level`
+    fn offset(&self, level: u32) -> u32 {
+        level * self.level_stride
+    }
+
+    /// contents of the tree uniform: theta, gravity, softening, depth and the level a pass
+    /// works on, one entry per level `level_stride` apart
+    fn uniform_data(params: &Params, depth: u32, level_stride: u32) -> Vec<u8> {
+        let mut data = vec![0u8; (level_stride * (depth + 1)) as usize];
+        for level in 0..=depth {
+            let offset = (level * level_stride) as usize;
+            let entry: [u32; 5] = [
+                params.theta.to_bits(),
+                params.gravity.to_bits(),
+                params.particle_radius.to_bits(),
+                depth,
+                level,
+            ];
+            data[offset..offset + 20].copy_from_slice(bytemuck::cast_slice(&entry));
+        }
+        data
+    }
+
+    /// fills the leaves from the cell list, then sums each level into the one above,
+    /// leaving `bind_group` set for the force pass
+    fn encode<'a>(&'a self, cpass: &mut wgpu::ComputePass<'a>, bind_group: &'a wgpu::BindGroup) {
+        let leaves = 1u32 << (2 * self.depth);
+        cpass.set_pipeline(&self.leaf_nodes_pipeline);
+        cpass.set_bind_group(0, bind_group, &[self.offset(self.depth)]);
+        cpass.dispatch_workgroups(leaves.div_ceil(PARTICLES_PER_GROUP), 1, 1);
+
+        cpass.set_pipeline(&self.reduce_nodes_pipeline);
+        for level in (0..self.depth).rev() {
+            let nodes = 1u32 << (2 * level);
+            cpass.set_bind_group(0, bind_group, &[self.offset(level)]);
+            cpass.dispatch_workgroups(nodes.div_ceil(PARTICLES_PER_GROUP), 1, 1);
+        }
+    }
+}
+
+/// the deposit, FFT and kernel passes of the particle-mesh solver
+#[derive(Debug)]
+struct Mesh {
+    deposit_pipeline: wgpu::ComputePipeline,
+    fft_pass_pipeline: wgpu::ComputePipeline,
+    apply_kernel_pipeline: wgpu::ComputePipeline,
+    /// both complex halves of the mesh, cleared every step so the padding stays empty
+    nodes_buffer: wgpu::Buffer,
+    /// the field kernel, rewritten when gravity, the softening or the boundary change
+    kernel_buffer: wgpu::Buffer,
+    /// FFT passes per transform
+    fft_passes: u32,
+    deposit_work_group_count: u32,
+    fft_work_group_count: u32,
+    kernel_work_group_count: u32,
+    /// distance between the per-pass entries of the mesh pass uniform buffer
+    pass_stride: u32,
+}
+
+impl Mesh {
+    /// dynamic offset of the mesh pass uniform for entry `pass`: 0 for the deposit and the
+    /// force pass, then the forward passes, the kernel and the inverse passes
+    fn offset(&self, pass: u32) -> u32 {
+        pass * self.pass_stride
+    }
+
+    /// deposits the mass, transforms it, applies the kernel and transforms back,
+    /// leaving `bind_group` set for the force pass
+    fn encode<'a>(&'a self, cpass: &mut wgpu::ComputePass<'a>, bind_group: &'a wgpu::BindGroup) {
+        cpass.set_pipeline(&self.deposit_pipeline);
+        cpass.set_bind_group(0, bind_group, &[self.offset(0)]);
+        cpass.dispatch_workgroups(self.deposit_work_group_count, 1, 1);
+
+        let kernel_pass = self.fft_passes + 1;
+        cpass.set_pipeline(&self.fft_pass_pipeline);
+        for pass in 1..kernel_pass {
+            cpass.set_bind_group(0, bind_group, &[self.offset(pass)]);
+            cpass.dispatch_workgroups(self.fft_work_group_count, 1, 1);
+        }
+
+        cpass.set_pipeline(&self.apply_kernel_pipeline);
+        cpass.set_bind_group(0, bind_group, &[self.offset(kernel_pass)]);
+        cpass.dispatch_workgroups(self.kernel_work_group_count, 1, 1);
+
+        cpass.set_pipeline(&self.fft_pass_pipeline);
+        for pass in kernel_pass + 1..=kernel_pass + self.fft_passes {
+            cpass.set_bind_group(0, bind_group, &[self.offset(pass)]);
+            cpass.dispatch_workgroups(self.fft_work_group_count, 1, 1);
+        }
+
+        cpass.set_bind_group(0, bind_group, &[self.offset(0)]);
+    }
+}
+
+/// working storage of a built-in solver: bound at 7, a per-pass uniform at 8 with a
+/// dynamic offset, and the field kernel at 9 for the mesh
+struct SolverBuffers {
+    storage: wgpu::Buffer,
+    passes: wgpu::Buffer,
+    pass_size: u64,
+    kernel: Option<wgpu::Buffer>,
+}
+
+/// the buffers of [`force_resources`], bound for the force pass of one state
+struct ForceBindings<'a> {
+    params: &'a wgpu::Buffer,
+    accelerations: &'a wgpu::Buffer,
+    attraction_matrix: &'a wgpu::Buffer,
+    cell_starts: &'a wgpu::Buffer,
+    cell_counts: &'a wgpu::Buffer,
+    sorted_indices: &'a wgpu::Buffer,
+    shader_params: &'a wgpu::Buffer,
+    solver: Option<&'a SolverBuffers>,
+}
+
+impl ForceBindings<'_> {
+    /// only what the shader uses, read-only where it only reads
+    fn layout_entries(&self, interface: &ShaderInterface) -> Vec<wgpu::BindGroupLayoutEntry> {
+        let uniform_entry =
+            |binding, has_dynamic_offset, min_binding_size| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset,
+                    min_binding_size,
+                },
+                count: None,
+            };
+        let mut entries = vec![
+            uniform_entry(0, false, wgpu::BufferSize::new(self.params.size())),
+            //input / source buffer
+            storage_entry(1, true),
+            //accelerations
+            storage_entry(2, false),
+            //attraction matrix buffer
+            storage_entry(3, true),
+            //cell_starts, cell_counts, sorted_indices
+            storage_entry(4, true),
+            storage_entry(5, true),
+            storage_entry(6, true),
+            //@param tunables
+            uniform_entry(shader_params::BINDING, false, None),
+        ];
+        if let Some(solver) = self.solver {
+            //solver storage and the per-pass solver uniform
+            entries.push(storage_entry(7, false));
+            entries.push(uniform_entry(
+                8,
+                true,
+                wgpu::BufferSize::new(solver.pass_size),
+            ));
+            if solver.kernel.is_some() {
+                //mesh kernel
+                entries.push(storage_entry(9, true));
+            }
+        }
+        entries
+            .into_iter()
+            .filter_map(|mut entry| {
+                let declared = interface.binding(0, entry.binding)?;
+                if let (
+                    wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only },
+                        ..
+                    },
+                    BindingSpace::Storage {
+                        read_only: declared_read_only,
+                    },
+                ) = (&mut entry.ty, declared.space)
+                {
+                    *read_only = declared_read_only;
+                }
+                Some(entry)
+            })
+            .collect()
+    }
+
+    /// the force pass bind group evaluating the particles in `state`
+    fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        state: &wgpu::Buffer,
+        interface: &ShaderInterface,
+    ) -> wgpu::BindGroup {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: state.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: self.accelerations.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: self.attraction_matrix.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: self.cell_starts.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: self.cell_counts.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: self.sorted_indices.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: shader_params::BINDING,
+                resource: self.shader_params.as_entire_binding(),
+            },
+        ];
+        if let Some(solver) = self.solver {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 7,
+                resource: solver.storage.as_entire_binding(),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &solver.passes,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(solver.pass_size),
+                }),
+            });
+            if let Some(kernel) = &solver.kernel {
+                entries.push(wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: kernel.as_entire_binding(),
+                });
+            }
+        }
+        entries.retain(|entry| interface.binding(0, entry.binding).is_some());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: None,
+        })
+    }
+}
+
+/// name and source of the force shader `params` builds, the built-in solvers bring their own
+fn force_shader(params: &Params) -> (&str, Cow<'_, str>) {
+    match params.algorithm {
+        Algorithm::BruteForce | Algorithm::UniformGrid => {
+            (&params.shader_name, force::compose(&params.shader_buffer))
+        }
+        Algorithm::BarnesHut => (
+            "barneshut.wgsl",
+            Cow::Borrowed(include_str!("shaders/barneshut.wgsl")),
+        ),
+        Algorithm::ParticleMesh => (
+            "particlemesh.wgsl",
+            Cow::Borrowed(include_str!("shaders/particlemesh.wgsl")),
+        ),
+    }
+}
+
+fn force_entry_point(algorithm: Algorithm) -> &'static str {
+    match algorithm {
+        Algorithm::BruteForce => "brute_force",
+        _ => "main",
+    }
+}
+
+/// a buffer the force pass binds in group 0, and how a shader may declare it
+struct ForceResource {
+    binding: u32,
+    /// what is bound there, for error messages
+    what: &'static str,
+    /// read-only when the force pass must not write it
+    space: BindingSpace,
+    /// bytes in a uniform, or the element stride of a storage array, 0 if not checked
+    size: u32,
+}
+
+/// the buffers the force pass of `params.algorithm` binds
+fn force_resources(params: &Params) -> Vec<ForceResource> {
+    let storage = |binding, what, read_only, size| ForceResource {
+        binding,
+        what,
+        space: BindingSpace::Storage { read_only },
+        size,
+    };
+    let mut resources = vec![
+        ForceResource {
+            binding: 0,
+            what: "the SimParams uniform",
+            space: BindingSpace::Uniform,
+            size: (params.to_slice().len() * mem::size_of::<f32>()) as u32,
+        },
+        storage(1, "the particles", true, mem::size_of::<Particle>() as u32),
+        storage(2, "the accelerations", false, 8),
+        storage(3, "the attraction matrix entries", true, 16),
+        storage(4, "the cell starts", true, 4),
+        storage(5, "the cell counts", true, 4),
+        storage(6, "the sorted indices", true, 4),
+    ];
+    if params.algorithm.uses_shader_buffer() {
+        resources.push(ForceResource {
+            binding: shader_params::BINDING,
+            what: "the @param uniform",
+            space: BindingSpace::Uniform,
+            size: (params.shader_param_slice().len() * mem::size_of::<f32>()) as u32,
+        });
+    } else {
+        resources.push(storage(7, "the solver nodes", false, 0));
+        resources.push(ForceResource {
+            binding: 8,
+            what: "the solver pass uniform",
+            space: BindingSpace::Uniform,
+            size: u32::MAX,
+        });
+        resources.push(storage(9, "the mesh kernel", true, 0));
+    }
+    resources
+}
+
+/// the uniform holding the `// @param` tunables of `params.shader_buffer`
+fn create_shader_param_buffer(params: &Params, device: &wgpu::Device) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Shader Param Buffer"),
+        contents: bytemuck::cast_slice(&params.shader_param_slice()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/// a compute-visible storage buffer binding
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl Simulation {
+    pub fn required_limits() -> wgpu::Limits {
+        //set surface limits based on the target architecture
+        if cfg!(target_arch = "wasm32") {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        } else {
+            wgpu::Limits::default()
+        }
+    }
+
+    pub fn required_downlevel_capabilities() -> wgpu::DownlevelCapabilities {
+        //downlevel capabilites that don't confirm to WebGPU standard
+        wgpu::DownlevelCapabilities {
+            flags: wgpu::DownlevelFlags::COMPUTE_SHADERS,
+            ..Default::default()
+        }
+    }
+
+    /// spawns the particles from the params and builds the simulation around them. A shader
+    /// that does not fit the algorithm's bind group layout, or does not compile, is an error
+    pub fn try_new(params: Params, device: &wgpu::Device) -> Result<Self, String> {
+        let initial_particle_data = params.spawn_particles();
+
+        Self::try_from_particles(params, &initial_particle_data, device)
+    }
+
+    /// builds the simulation around an existing particle state instead of a random one, or
+    /// reports what keeps it from being built
+    pub fn try_from_particles(
+        params: Params,
+        initial_particle_data: &[Particle],
+        device: &wgpu::Device,
+    ) -> Result<Self, String> {
+        if let Err(reason) = params.algorithm.check_grids_side(params.num_grids_side) {
+            return Err(format!("num_grids_side {}", reason));
+        }
+        let interface = Self::check_shader(&params)?;
+
+        // anything naga let through that wgpu still rejects is reported through the
+        // error scope rather than from the create calls
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let sim = Self::build(params, initial_particle_data, device, &interface);
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(error) => Err(format!(
+                "{} does not fit the {} pipeline: {}",
+                sim.params.shader_name, sim.params.algorithm, error
+            )),
+            None => Ok(sim),
+        }
+    }
+
+    /// reflects the force shader `params` would build and checks that its entry point is
+    /// there and that every buffer it uses is one the force pass binds, declared to fit
+    pub fn check_shader(params: &Params) -> Result<ShaderInterface, String> {
+        let (name, source) = force_shader(params);
+        if params.algorithm.uses_shader_buffer() {
+            params
+                .shader_params()
+                .map_err(|e| format!("{}: {}", name, e))?;
+        }
+        if params.algorithm == Algorithm::BruteForce && !force::is_module(&params.shader_buffer) {
+            return Err(format!(
+                "{} runs a force module, but {} has its own entry point",
+                params.algorithm, name
+            ));
+        }
+        let mut interface =
+            reflect::reflect(&source, name).map_err(|e| format!("{}: {}", name, e))?;
+
+        let entry_point = force_entry_point(params.algorithm);
+        if !interface.entry_points.iter().any(|ep| ep == entry_point) {
+            return Err(format!(
+                "{} has no `{}` entry point for {}",
+                name, entry_point, params.algorithm
+            ));
+        }
+        // the solvers run every entry point of their shader with the same layout
+        if params.algorithm.uses_shader_buffer() {
+            interface.retain_entry_point(entry_point);
+        }
+
+        let resources = force_resources(params);
+        for declared in &interface.bindings {
+            let fail = |reason: String| {
+                Err(format!(
+                    "{}: `{}` at group {} binding {} {}",
+                    name, declared.name, declared.group, declared.binding, reason
+                ))
+            };
+            let provided = resources
+                .iter()
+                .find(|r| declared.group == 0 && r.binding == declared.binding);
+            let Some(provided) = provided else {
+                return fail("is not a buffer eden binds for the force pass".to_string());
+            };
+            match (declared.space, provided.space) {
+                (BindingSpace::Uniform, BindingSpace::Uniform) => {
+                    if declared.size > provided.size {
+                        return fail(format!(
+                            "needs {} bytes, but {} holds {}",
+                            declared.size, provided.what, provided.size
+                        ));
+                    }
+                }
+                (
+                    BindingSpace::Storage { read_only },
+                    BindingSpace::Storage {
+                        read_only: provided_read_only,
+                    },
+                ) => {
+                    if !read_only && provided_read_only {
+                        return fail(format!(
+                            "is read_write, but {} are read-only to the force pass",
+                            provided.what
+                        ));
+                    }
+                    match declared.stride {
+                        Some(stride) if provided.size != 0 && stride != provided.size => {
+                            return fail(format!(
+                                "has {}-byte elements, but {} are {} bytes each",
+                                stride, provided.what, provided.size
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+                (BindingSpace::Uniform, _) => {
+                    return fail(format!(
+                        "is a uniform, but {} are a storage buffer",
+                        provided.what
+                    ))
+                }
+                (_, BindingSpace::Uniform) => {
+                    return fail(format!(
+                        "is a storage buffer, but {} are a uniform",
+                        provided.what
+                    ))
+                }
+            }
+        }
+        Ok(interface)
+    }
+
+    fn build(
+        params: Params,
+        initial_particle_data: &[Particle],
+        device: &wgpu::Device,
+        interface: &ShaderInterface,
+    ) -> Self {
+        assert_eq!(
+            initial_particle_data.len(),
+            params.num_particles as usize,
+            "particle count does not match params.num_particles"
+        );
+
+        //create parameters
+        let params_slice = params.to_slice();
+        let params_attraction_matrix = params.attraction_matrix_slice();
+
+        let num_cells = params.num_grids_side.pow(2);
+        let scan_block_count = num_cells.div_ceil(CELLS_PER_SCAN_BLOCK);
+
+        // a Morton code interleaves the bits of both cell coordinates
+        let cell_bits = u32::BITS - (params.num_grids_side.max(1) - 1).leading_zeros();
+        let sort_passes = (2 * cell_bits).div_ceil(RADIX_BITS);
+        let sort_block_count = params.num_particles.div_ceil(KEYS_PER_SORT_BLOCK);
+
+        //initialize cell list shader
+        let grid_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/grid.wgsl"))),
+        });
+
+        //initialize morton sort shader
+        let morton_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Morton Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/morton.wgsl"))),
+        });
+
+        //initialize integration shader
+        let integrate_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Integrate Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/integrate.wgsl"))),
+        });
+
+        //initialize timestep shader
+        let timestep_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Timestep Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/timestep.wgsl"))),
+        });
+
+        //initialize compute shader module, the built-in solvers bring their own
+        let (_, compute_source) = force_shader(&params);
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(compute_source),
+        });
+
+        //set up uniform buffer to store global parameters
+        let sim_param_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Parameter Buffer"),
+            contents: bytemuck::cast_slice(&params_slice),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        //set up uniform buffer to store the shader's own @param tunables
+        let shader_param_buffer = create_shader_param_buffer(&params, device);
+
+        //set up uniform buffer to store global parameters
+        let attraction_matrix_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Parameter Buffer"),
+                contents: bytemuck::cast_slice(params_attraction_matrix),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            });
+
+        //cell list buffers, all u32
+        let u32_buffer = |label: &str, len: u32| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: (len.max(1) as usize * mem::size_of::<u32>()) as u64,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        };
+        let particle_cells_buffer = u32_buffer("Particle Cells", params.num_particles);
+        let particle_ranks_buffer = u32_buffer("Particle Ranks", params.num_particles);
+        let cell_counts_buffer = u32_buffer("Cell Counts", num_cells);
+        let cell_starts_buffer = u32_buffer("Cell Starts", num_cells);
+        let block_sums_buffer = u32_buffer("Cell Block Sums", scan_block_count);
+        let sorted_indices_buffer = u32_buffer("Sorted Indices", params.num_particles);
+
+        //integration buffers, the stage and the RK4 derivative sums only sized when used
+        let step_passes = StepPass::schedule(params.integrator, params.adaptive_dt);
+        let staged = step_passes.contains(&StepPass::Forces { stage: true });
+        let accelerations_buffer = u32_buffer("Accelerations", 2 * params.num_particles);
+        let derivatives_buffer = u32_buffer(
+            "Stage Derivatives",
+            4 * match params.integrator {
+                Integrator::Rk4 => params.num_particles,
+                _ => 1,
+            },
+        );
+        let stage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Stage Particles"),
+            size: (if staged { params.num_particles } else { 1 }) as u64
+                * mem::size_of::<Particle>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        //morton sort buffers, keys and values ping-pong between the radix passes
+        let sort_keys_buffers = [
+            u32_buffer("Sort Keys 0", params.num_particles),
+            u32_buffer("Sort Keys 1", params.num_particles),
+        ];
+        let sort_values_buffers = [
+            u32_buffer("Sort Values 0", params.num_particles),
+            u32_buffer("Sort Values 1", params.num_particles),
+        ];
+        let histograms_buffer = u32_buffer(
+            "Sort Histograms",
+            (1 << RADIX_BITS) * sort_block_count.max(1),
+        );
+
+        // one `shift` per radix pass, each at an offset the device accepts for dynamic uniforms
+        let sort_pass_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment
+            .max(mem::size_of::<u32>() as u32);
+        let mut sort_pass_data = vec![0u8; (sort_pass_stride * sort_passes.max(1)) as usize];
+        for pass in 0..sort_passes {
+            let offset = (pass * sort_pass_stride) as usize;
+            sort_pass_data[offset..offset + 4].copy_from_slice(&(pass * RADIX_BITS).to_le_bytes());
+        }
+        let sort_pass_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sort Pass Buffer"),
+            contents: &sort_pass_data,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        // weight, fraction and first of every integration pass
+        let stage_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment
+            .max(STAGE_PARAMS_SIZE as u32);
+        let mut stage_data = Vec::new();
+        for pass in &step_passes {
+            if let StepPass::Integrate {
+                weight,
+                fraction,
+                first,
+                ..
+            } = *pass
+            {
+                let mut entry = vec![0u8; stage_stride as usize];
+                let stage: [u32; 4] = [weight.to_bits(), fraction.to_bits(), first as u32, 0];
+                entry[..STAGE_PARAMS_SIZE as usize].copy_from_slice(bytemuck::cast_slice(&stage));
+                stage_data.extend(entry);
+            }
+        }
+        //adaptive dt: per-block maxima, the clock and the bounds
+        let dt_block_count = params.num_particles.div_ceil(PARTICLES_PER_DT_BLOCK);
+        let maxima_buffer = u32_buffer("Dt Maxima", 2 * dt_block_count);
+        let clock_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Clock Buffer"),
+            contents: bytemuck::bytes_of(&Clock::default()),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let dt_bounds_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dt Bounds Buffer"),
+            contents: bytemuck::cast_slice(&params.dt_bounds_slice()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let stage_params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Stage Params Buffer"),
+            contents: &stage_data,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let dynamic_stride = device.limits().min_uniform_buffer_offset_alignment.max(32);
+        let tree_depth = cell_bits;
+        let mesh_side = mesh::mesh_side(&params);
+        let mesh_fft_passes = mesh::fft_passes(mesh_side);
+        let solver_buffers = match params.algorithm {
+            Algorithm::BruteForce | Algorithm::UniformGrid => None,
+            Algorithm::BarnesHut => {
+                // a complete quadtree over the cells, leaves padded out to a power of two per
+                // side; each node is the 16 byte `Node` of barneshut.wgsl
+                let nodes_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Tree Nodes"),
+                    size: Tree::node_count(tree_depth) as u64 * 16,
+                    usage: wgpu::BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                });
+
+                let tree_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Tree Buffer"),
+                    contents: &Tree::uniform_data(&params, tree_depth, dynamic_stride),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+                Some(SolverBuffers {
+                    storage: nodes_buffer,
+                    passes: tree_buffer,
+                    pass_size: 20,
+                    kernel: None,
+                })
+            }
+            Algorithm::ParticleMesh => {
+                // two complex halves for the FFT passes to ping-pong between
+                let nodes_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Mesh Nodes"),
+                    size: 2 * (mesh_side as u64).pow(2) * mem::size_of::<mesh::Complex>() as u64,
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let kernel_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Mesh Kernel"),
+                    contents: bytemuck::cast_slice(&mesh::field_kernel(&params)),
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                });
+
+                // entry 0 for deposit and main, then forward passes, kernel, inverse passes
+                let pass_count = 2 + 2 * mesh_fft_passes.len() as u32;
+                let mut pass_data = vec![0u8; (dynamic_stride * pass_count) as usize];
+                let mut write_pass = |pass: u32, entry: [u32; 8]| {
+                    let offset = (pass * dynamic_stride) as usize;
+                    pass_data[offset..offset + 32].copy_from_slice(bytemuck::cast_slice(&entry));
+                };
+                let transform = |inverse: bool| {
+                    mesh_fft_passes
+                        .iter()
+                        .map(move |&(axis, span)| Some((axis, span, inverse)))
+                };
+                let passes = transform(false)
+                    .chain(std::iter::once(None))
+                    .chain(transform(true));
+                let mut src_half = 0;
+                for (i, pass) in passes.enumerate() {
+                    let (axis, span, inverse) = pass.unwrap_or((0, 0, false));
+                    let entry = [
+                        mesh_side,
+                        params.num_grids_side,
+                        span,
+                        axis,
+                        inverse as u32,
+                        src_half,
+                        0,
+                        0,
+                    ];
+                    write_pass(1 + i as u32, entry);
+                    if pass.is_some() {
+                        src_half = 1 - src_half;
+                    }
+                }
+                write_pass(
+                    0,
+                    [mesh_side, params.num_grids_side, 0, 0, 0, 0, src_half, 0],
+                );
+                let mesh_pass_buffer =
+                    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Mesh Pass Buffer"),
+                        contents: &pass_data,
+                        usage: wgpu::BufferUsages::UNIFORM,
+                    });
+
+                Some(SolverBuffers {
+                    storage: nodes_buffer,
+                    passes: mesh_pass_buffer,
+                    pass_size: 32,
+                    kernel: Some(kernel_buffer),
+                })
+            }
+        };
+
+        let param_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    (params_slice.len() * mem::size_of::<f32>()) as _,
+                ),
+            },
+            count: None,
+        };
+
+        let grid_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    param_entry,
+                    //particles
+                    storage_entry(1, true),
+                    //particle_cells, particle_ranks
+                    storage_entry(2, false),
+                    storage_entry(3, false),
+                    //cell_counts, cell_starts, block_sums
+                    storage_entry(4, false),
+                    storage_entry(5, false),
+                    storage_entry(6, false),
+                    //sorted_indices
+                    storage_entry(7, false),
+                ],
+                label: Some("Grid Bind Group Layout"),
+            });
+
+        let morton_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    param_entry,
+                    //particles src / dst
+                    storage_entry(1, true),
+                    storage_entry(2, false),
+                    //keys_in, values_in
+                    storage_entry(3, true),
+                    storage_entry(4, true),
+                    //keys_out, values_out
+                    storage_entry(5, false),
+                    storage_entry(6, false),
+                    //histograms
+                    storage_entry(7, false),
+                ],
+                label: Some("Morton Bind Group Layout"),
+            });
+
+        let sort_pass_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(mem::size_of::<u32>() as _),
+                    },
+                    count: None,
+                }],
+                label: Some("Sort Pass Bind Group Layout"),
+            });
+
+        //set up compute bind group layouts and compute pipeline layours
+        let force_bindings = ForceBindings {
+            params: &sim_param_buffer,
+            accelerations: &accelerations_buffer,
+            attraction_matrix: &attraction_matrix_buffer,
+            cell_starts: &cell_starts_buffer,
+            cell_counts: &cell_counts_buffer,
+            sorted_indices: &sorted_indices_buffer,
+            shader_params: &shader_param_buffer,
+            solver: solver_buffers.as_ref(),
+        };
+        let compute_entries = force_bindings.layout_entries(interface);
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &compute_entries,
+                label: None,
+            });
+
+        let integrate_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    param_entry,
+                    //particles src, stage, dst
+                    storage_entry(1, true),
+                    storage_entry(2, false),
+                    storage_entry(3, false),
+                    //accelerations, derivatives
+                    storage_entry(4, true),
+                    storage_entry(5, false),
+                    //stage params of the pass
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(STAGE_PARAMS_SIZE),
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("Integrate Bind Group Layout"),
+            });
+
+        let timestep_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    param_entry,
+                    //particles, accelerations
+                    storage_entry(1, true),
+                    storage_entry(2, true),
+                    //maxima, clock
+                    storage_entry(3, false),
+                    storage_entry(4, false),
+                    //dt bounds
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(
+                                (4 * mem::size_of::<f32>()) as _,
+                            ),
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("Timestep Bind Group Layout"),
+            });
+
+        let grid_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid"),
+            bind_group_layouts: &[&grid_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let morton_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Morton"),
+                bind_group_layouts: &[&morton_bind_group_layout, &sort_pass_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let integrate_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Integrate"),
+                bind_group_layouts: &[&integrate_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let timestep_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Timestep"),
+                bind_group_layouts: &[&timestep_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        //compute pipeline layout =
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let grid_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&grid_pipeline_layout),
+                module: &grid_shader,
+                entry_point,
+            })
+        };
+
+        let morton_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&morton_pipeline_layout),
+                module: &morton_shader,
+                entry_point,
+            })
+        };
+
+        let timestep_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&timestep_pipeline_layout),
+                module: &timestep_shader,
+                entry_point,
+            })
+        };
+
+        let integrate_pipelines = step_passes
+            .iter()
+            .filter_map(|pass| match *pass {
+                StepPass::Integrate { entry_point, .. } => Some(device.create_compute_pipeline(
+                    &wgpu::ComputePipelineDescriptor {
+                        label: Some(entry_point),
+                        layout: Some(&integrate_pipeline_layout),
+                        module: &integrate_shader,
+                        entry_point,
+                    },
+                )),
+                StepPass::Forces { .. } | StepPass::Timestep => None,
+            })
+            .collect();
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: force_entry_point(params.algorithm),
+        });
+
+        let solver_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point,
+            })
+        };
+
+        // creates two buffers of particle data each of size NUM_PARTICLES
+        // the two buffers alternate as dst and src for each frame
+
+        let mut particle_buffers = Vec::<wgpu::Buffer>::new();
+        let mut particle_bind_groups = Vec::<wgpu::BindGroup>::new();
+
+        for i in 0..2 {
+            particle_buffers.push(
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Particle Buffer {}", i)),
+                    contents: bytemuck::cast_slice(initial_particle_data),
+                    usage: wgpu::BufferUsages::VERTEX
+                        | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_DST
+                        | wgpu::BufferUsages::COPY_SRC,
+                }),
+            );
+        }
+
+        // create a bind group for each state the forces are evaluated at:
+        // either particle buffer, then the stage of a multi-stage integrator at `STAGE`
+
+        for state in particle_buffers.iter().chain(Some(&stage_buffer)) {
+            particle_bind_groups.push(force_bindings.bind_group(
+                device,
+                &compute_bind_group_layout,
+                state,
+                interface,
+            ));
+        }
+
+        let integrate_bind_groups = (0..2)
+            .map(|src| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &integrate_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: sim_param_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: particle_buffers[src].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: stage_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: particle_buffers[(src + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: accelerations_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: derivatives_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                                buffer: &stage_params_buffer,
+                                offset: 0,
+                                size: wgpu::BufferSize::new(STAGE_PARAMS_SIZE),
+                            }),
+                        },
+                    ],
+                    label: None,
+                })
+            })
+            .collect();
+
+        let timestep_bind_groups = (0..2)
+            .map(|src| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &timestep_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: sim_param_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: particle_buffers[src].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: accelerations_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: maxima_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: clock_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: dt_bounds_buffer.as_entire_binding(),
+                        },
+                    ],
+                    label: None,
+                })
+            })
+            .collect();
+
+        let mut morton_bind_groups = Vec::<wgpu::BindGroup>::new();
+        for src in 0..2 {
+            for k in 0..2 {
+                morton_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &morton_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: sim_param_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: particle_buffers[src].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: particle_buffers[(src + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: sort_keys_buffers[k].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: sort_values_buffers[k].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: sort_keys_buffers[(k + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: sort_values_buffers[(k + 1) % 2].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 7,
+                            resource: histograms_buffer.as_entire_binding(),
+                        },
+                    ],
+                    label: None,
+                }));
+            }
+        }
+
+        let sort_pass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &sort_pass_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &sort_pass_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(mem::size_of::<u32>() as _),
+                }),
+            }],
+            label: None,
+        });
+
+        let (tree, mesh) = match (params.algorithm, solver_buffers) {
+            (Algorithm::BarnesHut, Some(solver)) => {
+                let tree = Tree {
+                    leaf_nodes_pipeline: solver_pipeline("leaf_nodes"),
+                    reduce_nodes_pipeline: solver_pipeline("reduce_nodes"),
+                    depth: tree_depth,
+                    level_stride: dynamic_stride,
+                    uniform_buffer: solver.passes,
+                };
+                (Some(tree), None)
+            }
+            (Algorithm::ParticleMesh, Some(solver)) => {
+                let mesh = Mesh {
+                    deposit_pipeline: solver_pipeline("deposit"),
+                    fft_pass_pipeline: solver_pipeline("fft_pass"),
+                    apply_kernel_pipeline: solver_pipeline("apply_kernel"),
+                    nodes_buffer: solver.storage,
+                    kernel_buffer: solver.kernel.expect("the mesh solver has a kernel"),
+                    fft_passes: mesh_fft_passes.len() as u32,
+                    deposit_work_group_count: num_cells.div_ceil(PARTICLES_PER_GROUP),
+                    fft_work_group_count: (mesh_side * mesh_side / 2).div_ceil(PARTICLES_PER_GROUP),
+                    kernel_work_group_count: (mesh_side * mesh_side).div_ceil(PARTICLES_PER_GROUP),
+                    pass_stride: dynamic_stride,
+                };
+                (None, Some(mesh))
+            }
+            _ => (None, None),
+        };
+
+        // only the brute-force pass does without the cell list
+        let cell_list = params.algorithm.uses_cell_list().then(|| CellList {
+            count_cells_pipeline: grid_pipeline("count_cells"),
+            scan_blocks_pipeline: grid_pipeline("scan_blocks"),
+            scan_block_sums_pipeline: grid_pipeline("scan_block_sums"),
+            add_block_offsets_pipeline: grid_pipeline("add_block_offsets"),
+            scatter_pipeline: grid_pipeline("scatter"),
+            sort_cells_pipeline: grid_pipeline("sort_cells"),
+            bind_groups: particle_buffers
+                .iter()
+                .chain(Some(&stage_buffer))
+                .map(|state| {
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &grid_bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: sim_param_buffer.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: state.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: particle_cells_buffer.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 3,
+                                resource: particle_ranks_buffer.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 4,
+                                resource: cell_counts_buffer.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 5,
+                                resource: cell_starts_buffer.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 6,
+                                resource: block_sums_buffer.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 7,
+                                resource: sorted_indices_buffer.as_entire_binding(),
+                            },
+                        ],
+                        label: None,
+                    })
+                })
+                .collect(),
+            scan_block_count,
+            cell_work_group_count: num_cells.div_ceil(PARTICLES_PER_GROUP),
+        });
+
+        // calculates number of work groups from PARTICLES_PER_GROUP constant
+        let work_group_count = params.num_particles.div_ceil(PARTICLES_PER_GROUP);
+
+        Simulation {
+            particle_bind_groups,
+            integrate_bind_groups,
+            morton_bind_groups,
+            sort_pass_bind_group,
+            particle_buffers,
+            sim_param_buffer,
+            attraction_matrix_buffer,
+            shader_param_buffer,
+            cell_counts_buffer,
+            cell_starts_buffer,
+            sorted_indices_buffer,
+            accelerations_buffer,
+            stage_buffer,
+            compute_pipeline,
+            morton_keys_pipeline: morton_pipeline("morton_keys"),
+            histogram_pipeline: morton_pipeline("histogram"),
+            scan_histogram_pipeline: morton_pipeline("scan_histogram"),
+            radix_scatter_pipeline: morton_pipeline("scatter"),
+            reorder_pipeline: morton_pipeline("reorder"),
+            step_passes,
+            integrate_pipelines,
+            stage_stride,
+            timestep: DtControl {
+                reduce_maxima_pipeline: timestep_pipeline("reduce_maxima"),
+                choose_dt_pipeline: timestep_pipeline("choose_dt"),
+                advance_clock_pipeline: timestep_pipeline("advance_clock"),
+                bind_groups: timestep_bind_groups,
+                clock_buffer,
+                bounds_buffer: dt_bounds_buffer,
+                block_count: dt_block_count,
+                adaptive: params.adaptive_dt,
+            },
+            work_group_count,
+            sort_block_count,
+            sort_passes,
+            sort_pass_stride,
+            cell_list,
+            tree,
+            mesh,
+            src: 0,
+            step_count: 0,
+            params,
+        }
+    }
+
+    /// buffer holding the particles after the last completed step, used as the vertex buffer
+    pub fn output_buffer(&self) -> &wgpu::Buffer {
+        &self.particle_buffers[self.src]
+    }
+
+    /// records a Morton reorder of the particle buffers, swapping which one is current
+    pub fn encode_sort(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let src = self.src;
+        let mut spass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Morton Sort Pass"),
+        });
+        spass.set_bind_group(1, &self.sort_pass_bind_group, &[0]);
+
+        // writes the keys to buffer 0, so the first radix pass reads from there
+        spass.set_pipeline(&self.morton_keys_pipeline);
+        spass.set_bind_group(0, &self.morton_bind_groups[src * 2 + 1], &[]);
+        spass.dispatch_workgroups(self.work_group_count, 1, 1);
+
+        for pass in 0..self.sort_passes {
+            let k = (pass % 2) as usize;
+            spass.set_bind_group(0, &self.morton_bind_groups[src * 2 + k], &[]);
+            spass.set_bind_group(
+                1,
+                &self.sort_pass_bind_group,
+                &[pass * self.sort_pass_stride],
+            );
+
+            spass.set_pipeline(&self.histogram_pipeline);
+            spass.dispatch_workgroups(self.sort_block_count, 1, 1);
+            spass.set_pipeline(&self.scan_histogram_pipeline);
+            spass.dispatch_workgroups(1, 1, 1);
+            spass.set_pipeline(&self.radix_scatter_pipeline);
+            spass.dispatch_workgroups(self.sort_block_count, 1, 1);
+        }
+
+        let k = (self.sort_passes % 2) as usize;
+        spass.set_pipeline(&self.reorder_pipeline);
+        spass.set_bind_group(0, &self.morton_bind_groups[src * 2 + k], &[]);
+        spass.dispatch_workgroups(self.work_group_count, 1, 1);
+
+        self.src = 1 - src;
+    }
+
+    /// records the cell list and compute passes of a single step and advances `step_count`,
+    /// reordering the particles first when a Morton sort is due
+    pub fn encode_step(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let sort_every = self.params.sort_every as u64;
+        if sort_every > 0 && self.step_count % sort_every == 0 {
+            self.encode_sort(encoder);
+        }
+        let src = self.src;
+
+        let mut integrate_pass = 0;
+        for pass in &self.step_passes {
+            match pass {
+                StepPass::Forces { stage } => {
+                    self.encode_forces(encoder, if *stage { STAGE } else { src });
+                }
+                StepPass::Timestep => {
+                    self.timestep.encode(encoder, src, &self.sim_param_buffer);
+                }
+                StepPass::Integrate { .. } => {
+                    let mut ipass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some("Integrate Pass"),
+                    });
+                    ipass.set_pipeline(&self.integrate_pipelines[integrate_pass]);
+                    ipass.set_bind_group(
+                        0,
+                        &self.integrate_bind_groups[src],
+                        &[integrate_pass as u32 * self.stage_stride],
+                    );
+                    ipass.dispatch_workgroups(self.work_group_count, 1, 1);
+                    integrate_pass += 1;
+                }
+            }
+        }
+
+        self.src = 1 - src;
+        self.step_count += 1;
+    }
+
+    /// rebuilds the cell list from `state`, a particle buffer or `STAGE`, if the algorithm
+    /// has one, and runs the force pass over it, leaving the result in `accelerations_buffer`
+    fn encode_forces(&self, encoder: &mut wgpu::CommandEncoder, state: usize) {
+        if let Some(cell_list) = &self.cell_list {
+            encoder.clear_buffer(&self.cell_counts_buffer, 0, None);
+            cell_list.encode(
+                encoder,
+                state,
+                self.work_group_count,
+                self.params.deterministic,
+            );
+        }
+
+        if let Some(mesh) = &self.mesh {
+            encoder.clear_buffer(&mesh.nodes_buffer, 0, None);
+        }
+
+        // compute pass
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        let bind_group = &self.particle_bind_groups[state];
+        if let Some(tree) = &self.tree {
+            tree.encode(&mut cpass, bind_group);
+        } else if let Some(mesh) = &self.mesh {
+            mesh.encode(&mut cpass, bind_group);
+        } else {
+            cpass.set_bind_group(0, bind_group, &[]);
+        }
+        cpass.set_pipeline(&self.compute_pipeline);
+        cpass.dispatch_workgroups(self.work_group_count, 1, 1);
+    }
+
+    /// advances the simulation by `steps` steps, one submission per step
+    pub fn step(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, steps: u32) {
+        for _ in 0..steps {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Step Command Encoder"),
+            });
+            self.encode_step(&mut encoder);
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    /// blocks until the clock has been copied back to the cpu
+    pub fn read_clock(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Clock {
+        read_buffer(device, queue, &self.timestep.clock_buffer)[0]
+    }
+
+    /// carries on from `clock`, e.g. the one of a snapshot
+    pub fn set_clock(&mut self, clock: Clock, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.timestep.clock_buffer, 0, bytemuck::bytes_of(&clock));
+    }
+
+    /// queues a copy of the clock without waiting for it. `callback` gets the clock once
+    /// the copy is done and the device has been polled
+    pub fn request_clock(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        callback: impl FnOnce(Clock) + Send + 'static,
+    ) {
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            queue,
+            &self.timestep.clock_buffer.slice(..),
+            move |result| {
+                if let Ok(buffer) = result {
+                    callback(bytemuck::pod_collect_to_vec(&buffer)[0]);
+                }
+            },
+        );
+    }
+
+    /// blocks until the current particle state has been copied back to the cpu, in id order
+    pub fn read_particles(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<Particle> {
+        let mut particles: Vec<Particle> = read_buffer(device, queue, self.output_buffer());
+        particles.sort_by_key(|particle| particle.id);
+        particles
+    }
+
+    /// swaps the force shader of a `brute_force` or `uniform_grid` run, rebuilding only the
+    /// force pipeline and its bind groups: particles, step count and clock carry on. Errors
+    /// leave `self` untouched, so the last shader that built keeps running
+    pub fn try_set_shader(
+        &mut self,
+        shader_buffer: String,
+        device: &wgpu::Device,
+    ) -> Result<(), String> {
+        if !self.params.algorithm.uses_shader_buffer() {
+            return Err(format!(
+                "{} runs a built-in solver rather than a shader",
+                self.params.algorithm
+            ));
+        }
+        let mut params = self.params.clone();
+        params.shader_buffer = shader_buffer;
+        let interface = Self::check_shader(&params)?;
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let (_, compute_source) = force_shader(&params);
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(compute_source),
+        });
+        let shader_param_buffer = create_shader_param_buffer(&params, device);
+        let force_bindings = ForceBindings {
+            params: &self.sim_param_buffer,
+            accelerations: &self.accelerations_buffer,
+            attraction_matrix: &self.attraction_matrix_buffer,
+            cell_starts: &self.cell_starts_buffer,
+            cell_counts: &self.cell_counts_buffer,
+            sorted_indices: &self.sorted_indices_buffer,
+            shader_params: &shader_param_buffer,
+            solver: None,
+        };
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &force_bindings.layout_entries(&interface),
+                label: None,
+            });
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: force_entry_point(params.algorithm),
+        });
+        let particle_bind_groups = self
+            .particle_buffers
+            .iter()
+            .chain(Some(&self.stage_buffer))
+            .map(|state| {
+                force_bindings.bind_group(device, &compute_bind_group_layout, state, &interface)
+            })
+            .collect();
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            return Err(format!(
+                "{} does not fit the {} pipeline: {}",
+                params.shader_name, params.algorithm, error
+            ));
+        }
+
+        self.compute_pipeline = compute_pipeline;
+        self.particle_bind_groups = particle_bind_groups;
+        self.shader_param_buffer = shader_param_buffer;
+        self.params = params;
+        Ok(())
+    }
+
+    /// carries the running simulation on with the parameters of `params` that live in
+    /// uniforms or the attraction matrix, writing only the buffers whose contents change.
+    /// Returns the fields that differ but need a rebuild, see [`Params::structural_changes`]
+    pub fn update_params(&mut self, params: &Params, queue: &wgpu::Queue) -> Vec<&'static str> {
+        let structural = self.params.structural_changes(params);
+
+        let mut updated = self.params.clone();
+        updated.dt = params.dt;
+        updated.well_depth = params.well_depth;
+        updated.attract_coeff = params.attract_coeff;
+        updated.repulse_coeff = params.repulse_coeff;
+        updated.friction_coeff = params.friction_coeff;
+        if !structural.contains(&"boundary") {
+            updated.boundary = params.boundary;
+        }
+        updated.deterministic = params.deterministic;
+        updated.sort_every = params.sort_every;
+        updated.theta = params.theta;
+        updated.gravity = params.gravity;
+        updated.particle_radius = params.particle_radius;
+        // bounds being typed in can be briefly invalid, the running ones stay until they are not
+        if params.check_dt_bounds().is_ok() {
+            updated.dt_min = params.dt_min;
+            updated.dt_max = params.dt_max;
+            updated.courant = params.courant;
+        }
+        updated.shader_values = params.shader_values.clone();
+        // drawn by the renderer, nothing to write here
+        updated.type_colours = params.type_colours.clone();
+        if !structural.contains(&"attraction_matrix") {
+            updated.attraction_matrix = params.attraction_matrix.clone();
+        }
+
+        // an adaptive dt is written into the uniform by the GPU every step
+        if updated.to_slice() != self.params.to_slice() {
+            queue.write_buffer(
+                &self.sim_param_buffer,
+                0,
+                bytemuck::cast_slice(&updated.to_slice()),
+            );
+        }
+        if updated.attraction_matrix != self.params.attraction_matrix {
+            queue.write_buffer(
+                &self.attraction_matrix_buffer,
+                0,
+                bytemuck::cast_slice(updated.attraction_matrix_slice()),
+            );
+        }
+        // `updated` keeps the running shader, so the values are packed for its layout
+        if updated.shader_values != self.params.shader_values {
+            queue.write_buffer(
+                &self.shader_param_buffer,
+                0,
+                bytemuck::cast_slice(&updated.shader_param_slice()),
+            );
+        }
+        if updated.dt_bounds_slice() != self.params.dt_bounds_slice() {
+            queue.write_buffer(
+                &self.timestep.bounds_buffer,
+                0,
+                bytemuck::cast_slice(&updated.dt_bounds_slice()),
+            );
+        }
+        if let Some(tree) = &self.tree {
+            let data = Tree::uniform_data(&updated, tree.depth, tree.level_stride);
+            if data != Tree::uniform_data(&self.params, tree.depth, tree.level_stride) {
+                queue.write_buffer(&tree.uniform_buffer, 0, &data);
+            }
+        }
+        // the kernel is costly to compute, so it is only redone when its inputs change.
+        // A boundary that resizes the mesh is left for a rebuild by `structural_changes`
+        let kernel_inputs =
+            |params: &Params| (params.gravity, params.particle_radius, params.boundary);
+        if let Some(mesh) = &self.mesh {
+            if kernel_inputs(&updated) != kernel_inputs(&self.params) {
+                let kernel = mesh::field_kernel(&updated);
+                queue.write_buffer(&mesh.kernel_buffer, 0, bytemuck::cast_slice(&kernel));
+            }
+        }
+
+        self.params = updated;
+        structural
+    }
+}
+
+/// blocks until `buffer` has been copied back to the cpu
+pub fn read_buffer<T: bytemuck::Pod + Send>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+) -> Vec<T> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    wgpu::util::DownloadBuffer::read_buffer(device, queue, &buffer.slice(..), move |result| {
+        let _ = sender.send(result.map(|buffer| bytemuck::pod_collect_to_vec(&buffer)));
+    });
+    device.poll(wgpu::Maintain::Wait);
+
+    receiver
+        .recv()
+        .expect("buffer readback callback was dropped")
+        .expect("failed to map buffer")
+}
+
+/// requests an adapter and device without creating a window or surface,
+/// honouring the same WGPU_* environment variables as the windowed app
+pub async fn request_headless_device() -> Option<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    let backends = wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all);
+    let dx12_shader_compiler = wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default();
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        dx12_shader_compiler,
+    });
+
+    let adapter = wgpu::util::initialize_adapter_from_env_or_default(&instance, None).await?;
+
+    let downlevel_capabilities = adapter.get_downlevel_capabilities();
+    if !downlevel_capabilities
+        .flags
+        .contains(Simulation::required_downlevel_capabilities().flags)
+    {
+        return None;
+    }
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Headless Device"),
+                features: wgpu::Features::empty(),
+                limits: Simulation::required_limits().using_resolution(adapter.limits()),
+            },
+            None,
+        )
+        .await
+        .ok()?;
+
+    Some((adapter, device, queue))
+}
@@ -0,0 +1,264 @@
+//! Binary snapshots of a running simulation.
+//!
+//! Layout (all integers and floats little-endian):
+//!
+//! | field        | type                                              |
+//! |--------------|---------------------------------------------------|
+//! | magic        | `b"EDENSNAP"`                                     |
+//! | version      | u32                                               |
+//! | step count   | u64                                               |
+//! | clock        | dt, time, time's summation error, padding as f32  |
+//! | params       | u32 length + scenario TOML                        |
+//! | shader       | u32 length + WGSL source                          |
+//! | camera       | u8 flag, then x, y, zoom, aspect ratio as f32     |
+//! | particles    | u32 count, then one record per particle, by id    |
+//!
+//! A particle record is `pos.x, pos.y, vel.x, vel.y, mass, kind` as f32.
+//! Version 1 snapshots have no clock and resume with the simulated time at zero.
+//! The neighbour-search fields are not stored; they are rebuilt on the first step.
+//! Particle ids are the record order, whatever order the GPU buffers were in.
+//! When `Particle` changes, bump `VERSION` and keep a reader for the old layout.
+
+use std::{
+    fmt, fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    scenario::{Scenario, ScenarioError},
+    simulation::Clock,
+    Camera, Params, Particle, Simulation,
+};
+
+pub const MAGIC: &[u8; 8] = b"EDENSNAP";
+pub const VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    NotASnapshot,
+    UnsupportedVersion(u32),
+    Params(ScenarioError),
+    Corrupt(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::NotASnapshot => write!(f, "not an eden snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(
+                f,
+                "snapshot version {} is newer than this build understands ({})",
+                v, VERSION
+            ),
+            SnapshotError::Params(e) => write!(f, "snapshot params: {}", e),
+            SnapshotError::Corrupt(e) => write!(f, "corrupt snapshot: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<ScenarioError> for SnapshotError {
+    fn from(e: ScenarioError) -> Self {
+        SnapshotError::Params(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub params: Params,
+    pub camera: Option<Camera>,
+    pub step_count: u64,
+    /// simulated time and the current dt, which adaptive stepping has moved off `params.dt`
+    pub clock: Clock,
+    pub particles: Vec<Particle>,
+}
+
+impl Snapshot {
+    /// downloads the current particle state, blocking until the gpu is done
+    pub fn capture(
+        sim: &Simulation,
+        camera: Option<Camera>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        Snapshot {
+            params: sim.params.clone(),
+            camera,
+            step_count: sim.step_count,
+            clock: sim.read_clock(device, queue),
+            particles: sim.read_particles(device, queue),
+        }
+    }
+
+    /// rebuilds a simulation that continues from the captured step, or says why the stored
+    /// shader no longer builds, e.g. against a particle layout that has changed since
+    pub fn to_simulation(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Simulation, String> {
+        let mut sim = Simulation::try_from_particles(self.params.clone(), &self.particles, device)?;
+        sim.step_count = self.step_count;
+        sim.set_clock(self.clock, queue);
+        Ok(sim)
+    }
+
+    /// writes the snapshot, leaving any file at `path` as it was if the params would
+    /// not load again
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        Scenario::from_params(&self.params).validate()?;
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        Self::read_from(&mut BufReader::new(fs::File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> Result<(), SnapshotError> {
+        let scenario = Scenario::from_params(&self.params).to_toml()?;
+
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.step_count.to_le_bytes())?;
+        for value in bytemuck::cast::<Clock, [f32; 4]>(self.clock) {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        write_str(out, &scenario)?;
+        write_str(out, &self.params.shader_buffer)?;
+
+        match &self.camera {
+            Some(camera) => {
+                out.write_all(&[1])?;
+                for value in camera.to_slice() {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+            None => out.write_all(&[0])?,
+        }
+
+        out.write_all(&(self.particles.len() as u32).to_le_bytes())?;
+        for p in &self.particles {
+            for value in [p.pos[0], p.pos[1], p.vel[0], p.vel[1], p.mass, p.kind] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(input: &mut R) -> Result<Self, SnapshotError> {
+        let mut magic = [0u8; 8];
+        input
+            .read_exact(&mut magic)
+            .map_err(|_| SnapshotError::NotASnapshot)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        match read_u32(input)? {
+            version @ (1 | 2) => read_body(input, version),
+            version => Err(SnapshotError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// everything after the version, which only adds the clock in version 2
+fn read_body<R: Read>(input: &mut R, version: u32) -> Result<Snapshot, SnapshotError> {
+    let step_count = read_u64(input)?;
+    let clock = if version >= 2 {
+        let mut values = [0.0; 4];
+        for value in &mut values {
+            *value = read_f32(input)?;
+        }
+        bytemuck::cast(values)
+    } else {
+        Clock::default()
+    };
+    let scenario = Scenario::from_toml(&read_str(input)?)?;
+    let params = scenario.to_params_with_shader(read_str(input)?)?;
+
+    let mut flag = [0u8; 1];
+    input.read_exact(&mut flag)?;
+    let camera = match flag[0] {
+        0 => None,
+        1 => Some(Camera::new(
+            read_f32(input)?,
+            read_f32(input)?,
+            read_f32(input)?,
+            read_f32(input)?,
+        )),
+        other => return Err(SnapshotError::Corrupt(format!("camera flag {}", other))),
+    };
+
+    let count = read_u32(input)?;
+    if count != params.num_particles {
+        return Err(SnapshotError::Corrupt(format!(
+            "{} particles stored but num_particles is {}",
+            count, params.num_particles
+        )));
+    }
+
+    let mut particles = Vec::with_capacity(count as usize);
+    for id in 0..count {
+        let mut particle = Particle::new();
+        particle.id = id;
+        particle.pos = [read_f32(input)?, read_f32(input)?];
+        particle.vel = [read_f32(input)?, read_f32(input)?];
+        particle.mass = read_f32(input)?;
+        particle.kind = read_f32(input)?;
+        particles.push(particle);
+    }
+
+    Ok(Snapshot {
+        params,
+        camera,
+        step_count,
+        clock,
+        particles,
+    })
+}
+
+fn write_str<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    out.write_all(&(s.len() as u32).to_le_bytes())?;
+    out.write_all(s.as_bytes())
+}
+
+fn read_str<R: Read>(input: &mut R) -> Result<String, SnapshotError> {
+    let len = read_u32(input)? as usize;
+    let mut bytes = Vec::new();
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(SnapshotError::Corrupt("truncated string".to_string()));
+    }
+    String::from_utf8(bytes).map_err(|e| SnapshotError::Corrupt(e.to_string()))
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(input: &mut R) -> io::Result<f32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
@@ -3,112 +3,53 @@ use std::{borrow::Cow, mem};
 
 use eden::Particle;
 
+use super::capture::CaptureTarget;
+
 use wgpu::{util::DeviceExt, TextureView};
 
 #[derive(Debug)]
 pub struct State {
-    particle_bind_groups: Vec<wgpu::BindGroup>,
-    preprocessing_bind_groups: Vec<wgpu::BindGroup>,
-    cleanup_bind_groups: Vec<wgpu::BindGroup>,
-    pub active_particles: u32,
-    pub particle_buffers: Vec<wgpu::Buffer>,
-    pub bucket_indeces_buffer: wgpu::Buffer,
+    pub sim: eden::Simulation,
     circle_buffer: wgpu::Buffer,
-    compute_pipeline: wgpu::ComputePipeline,
-    preprocessing_pipeline: wgpu::ComputePipeline,
-    cleanup_pipeline: wgpu::ComputePipeline,
+    /// the particle radius the circle in `circle_buffer` was drawn with
+    circle_radius: f32,
     render_pipeline: wgpu::RenderPipeline,
-    work_group_count: u32,
-    frame_num: usize,
     pub camera: eden::Camera,
     pub camera_uniform_buffer: wgpu::Buffer,
-    pub params: eden::Params,
     camera_bind_group: wgpu::BindGroup,
+    capture_camera_buffer: wgpu::Buffer,
+    capture_camera_bind_group: wgpu::BindGroup,
+    /// the `TypeColours` uniform of draw.wgsl and what was last written to it
+    colour_buffer: wgpu::Buffer,
+    colours: Vec<f32>,
     // post-processing stuff
     // tex_view: Option<wgpu::TextureView>,
 }
 
 impl State {
-    pub fn required_limits() -> wgpu::Limits {
-        //set surface limits based on the target architecture
-        if cfg!(target_arch = "wasm32") {
-            wgpu::Limits::downlevel_webgl2_defaults()
-        } else {
-            wgpu::Limits::default()
-        }
-    }
-
-    pub fn required_downlevel_capabilities() -> wgpu::DownlevelCapabilities {
-        //downlevel capabilites that don't confirm to WebGPU standard
-        wgpu::DownlevelCapabilities {
-            flags: wgpu::DownlevelFlags::COMPUTE_SHADERS,
-            ..Default::default()
-        }
+    /// a fresh simulation, or why its shader does not fit the algorithm
+    pub fn try_init(
+        params: eden::Params,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+    ) -> Result<Self, String> {
+        eden::Simulation::try_new(params, device)
+            .map(|sim| Self::from_simulation(sim, config, device))
     }
 
-    pub fn init(
-        params: eden::Params,
+    /// wraps an existing simulation, e.g. one restored from a snapshot
+    pub fn from_simulation(
+        sim: eden::Simulation,
         config: &wgpu::SurfaceConfiguration,
-        _adapter: &wgpu::Adapter,
         device: &wgpu::Device,
-        _queue: &wgpu::Queue,
     ) -> Self {
-        //create parameters
-        let params = params;
-        let params_slice = params.to_slice();
-        let params_attraction_matrix = params.attraction_matrix_slice();
-
-        //create bucket index buffer data
-
-        let bucket_indeces_data: Vec<i32> = vec![-1; params.num_grids_side.pow(2) as usize];
-
-        //nitialize preprocessing shader
-        let preprocessing_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Preprocessing Shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-                "shaders/preprocnew.wgsl"
-            ))),
-        });
-
-        //initialize compute shader module
-        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&params.shader_buffer)),
-        });
-
-        let cleanup_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/cleanup.wgsl"))),
-        });
-
         //initialize vertex and fragment shaders
         let draw_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/draw.wgsl"))),
         });
 
-        //set up uniform buffer to store global parameters
-        let sim_param_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Parameter Buffer"),
-            contents: bytemuck::cast_slice(&params_slice),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        //set up uniform buffer to store global parameters
-        let attraction_matrix_buffer =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Parameter Buffer"),
-                contents: bytemuck::cast_slice(params_attraction_matrix),
-                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            });
-
-        let bucket_indeces_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Bucket Indeces"),
-            contents: bytemuck::cast_slice(&bucket_indeces_data),
-            usage: wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::COPY_SRC,
-        });
+        let params = &sim.params;
 
         //set up camera buffer
         // let camera = Camera::new(1.0 / (params.world_size * 1.5));
@@ -125,217 +66,27 @@ impl State {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let preprocessing_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    //PARAM buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params_slice.len() * mem::size_of::<f32>()) as _,
-                            ),
-                        },
-                        count: None,
-                    },
-                    //input / source buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params.num_particles * (mem::size_of::<Particle>() as u32)) as _,
-                            ), //CHANGE SIZE IF ISSUES
-                        },
-                        count: None,
-                    },
-                    //output / destination buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params.num_particles * (mem::size_of::<Particle>() as u32)) as _,
-                            ),
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                bucket_indeces_data.len() as u64
-                                    * std::mem::size_of::<i32>() as u64,
-                            ),
-                        },
-                        count: None,
-                    },
-                ],
-                label: Some("Preprocessing Bind Group Layout"),
-            });
-
-        //set up compute bind group layouts and compute pipeline layours
-        let compute_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    //PARAM buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params_slice.len() * mem::size_of::<f32>()) as _,
-                            ),
-                        },
-                        count: None,
-                    },
-                    //input / source buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params.num_particles * (mem::size_of::<Particle>() as u32)) as _,
-                            ), //CHANGE SIZE IF ISSUES
-                        },
-                        count: None,
-                    },
-                    //output / destination buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params.num_particles * (mem::size_of::<Particle>() as u32)) as _,
-                            ),
-                        },
-                        count: None,
-                    },
-                    //attraction matrix buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params_attraction_matrix.len() * mem::size_of::<f32>()) as _,
-                            ),
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 4,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                bucket_indeces_data.len() as u64 * mem::size_of::<i32>() as u64,
-                            ),
-                        },
-                        count: None,
-                    },
-                ],
-                label: None,
-            });
-
-        let cleanup_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    //input / source buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params.num_particles * (mem::size_of::<Particle>() as u32)) as _,
-                            ), //CHANGE SIZE IF ISSUES
-                        },
-                        count: None,
-                    },
-                    //output / destination buffer
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                (params.num_particles * (mem::size_of::<Particle>() as u32)) as _,
-                            ),
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: false },
-                            has_dynamic_offset: false,
-                            min_binding_size: wgpu::BufferSize::new(
-                                bucket_indeces_data.len() as u64
-                                    * std::mem::size_of::<i32>() as u64,
-                            ),
-                        },
-                        count: None,
-                    },
-                ],
-                label: Some("Cleanup Bind Group Layout"),
-            });
-
-        let preprocessing_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Preprocessing"),
-                bind_group_layouts: &[&preprocessing_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        //compute pipeline layout =
-        let compute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute"),
-                bind_group_layouts: &[&compute_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let cleanup_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Preprocessing"),
-                bind_group_layouts: &[&cleanup_group_layout],
-                push_constant_ranges: &[],
-            });
+        let colours = params.colour_slice();
+        let colour_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Type Colour Buffer"),
+            contents: bytemuck::cast_slice(&colours),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
 
-        //camera bind group layout
+        //camera bind group layout, the type colours ride along
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
         let camera_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
+                entries: &[uniform_entry(0), uniform_entry(1)],
                 label: Some("camera_bind_group_layout"),
             });
 
@@ -389,30 +140,6 @@ impl State {
             multiview: None,
         });
 
-        // create compute pipeline
-        let preprocessing_pipeline =
-            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("Preprocessing Pipeline"),
-                layout: Some(&preprocessing_pipeline_layout),
-                module: &preprocessing_shader,
-                entry_point: "main",
-            });
-
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: "main",
-        });
-
-        // create compute pipeline
-        let cleanup_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Cleanup Pipeline"),
-            layout: Some(&cleanup_pipeline_layout),
-            module: &cleanup_shader,
-            entry_point: "main",
-        });
-
         //buffer for particle circle coordinates
 
         //  let circle_buffer_data = [-0.01f32, -0.02, 0.01, -0.02, 0.00, 0.02];
@@ -422,169 +149,90 @@ impl State {
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
-        let mut initial_particle_data: Vec<f32> = Vec::new();
-
-        for _ in 0..params.num_particles {
-            initial_particle_data.extend_from_slice(&eden::Particle::new_random(&params).to_slice())
-        }
-
-        // creates two buffers of particle data each of size NUM_PARTICLES
-        // the two buffers alternate as dst and src for each frame
-
-        let mut particle_buffers = Vec::<wgpu::Buffer>::new();
-        let mut preprocessing_bind_groups = Vec::<wgpu::BindGroup>::new();
-        let mut particle_bind_groups = Vec::<wgpu::BindGroup>::new();
-
-        let mut cleanup_bind_groups = Vec::<wgpu::BindGroup>::new();
-
-        for i in 0..3 {
-            particle_buffers.push(
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some(&format!("Particle Buffer {}", i)),
-                    contents: bytemuck::cast_slice(&initial_particle_data),
-                    usage: wgpu::BufferUsages::VERTEX
-                        | wgpu::BufferUsages::STORAGE
-                        | wgpu::BufferUsages::COPY_DST
-                        | wgpu::BufferUsages::COPY_SRC,
-                }),
-            );
-        }
-
         //camera bind group
         let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
-
-        // create two bind groups, one for each buffer as the src
-        // where the alternate buffer is used as the dst
-
-        preprocessing_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &preprocessing_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: sim_param_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: particle_buffers[0].as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: particle_buffers[1].as_entire_binding(), // bind to opposite buffer
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: bucket_indeces_buffer.as_entire_binding(),
-                },
-            ],
-            label: None,
-        }));
-
-        preprocessing_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &preprocessing_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: sim_param_buffer.as_entire_binding(),
+                    resource: camera_uniform_buffer.as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: particle_buffers[1].as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: particle_buffers[0].as_entire_binding(), // bind to opposite buffer
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: bucket_indeces_buffer.as_entire_binding(),
-                },
-            ],
-            label: None,
-        }));
-
-        particle_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &compute_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: sim_param_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: particle_buffers[1].as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: particle_buffers[2].as_entire_binding(), // bind to opposite buffer
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: attraction_matrix_buffer.as_entire_binding(), // bind to opposite buffer
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: bucket_indeces_buffer.as_entire_binding(),
+                    resource: colour_buffer.as_entire_binding(),
                 },
             ],
-            label: None,
-        }));
+            label: Some("camera_bind_group"),
+        });
 
-        cleanup_bind_groups.push(device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &cleanup_group_layout,
+        //offscreen captures get their own camera so the window's aspect ratio is untouched
+        let capture_camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Capture Camera Buffer"),
+            contents: bytemuck::cast_slice(&(camera.to_slice())),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let capture_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: particle_buffers[2].as_entire_binding(),
+                    resource: capture_camera_buffer.as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: particle_buffers[0].as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: bucket_indeces_buffer.as_entire_binding(),
+                    resource: colour_buffer.as_entire_binding(),
                 },
             ],
-            label: None,
-        }));
-
-        // calculates number of work groups from PARTICLES_PER_GROUP constant
-        let work_group_count = u32::min(params.num_particles, 65535);
-
-        let active_particles: u32 = params.num_particles;
-        // returns Example struct and No encoder commands
+            label: Some("capture_camera_bind_group"),
+        });
 
         //post-processing
         // let tex_view: wgpu::TextureView = device.create_texture()
 
         State {
-            particle_bind_groups,
-            preprocessing_bind_groups,
-            cleanup_bind_groups,
-            active_particles,
-            particle_buffers,
-            bucket_indeces_buffer,
+            circle_radius: sim.params.particle_radius,
+            sim,
             circle_buffer,
-            compute_pipeline,
-            preprocessing_pipeline,
             render_pipeline,
-            cleanup_pipeline,
-            work_group_count,
-            frame_num: 0,
             camera,
             camera_uniform_buffer,
-            params,
             camera_bind_group,
+            capture_camera_buffer,
+            capture_camera_bind_group,
+            colour_buffer,
+            colours,
+        }
+    }
+
+    /// writes the type colours and particle radius of the running simulation's
+    /// parameters, if they changed
+    pub fn update_drawing(&mut self, queue: &wgpu::Queue) {
+        let colours = self.sim.params.colour_slice();
+        if colours != self.colours {
+            queue.write_buffer(&self.colour_buffer, 0, bytemuck::cast_slice(&colours));
+            self.colours = colours;
+        }
+        let radius = self.sim.params.particle_radius;
+        if radius != self.circle_radius {
+            let circle = eden::generate_circle(radius);
+            queue.write_buffer(&self.circle_buffer, 0, bytemuck::bytes_of(&circle));
+            self.circle_radius = radius;
         }
     }
 
+    /// restores a saved camera position, keeping the current window's aspect ratio
+    pub fn set_camera(&mut self, camera: eden::Camera, queue: &wgpu::Queue) {
+        self.camera = eden::Camera {
+            aspect_ratio: self.camera.aspect_ratio,
+            ..camera
+        };
+        queue.write_buffer(
+            &(self.camera_uniform_buffer),
+            0,
+            bytemuck::cast_slice(&[self.camera.to_slice()]),
+        );
+    }
+
     /// update is called for any WindowEvent not handled by the framework
     pub fn update(&mut self, _event: winit::event::WindowEvent) {
         //empty
@@ -605,92 +253,42 @@ impl State {
         );
     }
 
+    /// prints how the particles are spread over the cells of the neighbour search
     pub fn debug(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        use eden::Particle;
-        use std::mem;
         use std::result;
 
-        let with_buffer =
-            |result: result::Result<wgpu::util::DownloadBuffer, wgpu::BufferAsyncError>| {
-                let buffer: &[u8] = &*result.unwrap();
-                let mut particle_buffer: Vec<Particle> = Vec::new();
-
-                for particle_bytes in buffer.chunks_exact(std::mem::size_of::<Particle>()) {
-                    let bytes_fixed: *const [u8; std::mem::size_of::<Particle>()] =
-                        particle_bytes.as_ptr() as *const [u8; std::mem::size_of::<Particle>()];
-                    unsafe {
-                        let new_particle: Particle = mem::transmute(*bytes_fixed);
-                        particle_buffer.push(new_particle);
-                    }
-                }
-
-                let mut accumulator: u32 = 0;
-                let mut accumulator_avg: u32 = 0;
-                let mut accumulator_mass: u32 = 0;
-                for particle in &particle_buffer {
-                    if !(particle.fptr == -1.0 || particle.bptr == -1.0) {
-                        accumulator = std::cmp::max(
-                            accumulator,
-                            ((particle.fptr as u32 - particle.bptr as u32) / 2) as u32,
-                        );
-
-                        accumulator_avg +=
-                            ((particle.fptr as u32 - particle.bptr as u32) / 2) as u32;
-
-                        accumulator_mass = std::cmp::max(particle.mass as u32, accumulator_mass);
-                    }
-                }
-
-                accumulator_avg = accumulator_avg / particle_buffer.len() as u32;
-                //accumulator_mass = accumulator_mass / particle_buffer.len() as u32;
-                //println!("{:#?}", particle_buffer);
-                let mut i = 0;
-                for particle in particle_buffer {
-                    println!(
-                        "{}: {} {} {} ",
-                        i, particle.fptr, particle.bptr, particle.mass
-                    );
-                    i += 1;
-                }
-                println!("MAXIMUM DISTANCE CHECKED: {}", accumulator);
-                println!("AVERAGE DISTANCE CHAECKED: {}", accumulator_avg);
-                println!("MAX PARTICLES / CELL: {}", accumulator_mass);
-            };
-
-        let with_buffer_index =
+        let with_cell_counts =
             |result: result::Result<wgpu::util::DownloadBuffer, wgpu::BufferAsyncError>| {
-                let buffer: &[u8] = &*result.unwrap();
-                let mut index_buffer: Vec<i32> = Vec::new();
-
-                for bytes in buffer.chunks_exact(std::mem::size_of::<i32>()) {
-                    let bytes_fixed: *const [u8; std::mem::size_of::<i32>()] =
-                        bytes.as_ptr() as *const [u8; std::mem::size_of::<i32>()];
-
-                    unsafe {
-                        let new_i32: i32 = mem::transmute(*bytes_fixed);
-                        index_buffer.push(new_i32);
-                    }
-                }
-
-                // println!("{:#?}", index_buffer);
+                let cell_counts: Vec<u32> = bytemuck::pod_collect_to_vec(&result.unwrap());
+
+                let occupied = cell_counts.iter().filter(|&&count| count > 0).count();
+                let total: u32 = cell_counts.iter().sum();
+                let max = cell_counts.iter().copied().max().unwrap_or(0);
+
+                println!("CELLS: {}", cell_counts.len());
+                println!("OCCUPIED CELLS: {}", occupied);
+                println!("PARTICLES IN CELLS: {}", total);
+                println!("MAX PARTICLES / CELL: {}", max);
+                println!(
+                    "AVERAGE PARTICLES / OCCUPIED CELL: {:.2}",
+                    total as f32 / occupied.max(1) as f32
+                );
             };
 
-        println!("DEBUG PARTICLES ---------------");
+        println!("DEBUG CELL LIST ---------------");
 
-        wgpu::util::DownloadBuffer::read_buffer(
-            device,
-            queue,
-            &self.particle_buffers[1].slice(..),
-            with_buffer,
-        );
-
-        println!("DEBUG INDEX BUFFER ----------");
+        // brute force never fills the cell counts, so they say nothing about the run
+        let algorithm = self.sim.params.algorithm;
+        if !algorithm.uses_cell_list() {
+            println!("NO CELL LIST: {} searches every pair", algorithm);
+            return;
+        }
 
         wgpu::util::DownloadBuffer::read_buffer(
             device,
             queue,
-            &self.bucket_indeces_buffer.slice(..),
-            with_buffer_index,
+            &self.sim.cell_counts_buffer.slice(..),
+            with_cell_counts,
         )
     }
     pub fn render(
@@ -718,76 +316,79 @@ impl State {
             depth_stencil_attachment: None,
         };
 
+        if play {
+            self.sim.step(device, queue, 1);
+        }
+
         // get command encoder
         let mut command_encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        let mut cleanup_command_encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-        if play {
-            //HAS TO BE ODD
-            for i in 0..3 {
-                let mut preprocessing_command_encoder =
-                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                        label: Some("Preprocessing Command Encoder"),
-                    });
-
-                {
-                    //preprocessing compute pass
-                    let mut ppass = preprocessing_command_encoder.begin_compute_pass(
-                        &wgpu::ComputePassDescriptor {
-                            label: Some("Preprocessing Pass"),
-                        },
-                    );
-                    ppass.set_pipeline(&self.preprocessing_pipeline);
-                    ppass.set_bind_group(0, &self.preprocessing_bind_groups[i % 2], &[]);
-                    ppass.dispatch_workgroups(self.work_group_count, 1, 1);
-                }
-                queue.submit(Some(preprocessing_command_encoder.finish()));
-            }
-            // compute pass
-            let mut cpass =
-                command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-            cpass.set_pipeline(&self.compute_pipeline);
-            cpass.set_bind_group(0, &self.particle_bind_groups[0], &[]);
-            cpass.dispatch_workgroups(self.work_group_count, 1, 1);
-
-            let mut clpass =
-                cleanup_command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                    label: Some("Cleanup Pass"),
-                });
-            clpass.set_pipeline(&self.cleanup_pipeline);
-            clpass.set_bind_group(0, &self.cleanup_bind_groups[0], &[]);
-
-            clpass.dispatch_workgroups(self.work_group_count, 1, 1);
-        } else {
-            self.frame_num -= 1;
-        }
 
         //render pass
         {
             // render pass
             let mut rpass = command_encoder.begin_render_pass(&render_pass_descriptor);
-            rpass.set_pipeline(&self.render_pipeline);
-            //load camera uniform buffer
-            rpass.set_bind_group(0, &self.camera_bind_group, &[]);
-            // render dst particles
-            rpass.set_vertex_buffer(0, self.particle_buffers[0].slice(..));
-            rpass.set_vertex_buffer(1, self.circle_buffer.slice(..));
-            // the three instance-local vertices ????
-            rpass.draw(
-                0..((eden::CIRCLE_RES * 3) as u32),
-                0..self.params.num_particles,
-            );
+            self.draw_particles(&mut rpass, &self.camera_bind_group);
         }
 
-        // update frame count
-        self.frame_num += 1;
-
         // done
         queue.submit(Some(command_encoder.finish()));
-        queue.submit(Some(cleanup_command_encoder.finish()));
     }
+
+    fn draw_particles<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, camera: &'a wgpu::BindGroup) {
+        rpass.set_pipeline(&self.render_pipeline);
+        //load camera uniform buffer
+        rpass.set_bind_group(0, camera, &[]);
+        // render dst particles
+        rpass.set_vertex_buffer(0, self.sim.output_buffer().slice(..));
+        rpass.set_vertex_buffer(1, self.circle_buffer.slice(..));
+        // the three instance-local vertices ????
+        rpass.draw(0..(eden::CIRCLE_RES * 3), 0..self.sim.params.num_particles);
+    }
+
+    /// draws the current particles, without the gui, into `target` at its own resolution
+    pub fn capture(
+        &self,
+        target: &CaptureTarget,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> image::RgbaImage {
+        let camera = eden::Camera {
+            aspect_ratio: target.aspect_ratio(),
+            ..self.camera
+        };
+        queue.write_buffer(
+            &self.capture_camera_buffer,
+            0,
+            bytemuck::cast_slice(&[camera.to_slice()]),
+        );
+
+        let (view, resolve_view) = target.views();
+        let color_attachments = [Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            resolve_target: resolve_view.as_ref(),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })];
+
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+        {
+            let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Capture Render Pass"),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: None,
+            });
+            self.draw_particles(&mut rpass, &self.capture_camera_bind_group);
+        }
+        queue.submit(Some(command_encoder.finish()));
+
+        target.read_image(device, queue)
+    }
+    #[allow(dead_code)]
     fn post_processing(
         &mut self,
         _view: &wgpu::TextureView,
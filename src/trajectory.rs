@@ -0,0 +1,227 @@
+//! Trajectory recording to CSV, extended XYZ or NumPy `.npy`.
+//!
+//! Frames are copied off the GPU with [`wgpu::util::DownloadBuffer`], so asking
+//! for one never blocks: the copy is queued behind the current step and the
+//! callback hands the particles over a channel. [`TrajectoryRecorder::drain`]
+//! writes whatever has arrived, and [`TrajectoryRecorder::finish`] waits for
+//! the rest.
+//!
+//! Every format stores `x, y, vx, vy` and the integer particle type per particle.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use crate::{BoundaryMode, Particle, Simulation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrajectoryFormat {
+    /// one row per particle per frame: `step,id,x,y,vx,vy,type`
+    Csv,
+    /// extended XYZ, readable by ParaView, OVITO and ASE
+    Xyz,
+    /// a single `float32` array of shape `(frames, particles, 5)`
+    Npy,
+}
+
+impl TrajectoryFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(TrajectoryFormat::Csv),
+            "xyz" | "extxyz" => Some(TrajectoryFormat::Xyz),
+            "npy" => Some(TrajectoryFormat::Npy),
+            _ => None,
+        }
+    }
+}
+
+/// the npy header is written up front with room for the final shape, then patched in `finish`
+const NPY_HEADER_LEN: usize = 128;
+
+struct Frame {
+    step: u64,
+    /// whether x and y wrapped around when the frame was taken
+    periodic: bool,
+    particles: Vec<Particle>,
+}
+
+pub struct TrajectoryRecorder {
+    pub path: PathBuf,
+    pub format: TrajectoryFormat,
+    /// record every `every` steps
+    pub every: u32,
+    out: BufWriter<File>,
+    num_particles: u32,
+    num_types: u32,
+    world_size: f32,
+    frames_written: u64,
+    frames_requested: u64,
+    last_step: Option<u64>,
+    sender: Sender<Frame>,
+    receiver: Receiver<Frame>,
+}
+
+impl TrajectoryRecorder {
+    pub fn create(
+        path: &Path,
+        format: TrajectoryFormat,
+        every: u32,
+        sim: &Simulation,
+    ) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            TrajectoryFormat::Csv => writeln!(out, "step,id,x,y,vx,vy,type")?,
+            TrajectoryFormat::Xyz => {}
+            TrajectoryFormat::Npy => out.write_all(&npy_header(0, sim.params.num_particles))?,
+        }
+
+        let (sender, receiver) = channel();
+        Ok(TrajectoryRecorder {
+            path: path.to_path_buf(),
+            format,
+            every: every.max(1),
+            out,
+            num_particles: sim.params.num_particles,
+            num_types: sim.params.num_types,
+            world_size: sim.params.world_size,
+            frames_written: 0,
+            frames_requested: 0,
+            last_step: None,
+            sender,
+            receiver,
+        })
+    }
+
+    /// queues a readback of the current state if the step counter has reached the next frame
+    pub fn record(&mut self, sim: &Simulation, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let step = sim.step_count;
+        let periodic = sim.params.boundary == BoundaryMode::Periodic;
+        if step % self.every as u64 != 0 || self.last_step == Some(step) {
+            return;
+        }
+        self.last_step = Some(step);
+        self.frames_requested += 1;
+
+        let sender = self.sender.clone();
+        wgpu::util::DownloadBuffer::read_buffer(
+            device,
+            queue,
+            &sim.output_buffer().slice(..),
+            move |result| {
+                if let Ok(buffer) = result {
+                    // the simulation reorders its buffers, so put the particles back in id order
+                    let mut particles: Vec<Particle> = bytemuck::pod_collect_to_vec(&buffer);
+                    particles.sort_by_key(|particle| particle.id);
+                    let _ = sender.send(Frame {
+                        step,
+                        periodic,
+                        particles,
+                    });
+                }
+            },
+        );
+    }
+
+    /// writes every frame whose readback has completed, without waiting for the rest
+    pub fn drain(&mut self) -> io::Result<()> {
+        while let Ok(frame) = self.receiver.try_recv() {
+            self.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    /// waits for outstanding readbacks, writes them and closes the file
+    pub fn finish(mut self, device: &wgpu::Device) -> io::Result<u64> {
+        while self.frames_written < self.frames_requested {
+            device.poll(wgpu::Maintain::Wait);
+            match self.receiver.try_recv() {
+                Ok(frame) => self.write_frame(&frame)?,
+                // a failed mapping never reports back, so don't wait on it forever
+                Err(_) => break,
+            }
+        }
+
+        if self.format == TrajectoryFormat::Npy {
+            self.out.seek(SeekFrom::Start(0))?;
+            self.out
+                .write_all(&npy_header(self.frames_written, self.num_particles))?;
+        }
+        self.out.flush()?;
+        Ok(self.frames_written)
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.frames_written
+    }
+
+    fn type_index(&self, particle: &Particle) -> u32 {
+        (particle.kind * self.num_types as f32).round() as u32
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        match self.format {
+            TrajectoryFormat::Csv => {
+                for p in &frame.particles {
+                    writeln!(
+                        self.out,
+                        "{},{},{},{},{},{},{}",
+                        frame.step,
+                        p.id,
+                        p.pos[0],
+                        p.pos[1],
+                        p.vel[0],
+                        p.vel[1],
+                        self.type_index(p)
+                    )?;
+                }
+            }
+            TrajectoryFormat::Xyz => {
+                // z is never periodic, the lattice is only one unit deep
+                let pbc = if frame.periodic { "T T F" } else { "F F F" };
+                writeln!(self.out, "{}", frame.particles.len())?;
+                writeln!(
+                    self.out,
+                    "Lattice=\"{l} 0 0 0 {l} 0 0 0 1\" Properties=species:S:1:pos:R:3:vel:R:3:type:I:1 step={} pbc=\"{}\"",
+                    frame.step,
+                    pbc,
+                    l = self.world_size
+                )?;
+                for p in &frame.particles {
+                    let kind = self.type_index(p);
+                    writeln!(
+                        self.out,
+                        "T{} {} {} 0 {} {} 0 {}",
+                        kind, p.pos[0], p.pos[1], p.vel[0], p.vel[1], kind
+                    )?;
+                }
+            }
+            TrajectoryFormat::Npy => {
+                for p in &frame.particles {
+                    let kind = self.type_index(p) as f32;
+                    for value in [p.pos[0], p.pos[1], p.vel[0], p.vel[1], kind] {
+                        self.out.write_all(&value.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+        self.frames_written += 1;
+        Ok(())
+    }
+}
+
+/// NPY v1.0 header for a little-endian `float32` array of shape `(frames, particles, 5)`
+fn npy_header(frames: u64, particles: u32) -> Vec<u8> {
+    let dict = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}, 5), }}",
+        frames, particles
+    );
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&((NPY_HEADER_LEN - 10) as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header.resize(NPY_HEADER_LEN - 1, b' ');
+    header.push(b'\n');
+    header
+}
@@ -0,0 +1,50 @@
+//! Notices shader files being saved, so an edited shader can be rebuilt while
+//! eden runs. Modification times are polled rather than subscribed to, which
+//! works the same on every platform and needs nothing but `std::fs`.
+
+use std::{collections::HashMap, fs, path::PathBuf, time::SystemTime};
+
+/// extension of the files [`ShaderWatcher`] looks at
+const SHADER_EXTENSION: &str = "wgsl";
+
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    pub dir: PathBuf,
+    /// last modification time seen for each shader in `dir`
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    /// watches the `.wgsl` files in `dir`; the ones already there count as seen
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let mut watcher = ShaderWatcher {
+            dir: dir.into(),
+            modified: HashMap::new(),
+        };
+        watcher.poll();
+        watcher
+    }
+
+    /// the shaders created or saved since the last poll, sorted by path. A directory
+    /// that cannot be read, e.g. one that does not exist yet, has no changes
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut changed = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(SHADER_EXTENSION) {
+                continue;
+            }
+            let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) else {
+                continue;
+            };
+            if self.modified.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
@@ -0,0 +1,80 @@
+use eden::{Algorithm, Params, Simulation};
+
+mod common;
+
+fn lennard_jones_params(algorithm: Algorithm) -> Params {
+    let mut params = Params::with_seed(11);
+    params.num_types = 3;
+    params.randomize_matrix();
+    params.num_particles = 200;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.shader_buffer = include_str!("../src/shaders/lennardjones.wgsl").to_string();
+    params.shader_name = "lennardjones.wgsl".to_string();
+    params.algorithm = algorithm;
+    params
+}
+
+const MISMATCHED: &str = "
+@group(0) @binding(1) var<uniform> scale : vec4<f32>;
+@group(0) @binding(2) var<storage, read_write> accelerations : array<vec2<f32>>;
+
+@compute
+@workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  accelerations[global_invocation_id.x] = scale.xy;
+}
+";
+
+#[test]
+fn brute_force_matches_a_grid_search_covering_the_world() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let run = |algorithm| {
+        let mut sim = Simulation::try_new(lennard_jones_params(algorithm), &device).unwrap();
+        sim.step(&device, &queue, 10);
+        sim.read_particles(&device, &queue)
+    };
+    let brute_force = run(Algorithm::BruteForce);
+    let grid = run(Algorithm::UniformGrid);
+    for (a, b) in brute_force.iter().zip(&grid) {
+        let d = ((a.pos[0] - b.pos[0]).powi(2) + (a.pos[1] - b.pos[1]).powi(2)).sqrt();
+        assert!(d < 1e-4, "particle {} is {} apart", a.id, d);
+    }
+}
+
+#[test]
+fn mismatched_shaders_are_errors_instead_of_panics() {
+    let Some((device, _queue)) = common::gpu() else {
+        return;
+    };
+
+    // binding 1 is the particle storage buffer, not a uniform
+    let mut params = lennard_jones_params(Algorithm::UniformGrid);
+    params.shader_buffer = MISMATCHED.to_string();
+    params.shader_name = "mismatched.wgsl".to_string();
+    let err = Simulation::try_new(params.clone(), &device).unwrap_err();
+    assert!(err.contains("mismatched.wgsl"), "{}", err);
+
+    params.algorithm = Algorithm::BruteForce;
+    let err = Simulation::try_new(params, &device).unwrap_err();
+    assert!(err.contains("own entry point"), "{}", err);
+
+    let mut params = lennard_jones_params(Algorithm::BruteForce);
+    params.shader_buffer = "fn force(".to_string();
+    assert!(Simulation::try_new(params, &device).is_err());
+
+    // the same device still builds a simulation that fits
+    let params = lennard_jones_params(Algorithm::BruteForce);
+    assert!(Simulation::try_new(params, &device).is_ok());
+}
+
+#[test]
+fn algorithms_round_trip_through_their_names() {
+    for algorithm in Algorithm::ALL {
+        assert_eq!(algorithm.to_string().parse::<Algorithm>(), Ok(algorithm));
+    }
+    assert!("octree".parse::<Algorithm>().is_err());
+}
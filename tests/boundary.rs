@@ -0,0 +1,67 @@
+use eden::{BoundaryMode, Params, Particle, Simulation};
+
+mod common;
+
+/// one particle heading out through the left wall at x = 0
+fn run(boundary: BoundaryMode, device: &wgpu::Device, queue: &wgpu::Queue) -> Particle {
+    let mut params = Params::with_seed(1);
+    params.num_particles = 1;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.friction_coeff = 1.0;
+    params.boundary = boundary;
+
+    let mut particle = params.spawn_particles()[0];
+    particle.pos = [0.05, 10.0];
+    particle.vel = [-100.0, 0.0];
+
+    let mut sim = Simulation::try_from_particles(params, &[particle], device).unwrap();
+    sim.step(device, queue, 1);
+    sim.read_particles(device, queue)[0]
+}
+
+#[test]
+fn each_mode_handles_the_edge() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let reflected = run(BoundaryMode::Reflect, &device, &queue);
+    assert!(reflected.pos[0] > 0.0 && reflected.pos[0] < 0.001);
+    assert_eq!(reflected.vel, [100.0, 0.0]);
+
+    let wrapped = run(BoundaryMode::Periodic, &device, &queue);
+    assert!((wrapped.pos[0] - 19.95).abs() < 1e-3, "{:?}", wrapped.pos);
+    assert_eq!(wrapped.vel, [-100.0, 0.0]);
+
+    let absorbed = run(BoundaryMode::Absorb, &device, &queue);
+    assert!(absorbed.pos[0] > 0.0 && absorbed.pos[0] < 0.001);
+    assert_eq!(absorbed.vel, [0.0, 0.0]);
+
+    let open = run(BoundaryMode::Open, &device, &queue);
+    assert!((open.pos[0] + 0.05).abs() < 1e-3, "{:?}", open.pos);
+}
+
+#[test]
+fn periodic_neighbours_interact_across_the_edge() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    // two particles 0.2 apart through the left / right edge, close enough to repel
+    let mut params = Params::with_seed(1);
+    params.num_particles = 2;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.boundary = BoundaryMode::Periodic;
+    let mut particles = params.spawn_particles();
+    particles[0].pos = [0.1, 10.0];
+    particles[1].pos = [19.9, 10.0];
+
+    let mut sim = Simulation::try_from_particles(params, &particles, &device).unwrap();
+    sim.step(&device, &queue, 1);
+    let after = sim.read_particles(&device, &queue);
+
+    assert!(after[0].vel[0] > 0.0, "{:?}", after[0].vel);
+    assert!(after[1].vel[0] < 0.0, "{:?}", after[1].vel);
+}
@@ -0,0 +1,13 @@
+//! Setup shared by the integration tests.
+
+use eden::simulation::request_headless_device;
+
+/// a headless device and queue, or `None` once it has said the test is skipped
+/// because there is no adapter that can run compute shaders
+pub fn gpu() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let gpu = pollster::block_on(request_headless_device());
+    if gpu.is_none() {
+        eprintln!("no compute-capable adapter available, skipping");
+    }
+    gpu.map(|(_adapter, device, queue)| (device, queue))
+}
@@ -0,0 +1,55 @@
+use eden::{Params, Simulation};
+
+mod common;
+
+fn seeded(seed: u64) -> Params {
+    let mut params = Params::with_seed(seed);
+    params.num_types = 4;
+    params.randomize_matrix();
+    params.num_particles = 512;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params
+}
+
+#[test]
+fn same_seed_gives_same_initial_conditions() {
+    let (a, b) = (seeded(1234), seeded(1234));
+    assert_eq!(a.attraction_matrix, b.attraction_matrix);
+
+    let (pa, pb) = (a.spawn_particles(), b.spawn_particles());
+    for (x, y) in pa.iter().zip(&pb) {
+        assert_eq!(x.pos, y.pos);
+        assert_eq!(x.kind, y.kind);
+    }
+
+    let c = seeded(1235);
+    assert_ne!(a.attraction_matrix, c.attraction_matrix);
+    assert_ne!(pa[0].pos, c.spawn_particles()[0].pos);
+}
+
+#[test]
+fn deterministic_mode_replays_bit_for_bit() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = seeded(99);
+    params.deterministic = true;
+
+    let run = || {
+        let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+        sim.step(&device, &queue, 50);
+        sim.read_particles(&device, &queue)
+    };
+    let (first, second) = (run(), run());
+
+    let as_bits = |particles: &[eden::Particle]| -> Vec<u32> {
+        particles
+            .iter()
+            .flat_map(|p| [p.pos[0], p.pos[1], p.vel[0], p.vel[1]])
+            .map(f32::to_bits)
+            .collect()
+    };
+    assert_eq!(as_bits(&first), as_bits(&second));
+}
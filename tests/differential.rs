@@ -0,0 +1,131 @@
+use eden::{
+    reference::{compare_with_gpu, ForceModel},
+    BoundaryMode, Integrator, Params,
+};
+
+mod common;
+
+fn test_params() -> Params {
+    let mut params = Params::new();
+    params.num_types = 3;
+    params.randomize_matrix();
+    params.num_particles = 256;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params
+}
+
+fn check(model: ForceModel, steps: u32, tolerance: f32) {
+    check_with(test_params(), model, steps, tolerance);
+}
+
+fn check_with(params: Params, model: ForceModel, steps: u32, tolerance: f32) {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let report = compare_with_gpu(&params, model, 7, steps, &device, &queue);
+    println!("{}", report);
+    assert!(
+        report.max_position() <= tolerance,
+        "GPU diverged from the CPU reference at step {:?}\n{}",
+        report.first_step_above(tolerance),
+        report
+    );
+}
+
+#[test]
+fn lennard_jones_matches_cpu_reference() {
+    check(ForceModel::LennardJones, 20, 1e-4);
+}
+
+#[test]
+fn particle_life_matches_cpu_reference() {
+    check(ForceModel::ParticleLife, 20, 1e-3);
+}
+
+#[test]
+fn periodic_particle_life_matches_cpu_reference() {
+    let mut params = test_params();
+    params.boundary = BoundaryMode::Periodic;
+    check_with(params, ForceModel::ParticleLife, 20, 1e-3);
+}
+
+#[test]
+fn absorbing_lennard_jones_matches_cpu_reference() {
+    let mut params = test_params();
+    params.boundary = BoundaryMode::Absorb;
+    check_with(params, ForceModel::LennardJones, 20, 1e-4);
+}
+
+#[test]
+fn barnes_hut_with_zero_theta_matches_direct_sum() {
+    let mut params = test_params();
+    params.theta = 0.0;
+    check_with(params, ForceModel::Gravity, 20, 1e-4);
+}
+
+#[test]
+fn barnes_hut_stays_close_to_direct_sum() {
+    let mut params = test_params();
+    params.boundary = BoundaryMode::Periodic;
+    check_with(params, ForceModel::Gravity, 20, 2e-4);
+}
+
+#[test]
+fn particle_mesh_matches_cpu_reference() {
+    let mut params = test_params();
+    params.num_grids_side = 16;
+    check_with(params, ForceModel::ParticleMesh, 20, 1e-4);
+}
+
+#[test]
+fn periodic_particle_mesh_matches_cpu_reference() {
+    let mut params = test_params();
+    params.num_grids_side = 16;
+    params.boundary = BoundaryMode::Periodic;
+    check_with(params, ForceModel::ParticleMesh, 20, 1e-4);
+}
+
+fn check_integrator(integrator: Integrator, model: ForceModel, tolerance: f32) {
+    let mut params = test_params();
+    params.integrator = integrator;
+    check_with(params, model, 20, tolerance);
+}
+
+#[test]
+fn euler_matches_cpu_reference() {
+    check_integrator(Integrator::Euler, ForceModel::LennardJones, 1e-4);
+}
+
+#[test]
+fn velocity_verlet_matches_cpu_reference() {
+    check_integrator(Integrator::VelocityVerlet, ForceModel::LennardJones, 1e-4);
+}
+
+#[test]
+fn leapfrog_matches_cpu_reference() {
+    check_integrator(Integrator::Leapfrog, ForceModel::ParticleLife, 1e-3);
+}
+
+#[test]
+fn rk4_matches_cpu_reference() {
+    check_integrator(Integrator::Rk4, ForceModel::LennardJones, 1e-4);
+}
+
+#[test]
+fn periodic_rk4_barnes_hut_matches_direct_sum() {
+    let mut params = test_params();
+    params.theta = 0.0;
+    params.boundary = BoundaryMode::Periodic;
+    params.integrator = Integrator::Rk4;
+    check_with(params, ForceModel::Gravity, 20, 1e-4);
+}
+
+#[test]
+fn adaptive_timestep_matches_cpu_reference() {
+    let mut params = test_params();
+    params.adaptive_dt = true;
+    params.integrator = Integrator::VelocityVerlet;
+    check_with(params, ForceModel::LennardJones, 20, 1e-4);
+}
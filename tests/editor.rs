@@ -0,0 +1,122 @@
+use eden::{
+    editor::{self, TokenKind},
+    Algorithm, BoundaryMode, Integrator, Params, Simulation,
+};
+
+mod common;
+
+/// a unit spring between every pair closer than one cell
+const SPRING: &str = "
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return r / particle.mass;
+}
+";
+
+fn params_with(source: &str) -> Params {
+    let mut params = Params::with_seed(1);
+    params.shader_name = "spring.wgsl".to_string();
+    params.shader_buffer = source.to_string();
+    params
+}
+
+#[test]
+fn tokens_cover_the_source() {
+    let source = "@compute // note\nfn f() -> vec2<f32> { return vec2(1.5e-3, 0x1fu); }";
+    let tokens = editor::tokenize(source);
+    let mut end = 0;
+    for (_, range) in &tokens {
+        assert_eq!(range.start, end);
+        end = range.end;
+    }
+    assert_eq!(end, source.len());
+
+    let kind_of = |text: &str| {
+        tokens
+            .iter()
+            .find(|(_, range)| &source[range.clone()] == text)
+            .map(|(kind, _)| *kind)
+    };
+    assert_eq!(kind_of("@compute"), Some(TokenKind::Attribute));
+    assert_eq!(kind_of("// note"), Some(TokenKind::Comment));
+    assert_eq!(kind_of("fn"), Some(TokenKind::Keyword));
+    assert_eq!(kind_of("vec2"), Some(TokenKind::Type));
+    assert_eq!(kind_of("1.5e-3"), Some(TokenKind::Number));
+    assert_eq!(kind_of("0x1fu"), Some(TokenKind::Number));
+    assert_eq!(kind_of("f"), Some(TokenKind::Identifier));
+}
+
+#[test]
+fn diagnostics_point_into_the_edited_source() {
+    assert_eq!(editor::diagnose(&params_with(SPRING)), []);
+
+    let typo = SPRING.replace("particle.mass", "particle.mas");
+    let diagnostics = editor::diagnose(&params_with(&typo));
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].line, Some(3));
+    let span = diagnostics[0].span.clone().unwrap();
+    assert!(typo[span].contains("mas"));
+
+    // a validation error rather than a parse error
+    let mistyped = SPRING.replace("return r / particle.mass;", "return particle.mass;");
+    let diagnostics = editor::diagnose(&params_with(&mistyped));
+    assert!(!diagnostics.is_empty());
+    assert!(
+        diagnostics.iter().all(|d| d.line.is_some()),
+        "{:?}",
+        diagnostics
+    );
+
+    let annotated = format!("{}// @param k 1 0 0\n", SPRING);
+    let diagnostics = editor::diagnose(&params_with(&annotated));
+    assert_eq!(diagnostics[0].line, Some(5));
+
+    // binding mismatches have nowhere in the source to point
+    let mut params = params_with(include_str!("../src/shaders/default.wgsl"));
+    params.algorithm = Algorithm::UniformGrid;
+    let diagnostics = editor::diagnose(&params);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].span.is_none());
+    assert!(diagnostics[0].message.contains("24-byte"));
+}
+
+#[test]
+fn applying_rebuilds_the_force_pass_in_place() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = params_with(SPRING);
+    params.num_particles = 2;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.friction_coeff = 1.0;
+    params.boundary = BoundaryMode::Open;
+    params.integrator = Integrator::Euler;
+    params.dt = 0.01;
+    let mut particles = params.spawn_particles();
+    particles[0].pos = [10.0, 10.0];
+    particles[1].pos = [11.0, 10.0];
+    for particle in &mut particles {
+        particle.vel = [0.0, 0.0];
+        particle.mass = 1.0;
+    }
+    let mut sim = Simulation::try_from_particles(params, &particles, &device).unwrap();
+
+    // the new shader brings a uniform the old layout did not have
+    let stiffer = SPRING
+        .replace("return r", "return shader_params.k * r")
+        .replace("fn force", "// @param k 0 10 3\nfn force");
+    sim.try_set_shader(stiffer, &device).unwrap();
+    sim.step(&device, &queue, 1);
+
+    // a = k * r / m, so after one Euler step v = dt * k
+    let vel = sim.read_particles(&device, &queue)[0].vel[0];
+    assert!((vel - 0.03).abs() < 1e-6, "{}", vel);
+
+    // the solvers have no shader to swap
+    let mut params = params_with(SPRING);
+    params.algorithm = Algorithm::BarnesHut;
+    params.num_grids_side = 16;
+    let mut solver = Simulation::try_new(params, &device).unwrap();
+    assert!(solver.try_set_shader(SPRING.to_string(), &device).is_err());
+}
@@ -0,0 +1,86 @@
+use eden::{force, BoundaryMode, Integrator, Params, Simulation};
+
+mod common;
+
+/// a unit spring between every pair closer than one cell
+const SPRING: &str = "
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return r / particle.mass;
+}
+";
+
+#[test]
+fn modules_are_spliced_into_the_core() {
+    let radii = |shader: &str| shader.matches("fn interaction_radius").count();
+    let shader = force::compose(SPRING);
+    assert!(shader.starts_with(SPRING) && shader.contains(force::CORE_SHADER));
+    assert_eq!(radii(&shader), radii(force::CORE_SHADER) + 1);
+
+    // a module that picks its own radius does not get the default one as well
+    let lennard_jones = force::compose(include_str!("../src/shaders/lennardjones.wgsl"));
+    assert_eq!(radii(&lennard_jones), radii(force::CORE_SHADER) + 1);
+
+    // complete shaders with their own entry point are left alone
+    let barnes_hut = include_str!("../src/shaders/barneshut.wgsl");
+    assert!(!force::is_module(barnes_hut));
+    assert_eq!(force::compose(barnes_hut), barnes_hut);
+}
+
+#[test]
+fn a_custom_module_drives_the_particles() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = Params::with_seed(1);
+    params.num_particles = 2;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.friction_coeff = 1.0;
+    params.boundary = BoundaryMode::Open;
+    params.integrator = Integrator::Euler;
+    params.dt = 0.01;
+    params.shader_buffer = SPRING.to_string();
+
+    let mut particles = params.spawn_particles();
+    particles[0].pos = [10.0, 10.0];
+    particles[1].pos = [11.0, 10.5];
+    for particle in &mut particles {
+        particle.vel = [0.0, 0.0];
+        particle.mass = 2.0;
+    }
+
+    let mut sim = Simulation::try_from_particles(params, &particles, &device).unwrap();
+    sim.step(&device, &queue, 1);
+    let moved = sim.read_particles(&device, &queue);
+
+    // a = r / m, so after one Euler step v = dt * r / m
+    let expected = [0.01 * 1.0 / 2.0, 0.01 * 0.5 / 2.0];
+    for (particle, sign) in moved.iter().zip([1.0, -1.0]) {
+        for (vel, expected) in particle.vel.iter().zip(expected) {
+            assert!((vel - sign * expected).abs() < 1e-6, "{:?}", particle.vel);
+        }
+    }
+}
+
+#[test]
+fn every_shipped_module_builds() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    for module in [
+        include_str!("../src/shaders/experimental.wgsl"),
+        include_str!("../src/shaders/expernew.wgsl"),
+        include_str!("../src/shaders/buggy.wgsl"),
+        include_str!("../src/shaders/lennardjones.wgsl"),
+    ] {
+        let mut params = Params::with_seed(3);
+        params.num_particles = 64;
+        params.shader_buffer = module.to_string();
+        let mut sim = Simulation::try_new(params, &device).unwrap();
+        sim.step(&device, &queue, 2);
+        let particles = sim.read_particles(&device, &queue);
+        assert!(particles.iter().all(|p| p.pos[0].is_finite()));
+    }
+}
@@ -0,0 +1,126 @@
+use eden::{simulation::read_buffer, Params, Particle, Simulation};
+
+mod common;
+
+#[test]
+fn simulation_steps_without_a_surface() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let params = Params::new();
+    let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+    sim.step(&device, &queue, 10);
+
+    let particles = sim.read_particles(&device, &queue);
+    assert_eq!(sim.step_count, 10);
+    assert_eq!(particles.len(), params.num_particles as usize);
+
+    // the clock also comes back without blocking, once the device is polled
+    let (sender, receiver) = std::sync::mpsc::channel();
+    sim.request_clock(&device, &queue, move |clock| sender.send(clock).unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let clock = receiver.try_recv().unwrap();
+    assert_eq!(clock, sim.read_clock(&device, &queue));
+    assert!(
+        (clock.time - 10.0 * params.dt).abs() < 1e-5,
+        "{}",
+        clock.time
+    );
+}
+
+#[test]
+fn cell_list_sorts_every_particle_into_its_cell() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    // 1600 cells, so the prefix sum spans several scan blocks
+    let mut params = Params::with_seed(3);
+    params.num_particles = 2000;
+    params.world_size = 40.0;
+    params.num_grids_side = 40;
+    params.sort_every = 0;
+    let particles = params.spawn_particles();
+
+    let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+    sim.step(&device, &queue, 1);
+
+    let counts: Vec<u32> = read_buffer(&device, &queue, &sim.cell_counts_buffer);
+    let starts: Vec<u32> = read_buffer(&device, &queue, &sim.cell_starts_buffer);
+    let sorted: Vec<u32> = read_buffer(&device, &queue, &sim.sorted_indices_buffer);
+
+    assert_eq!(counts.iter().sum::<u32>(), params.num_particles);
+    let mut seen = vec![false; sorted.len()];
+    let mut start = 0;
+    for (cell, (&count, &cell_start)) in counts.iter().zip(&starts).enumerate() {
+        assert_eq!(
+            cell_start, start,
+            "cell {} starts at the wrong offset",
+            cell
+        );
+        start += count;
+
+        for &index in &sorted[cell_start as usize..(cell_start + count) as usize] {
+            let pos = particles[index as usize].pos;
+            let side = params.num_grids_side as usize;
+            let grid_size_side = params.world_size / params.num_grids_side as f32;
+            let x = ((pos[0] / grid_size_side).floor() as usize).min(side - 1);
+            let y = ((pos[1] / grid_size_side).floor() as usize).min(side - 1);
+            assert_eq!(
+                y * side + x,
+                cell,
+                "particle {} is in the wrong cell",
+                index
+            );
+            assert!(!seen[index as usize], "particle {} sorted twice", index);
+            seen[index as usize] = true;
+        }
+    }
+}
+
+fn morton_code(params: &Params, particle: &Particle) -> u32 {
+    let grid_size_side = params.world_size / params.num_grids_side as f32;
+    let cell = |x: f32| ((x / grid_size_side).floor() as u32).min(params.num_grids_side - 1);
+    let (x, y) = (cell(particle.pos[0]), cell(particle.pos[1]));
+    (0..16).fold(0, |code, bit| {
+        code | ((x >> bit) & 1) << (2 * bit) | ((y >> bit) & 1) << (2 * bit + 1)
+    })
+}
+
+#[test]
+fn morton_sort_reorders_buffers_and_keeps_ids() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    // 100 cells per side needs 14 key bits, an odd number of 4 bit radix passes
+    let mut params = Params::with_seed(5);
+    params.num_particles = 3000;
+    params.world_size = 100.0;
+    params.num_grids_side = 100;
+    let spawned = params.spawn_particles();
+
+    let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    sim.encode_sort(&mut encoder);
+    queue.submit(Some(encoder.finish()));
+
+    let sorted: Vec<Particle> = read_buffer(&device, &queue, sim.output_buffer());
+    let codes: Vec<u32> = sorted.iter().map(|p| morton_code(&params, p)).collect();
+    assert!(
+        codes.windows(2).all(|pair| pair[0] <= pair[1]),
+        "particles are not in Morton order"
+    );
+
+    let mut ids: Vec<u32> = sorted.iter().map(|p| p.id).collect();
+    ids.sort_unstable();
+    assert!(ids.iter().copied().eq(0..params.num_particles));
+
+    for (a, b) in sim.read_particles(&device, &queue).iter().zip(&spawned) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.pos, b.pos);
+        assert_eq!(a.kind, b.kind);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use eden::{watch::ShaderWatcher, BoundaryMode, Params, Simulation};
+
+mod common;
+
+/// a unit spring between every pair closer than one cell
+const SPRING: &str = "
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return r / particle.mass;
+}
+";
+
+/// the spring with a typo on its third line
+const TYPO: &str = "
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return r / particle.mas;
+}
+";
+
+#[test]
+fn the_watcher_reports_saved_shaders() {
+    let dir = std::env::temp_dir().join("eden-test-shader-watch");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("old.wgsl"), SPRING).unwrap();
+
+    // what is there already counts as seen
+    let mut watcher = ShaderWatcher::new(&dir);
+    assert!(watcher.poll().is_empty());
+
+    fs::write(dir.join("new.wgsl"), SPRING).unwrap();
+    fs::write(dir.join("notes.txt"), "not a shader").unwrap();
+    assert_eq!(watcher.poll(), [dir.join("new.wgsl")]);
+    assert!(watcher.poll().is_empty());
+
+    // saving bumps the modification time, which is all the watcher looks at
+    let old = fs::File::options()
+        .write(true)
+        .open(dir.join("old.wgsl"))
+        .unwrap();
+    old.set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(watcher.poll(), [dir.join("old.wgsl")]);
+
+    // a folder that is not there has nothing to report
+    assert!(ShaderWatcher::new(dir.join("missing")).poll().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn module_errors_point_at_the_module_lines() {
+    let mut params = Params::with_seed(1);
+    params.shader_buffer = TYPO.to_string();
+    params.shader_name = "spring.wgsl".to_string();
+    let err = Simulation::check_shader(&params).unwrap_err();
+    assert!(err.contains("spring.wgsl:3:"), "{}", err);
+}
+
+#[test]
+fn a_failed_reload_keeps_the_running_shader() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = Params::with_seed(4);
+    params.num_particles = 64;
+    params.boundary = BoundaryMode::Periodic;
+    params.shader_name = "spring.wgsl".to_string();
+    params.shader_buffer = SPRING.to_string();
+    let mut sim = Simulation::try_new(params, &device).unwrap();
+    sim.step(&device, &queue, 5);
+
+    let before = sim.read_particles(&device, &queue);
+    let clock = sim.read_clock(&device, &queue);
+    let step_count = sim.step_count;
+
+    assert!(sim.try_set_shader(TYPO.to_string(), &device).is_err());
+    assert_eq!(sim.params.shader_buffer, SPRING);
+
+    let edited = SPRING.replace("r / particle.mass", "-r / particle.mass");
+    sim.try_set_shader(edited.clone(), &device).unwrap();
+    assert_eq!(sim.params.shader_buffer, edited);
+    assert_eq!(sim.step_count, step_count);
+    assert_eq!(sim.read_clock(&device, &queue).time, clock.time);
+
+    let after = sim.read_particles(&device, &queue);
+    for (a, b) in before.iter().zip(&after) {
+        assert_eq!((a.id, a.pos, a.vel), (b.id, b.pos, b.vel));
+    }
+}
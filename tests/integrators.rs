@@ -0,0 +1,131 @@
+use eden::{
+    reference::{CpuSimulation, ForceModel},
+    BoundaryMode, Integrator, Params, Particle,
+};
+
+const SOFTENING: f32 = 0.1;
+
+fn body(pos: [f32; 2], vel: [f32; 2]) -> Particle {
+    let mut particle = Particle::new();
+    particle.pos = pos;
+    particle.vel = vel;
+    particle.mass = 1.0;
+    particle
+}
+
+/// two equal bodies on an eccentric orbit around the middle of an open world
+fn binary(integrator: Integrator) -> CpuSimulation {
+    let mut params = Params::with_seed(5);
+    params.world_size = 50.0;
+    params.num_grids_side = 8;
+    params.boundary = BoundaryMode::Open;
+    params.friction_coeff = 1.0;
+    params.particle_radius = SOFTENING;
+    params.dt = 0.02;
+    params.integrator = integrator;
+
+    // 0.7 of the circular speed at a separation of 2
+    let r2: f32 = 4.0 + SOFTENING * SOFTENING;
+    let speed = 0.7 * (2.0 / (r2 * r2.sqrt())).sqrt();
+    let bodies = vec![
+        body([24.0, 25.0], [0.0, -speed]),
+        body([26.0, 25.0], [0.0, speed]),
+    ];
+    CpuSimulation::new(params, bodies, ForceModel::Gravity)
+}
+
+/// kinetic plus softened potential energy, in f64
+fn energy(sim: &CpuSimulation) -> f64 {
+    let [a, b] = [sim.particles[0], sim.particles[1]];
+    let kinetic: f64 = sim
+        .particles
+        .iter()
+        .map(|p| 0.5 * p.mass as f64 * ((p.vel[0] as f64).powi(2) + (p.vel[1] as f64).powi(2)))
+        .sum();
+    let r2 = ((a.pos[0] - b.pos[0]) as f64).powi(2)
+        + ((a.pos[1] - b.pos[1]) as f64).powi(2)
+        + (SOFTENING as f64).powi(2);
+    kinetic - sim.params.gravity as f64 * a.mass as f64 * b.mass as f64 / r2.sqrt()
+}
+
+/// largest relative energy error over `steps` steps
+fn energy_error(integrator: Integrator, steps: u32) -> f64 {
+    let mut sim = binary(integrator);
+    let initial = energy(&sim);
+    let mut worst: f64 = 0.0;
+    for _ in 0..steps {
+        sim.step(1);
+        worst = worst.max(((energy(&sim) - initial) / initial).abs());
+    }
+    worst
+}
+
+#[test]
+fn symplectic_integrators_keep_the_orbit_energy() {
+    let trapezoidal = energy_error(Integrator::Trapezoidal, 2000);
+    for integrator in [Integrator::VelocityVerlet, Integrator::Leapfrog] {
+        let error = energy_error(integrator, 2000);
+        println!(
+            "{}: {:e}, trapezoidal: {:e}",
+            integrator, error, trapezoidal
+        );
+        assert!(error < 1e-2, "{} energy error {}", integrator, error);
+        assert!(
+            error * 10.0 < trapezoidal,
+            "{} energy error {} is not well below trapezoidal {}",
+            integrator,
+            error,
+            trapezoidal
+        );
+    }
+}
+
+#[test]
+fn rk4_is_the_most_accurate_over_a_short_run() {
+    let rk4 = energy_error(Integrator::Rk4, 200);
+    for integrator in [
+        Integrator::Euler,
+        Integrator::Trapezoidal,
+        Integrator::VelocityVerlet,
+        Integrator::Leapfrog,
+    ] {
+        let error = energy_error(integrator, 200);
+        println!("{}: {:e}, rk4: {:e}", integrator, error, rk4);
+        assert!(rk4 < error, "rk4 {} vs {} {}", rk4, integrator, error);
+    }
+}
+
+#[test]
+fn integrators_round_trip_through_their_names() {
+    for integrator in Integrator::ALL {
+        assert_eq!(integrator.to_string().parse::<Integrator>(), Ok(integrator));
+    }
+    assert!("midpoint".parse::<Integrator>().is_err());
+}
+
+#[test]
+fn adaptive_dt_shrinks_at_pericentre_and_stays_in_bounds() {
+    let mut sim = binary(Integrator::VelocityVerlet);
+    sim.params.adaptive_dt = true;
+    sim.params.dt_min = 1e-4;
+    sim.params.dt_max = 0.05;
+
+    let mut dts = Vec::new();
+    for _ in 0..400 {
+        sim.step(1);
+        dts.push(sim.dt);
+    }
+    let smallest = dts.iter().copied().fold(f32::INFINITY, f32::min);
+    let largest = dts.iter().copied().fold(0.0, f32::max);
+    println!("dt between {} and {}", smallest, largest);
+    assert!(smallest >= sim.params.dt_min && largest <= sim.params.dt_max);
+    assert!(
+        smallest < 0.5 * largest,
+        "dt never adapted: {} to {}",
+        smallest,
+        largest
+    );
+
+    let simulated: f64 = dts.iter().map(|&dt| dt as f64).sum();
+    assert!((sim.time - simulated).abs() < 1e-9);
+}
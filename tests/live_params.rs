@@ -0,0 +1,156 @@
+use eden::{reference::ForceModel, Algorithm, BoundaryMode, Integrator, Params, Simulation};
+
+mod common;
+
+/// a spring between every pair closer than one cell, `k` times stiffer
+const SPRING: &str = "
+// @param k 0 10 1
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return shader_params.k * r / particle.mass;
+}
+";
+
+#[test]
+fn only_layout_changes_need_a_restart() {
+    let params = Params::with_seed(1);
+
+    let mut live = params.clone();
+    live.dt *= 2.0;
+    live.friction_coeff = 0.5;
+    live.attraction_matrix[0] = -1.0;
+    live.shader_values.insert("search_grids".to_string(), 4.0);
+    live.seed = 2;
+    live.theta = 0.2;
+    live.gravity = 3.0;
+    live.particle_radius = 0.5;
+    assert!(params.structural_changes(&live).is_empty());
+
+    let mut restart = params.clone();
+    restart.num_particles += 1;
+    restart.algorithm = Algorithm::BruteForce;
+    restart.shader_buffer.push('\n');
+    assert_eq!(
+        params.structural_changes(&restart),
+        ["num_particles", "shader", "algorithm"]
+    );
+
+    let mut types = params.clone();
+    types.num_types += 1;
+    types.randomize_matrix();
+    assert_eq!(
+        params.structural_changes(&types),
+        ["num_types", "attraction_matrix"]
+    );
+}
+
+#[test]
+fn updates_carry_on_the_running_simulation() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = Params::with_seed(1);
+    params.num_particles = 2;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.friction_coeff = 1.0;
+    params.boundary = BoundaryMode::Open;
+    params.integrator = Integrator::Euler;
+    params.dt = 0.01;
+    params.shader_name = "spring.wgsl".to_string();
+    params.shader_buffer = SPRING.to_string();
+    let mut particles = params.spawn_particles();
+    particles[0].pos = [10.0, 10.0];
+    particles[1].pos = [11.0, 10.0];
+    for particle in &mut particles {
+        particle.vel = [0.0, 0.0];
+        particle.mass = 1.0;
+    }
+    let mut sim = Simulation::try_from_particles(params.clone(), &particles, &device).unwrap();
+
+    let mut edited = params.clone();
+    edited.dt = 0.02;
+    edited.shader_values.insert("k".to_string(), 3.0);
+    edited.num_particles = 100;
+    assert_eq!(sim.update_params(&edited, &queue), ["num_particles"]);
+    assert_eq!(sim.params.num_particles, 2);
+    assert_eq!(sim.params.dt, 0.02);
+
+    // a = k * r / m, so after one Euler step v = dt * k
+    sim.step(&device, &queue, 1);
+    let vel = sim.read_particles(&device, &queue)[0].vel[0];
+    assert!((vel - 0.06).abs() < 1e-6, "{}", vel);
+    assert_eq!(sim.step_count, 1);
+}
+
+#[test]
+fn solver_constants_are_written_to_the_running_solver() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    for model in [ForceModel::Gravity, ForceModel::ParticleMesh] {
+        let mut params = Params::with_seed(3);
+        params.num_particles = 256;
+        params.world_size = 20.0;
+        params.num_grids_side = 16;
+        params.shader_buffer = model.shader_source().to_string();
+        params.algorithm = model.algorithm();
+        let particles = params.spawn_particles();
+
+        let mut edited = params.clone();
+        edited.theta = 0.2;
+        edited.gravity = 4.0;
+        edited.particle_radius = 0.5;
+
+        let mut live = Simulation::try_from_particles(params, &particles, &device).unwrap();
+        assert!(live.update_params(&edited, &queue).is_empty());
+        let mut rebuilt = Simulation::try_from_particles(edited, &particles, &device).unwrap();
+
+        live.step(&device, &queue, 20);
+        rebuilt.step(&device, &queue, 20);
+        let live = live.read_particles(&device, &queue);
+        let rebuilt = rebuilt.read_particles(&device, &queue);
+        for (a, b) in live.iter().zip(&rebuilt) {
+            let d = ((a.pos[0] - b.pos[0]).powi(2) + (a.pos[1] - b.pos[1]).powi(2)).sqrt();
+            assert!(d < 1e-5, "{:?}: particle {} is {} apart", model, a.id, d);
+        }
+    }
+}
+
+#[test]
+fn resizing_the_particle_mesh_needs_a_restart() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = Params::with_seed(3);
+    params.num_particles = 256;
+    params.world_size = 20.0;
+    params.num_grids_side = 16;
+    params.shader_buffer = ForceModel::ParticleMesh.shader_source().to_string();
+    params.algorithm = Algorithm::ParticleMesh;
+
+    // a periodic mesh is the grid, any other is padded to twice its side
+    for (from, to) in [
+        (BoundaryMode::Periodic, BoundaryMode::Reflect),
+        (BoundaryMode::Reflect, BoundaryMode::Periodic),
+    ] {
+        params.boundary = from;
+        let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+        let mut edited = params.clone();
+        edited.boundary = to;
+        assert_eq!(sim.update_params(&edited, &queue), ["boundary"]);
+        assert_eq!(sim.params.boundary, from);
+        sim.step(&device, &queue, 1);
+    }
+
+    // the same mesh size carries on
+    params.boundary = BoundaryMode::Reflect;
+    let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+    let mut edited = params.clone();
+    edited.boundary = BoundaryMode::Absorb;
+    assert!(sim.update_params(&edited, &queue).is_empty());
+    assert_eq!(sim.params.boundary, BoundaryMode::Absorb);
+    sim.step(&device, &queue, 1);
+}
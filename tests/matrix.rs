@@ -0,0 +1,91 @@
+use eden::{
+    matrix::{self, MatrixGenerator},
+    Params,
+};
+
+#[test]
+fn generators_have_their_shapes() {
+    let mut params = Params::with_seed(3);
+    params.num_types = 9;
+    let n = params.num_types as usize;
+
+    let generated = |generator: MatrixGenerator| {
+        let mut params = params.clone();
+        params.generate_matrix(generator);
+        assert_eq!(params.attraction_matrix.len(), n * n * 4);
+        params.matrix_rows()
+    };
+
+    for generator in MatrixGenerator::ALL {
+        let rows = generated(generator);
+        assert!(
+            rows.iter()
+                .flatten()
+                .all(|value| (-1.0..=1.0).contains(value)),
+            "{}",
+            generator
+        );
+        // drawn from the seed, so the same every time
+        assert_eq!(rows, generated(generator), "{}", generator);
+    }
+
+    let mut random = params.clone();
+    random.randomize_matrix();
+    assert_eq!(generated(MatrixGenerator::Random), random.matrix_rows());
+
+    let symmetric = generated(MatrixGenerator::Symmetric);
+    assert_eq!(matrix::transpose(&symmetric), symmetric);
+
+    let antisymmetric = generated(MatrixGenerator::Antisymmetric);
+    assert_eq!(
+        matrix::transpose(&antisymmetric),
+        matrix::negate(&antisymmetric)
+    );
+
+    let snakes = generated(MatrixGenerator::Snakes);
+    for (i, row) in snakes.iter().enumerate() {
+        assert_eq!(row[i], 1.0);
+        assert!(row[(i + 1) % n] > 0.0);
+        assert_eq!(row.iter().filter(|&&value| value != 0.0).count(), 2);
+    }
+
+    // 9 types make three blocks of three
+    let blocks = generated(MatrixGenerator::BlockDiagonal);
+    assert_eq!(blocks[0][3], 0.0);
+    assert_eq!(blocks[8][5], 0.0);
+    assert!(blocks[4][5] > 0.0);
+
+    let sparse = generated(MatrixGenerator::Sparse);
+    let zeros = sparse
+        .iter()
+        .flatten()
+        .filter(|&&value| value == 0.0)
+        .count();
+    assert!(zeros > n * n / 2, "{} of {}", zeros, n * n);
+}
+
+#[test]
+fn matrices_survive_a_copy_and_paste() {
+    let mut params = Params::with_seed(5);
+    params.num_types = 4;
+    params.generate_matrix(MatrixGenerator::Gaussian);
+    let rows = params.matrix_rows();
+
+    let text = matrix::to_text(&rows);
+    assert_eq!(text.lines().count(), 4);
+    assert_eq!(matrix::parse(&text).unwrap(), rows);
+
+    // a TOML or Python list of rows pastes too
+    let listed = matrix::parse("[[0.5, -1],\n [0, 1e-2]]").unwrap();
+    assert_eq!(listed, [[0.5, -1.0], [0.0, 0.01]]);
+
+    for (text, expected) in [
+        ("", "no matrix entries"),
+        ("1 2\n3", "row 2 has 1 entries"),
+        ("1 x\n3 4", "line 1: `x` is not a finite number"),
+        ("1 inf\n3 4", "`inf` is not a finite number"),
+    ] {
+        let err = matrix::parse(text).unwrap_err();
+        assert!(err.contains(expected), "{:?}: {}", text, err);
+    }
+}
@@ -0,0 +1,89 @@
+use eden::{
+    palette::{self, Palette, MAX_TYPE_COLOURS},
+    reflect::reflect,
+    scenario::Scenario,
+    Params,
+};
+
+#[test]
+fn palettes_colour_any_number_of_types() {
+    for palette in Palette::ALL {
+        for num_types in [1, 3, 12, 100] {
+            let colours = palette.colours(num_types);
+            assert_eq!(colours.len(), num_types);
+            assert!(
+                colours.iter().flatten().all(|c| (0.0..=1.0).contains(c)),
+                "{} with {} types",
+                palette,
+                num_types
+            );
+        }
+    }
+
+    // the qualitative palettes keep their first colours apart
+    for palette in [Palette::Tableau, Palette::OkabeIto, Palette::TolBright] {
+        let colours = palette.colours(7);
+        for (i, a) in colours.iter().enumerate() {
+            assert!(!colours[i + 1..].contains(a), "{}", palette);
+        }
+    }
+    assert!(Palette::OkabeIto.colour_blind_safe());
+    assert!(!Palette::Classic.colour_blind_safe());
+
+    // viridis runs from its dark end to its light end
+    let viridis = Palette::Viridis.colours(5);
+    assert_eq!(viridis[0], Palette::Viridis.colour(0, 2));
+    assert!(viridis[4][0] > 0.9 && viridis[4][1] > 0.85);
+
+    for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+        let back = palette::srgb_to_linear(palette::linear_to_srgb(value));
+        assert!((back - value).abs() < 1e-5, "{}", value);
+    }
+}
+
+#[test]
+fn the_colour_table_matches_the_draw_shader() {
+    let mut params = Params::with_seed(1);
+    params.num_types = 3;
+    params.type_colours = vec![[1.0, 0.0, 0.0]];
+
+    // the first type as set, the rest as eden always drew them
+    assert_eq!(params.type_colour(0), [1.0, 0.0, 0.0]);
+    assert_eq!(params.type_colour(2), Palette::Classic.colour(2, 3));
+
+    let slice = params.colour_slice();
+    assert_eq!(&slice[..8], [3.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+
+    let interface = reflect(include_str!("../src/shaders/draw.wgsl"), "draw.wgsl").unwrap();
+    let colours = interface.binding(0, 1).unwrap();
+    assert_eq!(colours.size as usize, slice.len() * 4);
+    assert_eq!(slice.len(), 4 * (MAX_TYPE_COLOURS + 1));
+}
+
+#[test]
+fn scenarios_keep_the_colours() {
+    let mut params = Params::with_seed(1);
+    params.num_types = 2;
+    params.randomize_matrix();
+    params.type_colours = Palette::OkabeIto.colours(2);
+
+    let toml = Scenario::from_params(&params).to_toml().unwrap();
+    assert!(toml.contains("type_colours"), "{}", toml);
+    let restored = Scenario::from_toml(&toml)
+        .unwrap()
+        .to_params_with_shader(params.shader_buffer.clone())
+        .unwrap();
+    assert_eq!(restored.type_colours, params.type_colours);
+
+    // none set, none written
+    params.type_colours.clear();
+    let toml = Scenario::from_params(&params).to_toml().unwrap();
+    assert!(!toml.contains("type_colours"));
+
+    let bright = toml.replace(
+        "num_types = 2",
+        "num_types = 2\ntype_colours = [[0.5, 2.0, 0.0]]",
+    );
+    let err = Scenario::from_toml(&bright).unwrap_err();
+    assert!(err.to_string().contains("type_colours[0]"), "{}", err);
+}
@@ -0,0 +1,62 @@
+use eden::{reference::particle_mesh_accels, BoundaryMode, Params, Particle};
+
+fn body(x: f32, y: f32) -> Particle {
+    let mut particle = Particle::new();
+    particle.pos = [x, y];
+    particle.mass = 1.0;
+    particle
+}
+
+fn params(boundary: BoundaryMode) -> Params {
+    let mut params = Params::with_seed(3);
+    params.world_size = 64.0;
+    params.num_grids_side = 64;
+    params.particle_radius = 1.0;
+    params.boundary = boundary;
+    params
+}
+
+#[test]
+fn isolated_mesh_follows_the_pairwise_field() {
+    let params = params(BoundaryMode::Reflect);
+    let bodies = [body(20.5, 30.5), body(36.5, 34.5)];
+
+    let mesh = particle_mesh_accels(&params, &bodies);
+    // -G r / (r² + ε²), the field of a softened 2D log potential
+    let offset = [16.0f32, 4.0];
+    let r2 = offset[0] * offset[0] + offset[1] * offset[1] + 1.0;
+    let expected = [offset[0] / r2, offset[1] / r2];
+
+    for axis in 0..2 {
+        assert!(
+            (mesh[0][axis] - expected[axis]).abs() < 0.01 * expected[0],
+            "{:?} vs {:?}",
+            mesh,
+            expected
+        );
+        assert!(
+            (mesh[1][axis] + expected[axis]).abs() < 0.01 * expected[0],
+            "{:?} vs {:?}",
+            mesh,
+            expected
+        );
+    }
+}
+
+#[test]
+fn periodic_mesh_pulls_bodies_together_and_conserves_momentum() {
+    let params = params(BoundaryMode::Periodic);
+    let bodies = [body(10.3, 20.0), body(17.8, 21.2), body(50.0, 50.0)];
+
+    let accels = particle_mesh_accels(&params, &bodies);
+    assert!(accels[0][0] > 0.0 && accels[1][0] < 0.0, "{:?}", accels);
+
+    let total = accels
+        .iter()
+        .fold([0.0, 0.0], |sum, a| [sum[0] + a[0], sum[1] + a[1]]);
+    assert!(
+        total[0].abs() < 1e-4 && total[1].abs() < 1e-4,
+        "{:?}",
+        total
+    );
+}
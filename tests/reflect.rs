@@ -0,0 +1,111 @@
+use eden::{
+    force,
+    reflect::{reflect, BindingSpace},
+    Algorithm, Params, Simulation,
+};
+
+mod common;
+
+fn params_with(algorithm: Algorithm, name: &str, shader: &str) -> Params {
+    let mut params = Params::with_seed(2);
+    params.num_particles = 32;
+    params.algorithm = algorithm;
+    params.shader_name = name.to_string();
+    params.shader_buffer = shader.to_string();
+    params
+}
+
+#[test]
+fn reflects_the_bindings_and_their_layout() {
+    let interface = reflect(
+        &force::compose(include_str!("../src/shaders/experimental.wgsl")),
+        "experimental.wgsl",
+    )
+    .unwrap();
+    assert_eq!(interface.entry_points, ["main", "brute_force"]);
+
+    let bound: Vec<u32> = interface.bindings.iter().map(|b| b.binding).collect();
+    assert_eq!(bound, [0, 1, 2, 3, 4, 5, 6]);
+
+    let params = interface.binding(0, 0).unwrap();
+    assert_eq!(params.space, BindingSpace::Uniform);
+    assert_eq!(params.size, 32);
+    let particles = interface.binding(0, 1).unwrap();
+    assert_eq!(particles.space, BindingSpace::Storage { read_only: true });
+    assert_eq!(particles.stride, Some(40));
+    let accelerations = interface.binding(0, 2).unwrap();
+    assert_eq!(
+        accelerations.space,
+        BindingSpace::Storage { read_only: false }
+    );
+    assert_eq!(accelerations.stride, Some(8));
+}
+
+#[test]
+fn shaders_eden_cannot_bind_are_explained() {
+    // a particle of 24 bytes and a SimParams of two floats, from before the cell list
+    let params = params_with(
+        Algorithm::UniformGrid,
+        "default.wgsl",
+        include_str!("../src/shaders/default.wgsl"),
+    );
+    let err = Simulation::check_shader(&params).unwrap_err();
+    assert!(
+        err.contains("default.wgsl") && err.contains("24-byte"),
+        "{}",
+        err
+    );
+
+    let writes_particles = "
+struct Particle { pos: vec2<f32>, vel: vec2<f32>, mass: f32, kind: f32, id: u32, bptr: f32, debug: f32 };
+@group(0) @binding(1) var<storage, read_write> particles : array<Particle>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+  particles[id.x].pos = vec2<f32>(0.0);
+}
+";
+    let params = params_with(Algorithm::UniformGrid, "writes.wgsl", writes_particles);
+    let err = Simulation::check_shader(&params).unwrap_err();
+    assert!(err.contains("read_write"), "{}", err);
+
+    let second_group = "
+@group(1) @binding(0) var<storage, read> extra : array<f32>;
+
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return r * extra[0];
+}
+";
+    let params = params_with(Algorithm::UniformGrid, "extra.wgsl", second_group);
+    let err = Simulation::check_shader(&params).unwrap_err();
+    assert!(err.contains("`extra` at group 1"), "{}", err);
+
+    let params = params_with(Algorithm::UniformGrid, "broken.wgsl", "fn force(");
+    let err = Simulation::check_shader(&params).unwrap_err();
+    assert!(err.starts_with("broken.wgsl"), "{}", err);
+}
+
+#[test]
+fn the_layout_only_holds_what_the_shader_uses() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    // brute force over a law that reads neither the matrix nor the cell list
+    let drag = "
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return -0.001 * particle.vel;
+}
+";
+    let params = params_with(Algorithm::BruteForce, "drag.wgsl", drag);
+    let interface = Simulation::check_shader(&params).unwrap();
+    let bound: Vec<u32> = interface.bindings.iter().map(|b| b.binding).collect();
+    assert_eq!(bound, [0, 1, 2]);
+
+    let mut sim = Simulation::try_new(params, &device).unwrap();
+    sim.step(&device, &queue, 2);
+    assert!(sim
+        .read_particles(&device, &queue)
+        .iter()
+        .all(|p| p.pos[0].is_finite()));
+}
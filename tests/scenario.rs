@@ -0,0 +1,106 @@
+use std::fs;
+
+use eden::{
+    scenario::{self, Scenario, ScenarioError},
+    shader_registry::ShaderRegistry,
+    Params, SpawnLayout,
+};
+
+fn sample() -> Scenario {
+    let mut params = Params::new();
+    params.num_types = 3;
+    params.randomize_matrix();
+    params.spawn = SpawnLayout::Disk { radius: 5.0 };
+    Scenario::from_params(&params)
+}
+
+#[test]
+fn round_trips_through_toml() {
+    let scenario = sample();
+    let parsed = Scenario::from_toml(&scenario.to_toml().unwrap()).unwrap();
+    assert_eq!(parsed, scenario);
+}
+
+#[test]
+fn matrix_survives_the_padded_layout() {
+    let scenario = sample();
+    let params = scenario
+        .to_params(None, &ShaderRegistry::new(None))
+        .unwrap();
+    assert_eq!(params.attraction_matrix.len(), 9 * 4);
+    assert_eq!(params.matrix_rows(), scenario.attraction_matrix);
+}
+
+#[test]
+fn reports_the_offending_field() {
+    let mut scenario = sample();
+    scenario.attraction_matrix[1].pop();
+    let err = Scenario::from_toml(&toml::to_string(&scenario).unwrap()).unwrap_err();
+    assert!(
+        matches!(&err, ScenarioError::Invalid { field, .. } if field == "attraction_matrix[1]"),
+        "{}",
+        err
+    );
+
+    let mut scenario = sample();
+    scenario.friction_coeff = 2.0;
+    let err = scenario.validate().unwrap_err();
+    assert!(err.to_string().contains("friction_coeff"), "{}", err);
+
+    let mut scenario = sample();
+    scenario.dt_max = scenario.dt_min / 2.0;
+    let err = scenario.validate().unwrap_err();
+    assert!(err.to_string().contains("dt_max"), "{}", err);
+
+    // what would not load again is not written either
+    let mut params = Params::new();
+    params.particle_radius = 0.0;
+    let err = Scenario::from_params(&params).to_toml().unwrap_err();
+    assert!(err.to_string().contains("particle_radius"), "{}", err);
+}
+
+#[test]
+fn unknown_shader_is_an_error() {
+    let mut scenario = sample();
+    scenario.shader = "does_not_exist.wgsl".to_string();
+    let err = scenario
+        .to_params(None, &ShaderRegistry::new(None))
+        .unwrap_err();
+    assert!(err.to_string().contains("`shader`"), "{}", err);
+}
+
+#[test]
+fn shaders_come_from_the_scenario_folder_then_the_registry() {
+    let root = std::env::temp_dir().join("eden-test-scenario-shaders");
+    let (scenarios, user) = (root.join("scenarios"), root.join("user"));
+    for dir in [&scenarios, &user] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(user.join("mine.wgsl"), "// from --shader-dir\n").unwrap();
+    fs::write(user.join("both.wgsl"), "// from --shader-dir\n").unwrap();
+    fs::write(scenarios.join("both.wgsl"), "// next to the scenario\n").unwrap();
+    let shaders = ShaderRegistry::new(Some(user.clone()));
+
+    let mut scenario = sample();
+    let path = scenarios.join("s.toml");
+    for (shader, expected) in [
+        ("mine.wgsl", "// from --shader-dir\n"),
+        ("both.wgsl", "// next to the scenario\n"),
+        (
+            "lennardjones.wgsl",
+            include_str!("../src/shaders/lennardjones.wgsl"),
+        ),
+    ] {
+        scenario.shader = shader.to_string();
+        scenario.save(&path).unwrap();
+        let params = scenario::load_params(&path, &shaders).unwrap();
+        assert_eq!(params.shader_buffer, expected, "{}", shader);
+    }
+
+    // the old full shaders left out of the built-ins stay out, wherever eden runs from
+    scenario.shader = "default.wgsl".to_string();
+    scenario.save(&path).unwrap();
+    let err = scenario::load_params(&path, &shaders).unwrap_err();
+    assert!(err.to_string().contains("user"), "{}", err);
+    let _ = fs::remove_dir_all(&root);
+}
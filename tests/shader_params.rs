@@ -0,0 +1,152 @@
+use eden::{
+    force, scenario::Scenario, shader_params, BoundaryMode, Integrator, Params, Simulation,
+};
+
+mod common;
+
+/// a spring whose stiffness is a `@param`
+const SPRING: &str = "
+// a spring between every pair closer than one cell
+// @param stiffness 0 10 1
+//   @param   damping -1 1 0.5
+
+fn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {
+  return shader_params.stiffness * r / particle.mass;
+}
+";
+
+#[test]
+fn annotations_are_parsed_in_order() {
+    let declared = shader_params::parse(SPRING).unwrap();
+    let names: Vec<&str> = declared.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["stiffness", "damping"]);
+    assert_eq!(
+        (declared[1].min, declared[1].max, declared[1].default),
+        (-1.0, 1.0, 0.5)
+    );
+
+    // two floats pad to one vec4, nothing declared still gets one
+    let mut params = Params::with_seed(1);
+    params.shader_buffer = SPRING.to_string();
+    params.shader_values.insert("damping".to_string(), 0.25);
+    assert_eq!(params.shader_param_slice(), [1.0, 0.25, 0.0, 0.0]);
+    assert_eq!(shader_params::pack(&[], &params.shader_values), [0.0; 4]);
+}
+
+#[test]
+fn malformed_annotations_are_explained() {
+    for (source, expected) in [
+        ("// @param k 0 1", "line 1: expected"),
+        (
+            "\n// @param 2k 0 1 0",
+            "line 2: `2k` is not a WGSL identifier",
+        ),
+        ("// @param k 0 one 0", "`one` of `k` is not a finite number"),
+        ("// @param k 1 0 0", "min 1 above max 0"),
+        ("// @param k 0 1 2", "defaults to 2, outside 0..=1"),
+        (
+            "// @param k 0 1 0\n// @param k 0 1 0",
+            "`k` is declared twice",
+        ),
+    ] {
+        let err = shader_params::parse(source).unwrap_err();
+        assert!(err.contains(expected), "{:?}: {}", source, err);
+    }
+
+    let mut params = Params::with_seed(1);
+    params.shader_buffer = format!("{}\n// @param stiffness 0 1 0", SPRING);
+    params.shader_name = "spring.wgsl".to_string();
+    let err = Simulation::check_shader(&params).unwrap_err();
+    assert!(err.starts_with("spring.wgsl: line 10:"), "{}", err);
+}
+
+#[test]
+fn the_uniform_is_declared_for_modules_and_full_shaders() {
+    let module = force::compose(SPRING);
+    assert!(module.contains("var<uniform> shader_params : ShaderParams;"));
+
+    let full = format!(
+        "// @param k 0 1 0\n{}",
+        include_str!("../src/shaders/default.wgsl")
+    );
+    let composed = force::compose(&full);
+    assert!(composed.starts_with(&full));
+    assert!(composed.contains("  k: f32,"));
+
+    // shaders without annotations are untouched
+    let barnes_hut = include_str!("../src/shaders/barneshut.wgsl");
+    assert_eq!(force::compose(barnes_hut), barnes_hut);
+}
+
+#[test]
+fn a_param_scales_the_force() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let speed = |stiffness: Option<f32>| {
+        let mut params = Params::with_seed(1);
+        params.num_particles = 2;
+        params.world_size = 20.0;
+        params.num_grids_side = 10;
+        params.friction_coeff = 1.0;
+        params.boundary = BoundaryMode::Open;
+        params.integrator = Integrator::Euler;
+        params.dt = 0.01;
+        params.shader_buffer = SPRING.to_string();
+        if let Some(stiffness) = stiffness {
+            params
+                .shader_values
+                .insert("stiffness".to_string(), stiffness);
+        }
+
+        let mut particles = params.spawn_particles();
+        particles[0].pos = [10.0, 10.0];
+        particles[1].pos = [11.0, 10.0];
+        for particle in &mut particles {
+            particle.vel = [0.0, 0.0];
+            particle.mass = 1.0;
+        }
+        let mut sim = Simulation::try_from_particles(params, &particles, &device).unwrap();
+        sim.step(&device, &queue, 1);
+        sim.read_particles(&device, &queue)[0].vel[0]
+    };
+
+    // a = stiffness * r / m, so after one Euler step v = dt * stiffness
+    let default = speed(None);
+    let stiff = speed(Some(3.0));
+    assert!((default - 0.01).abs() < 1e-6, "{}", default);
+    assert!((stiff - 0.03).abs() < 1e-6, "{}", stiff);
+}
+
+#[test]
+fn scenarios_keep_the_values_of_the_current_shader() {
+    let mut params = Params::with_seed(1);
+    params.shader_buffer = SPRING.to_string();
+    params.shader_name = "spring.wgsl".to_string();
+    params.shader_values.insert("stiffness".to_string(), 4.0);
+    // left over from another shader
+    params
+        .shader_values
+        .insert("search_grids".to_string(), 12.0);
+
+    let scenario = Scenario::from_params(&params);
+    let toml = scenario.to_toml().unwrap();
+    assert!(
+        toml.contains("[shader_params]\nstiffness = 4.0"),
+        "{}",
+        toml
+    );
+    assert!(!toml.contains("search_grids"));
+
+    let parsed = Scenario::from_toml(&toml).unwrap();
+    let restored = parsed.to_params_with_shader(SPRING.to_string()).unwrap();
+    assert_eq!(restored.shader_param_slice(), [4.0, 0.5, 0.0, 0.0]);
+
+    let err = Scenario::from_toml(&toml.replace("4.0", "nan")).unwrap_err();
+    assert!(
+        err.to_string().contains("shader_params.stiffness"),
+        "{}",
+        err
+    );
+}
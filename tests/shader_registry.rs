@@ -0,0 +1,88 @@
+use std::fs;
+
+use eden::{
+    shader_registry::{self, ShaderRegistry},
+    Algorithm, Params, Simulation,
+};
+
+#[test]
+fn every_builtin_shader_builds() {
+    let registry = ShaderRegistry::new(None);
+    assert_eq!(
+        registry.entries().len(),
+        shader_registry::BUILTIN_SHADERS.len()
+    );
+    assert!(registry.get(eden::DEFAULT_COMPUTE_SHADER_NAME).is_some());
+
+    for entry in registry.entries() {
+        assert!(!entry.description.is_empty());
+        for algorithm in Algorithm::ALL {
+            if !entry.runs_with(algorithm) {
+                continue;
+            }
+            let mut params = Params::with_seed(1);
+            params.algorithm = algorithm;
+            params.shader_name = entry.name.clone();
+            params.shader_buffer = entry.source.clone();
+            if let Err(e) = Simulation::check_shader(&params) {
+                panic!("{} with {}: {}", entry.name, algorithm, e);
+            }
+        }
+    }
+}
+
+#[test]
+fn the_user_directory_adds_and_replaces_shaders() {
+    let dir = std::env::temp_dir().join("eden-test-shader-registry");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let spring = "// a unit spring\n// @param k 0 1 1\n\nfn force(particle: Particle, other: Particle, r: vec2<f32>) -> vec2<f32> {\n  return r;\n}\n";
+    fs::write(dir.join("spring.wgsl"), spring).unwrap();
+    fs::write(dir.join("buggy.wgsl"), spring).unwrap();
+    fs::write(
+        dir.join("full.wgsl"),
+        include_str!("../src/shaders/default.wgsl"),
+    )
+    .unwrap();
+    fs::write(dir.join("notes.txt"), "not a shader").unwrap();
+
+    let mut registry = ShaderRegistry::new(Some(dir.clone()));
+    let names: Vec<String> = registry.entries().iter().map(|e| e.name.clone()).collect();
+    assert_eq!(
+        names,
+        [
+            "buggy.wgsl",
+            "experimental.wgsl",
+            "expernew.wgsl",
+            "full.wgsl",
+            "lennardjones.wgsl",
+            "spring.wgsl"
+        ]
+    );
+
+    let spring_entry = registry.get("spring.wgsl").unwrap();
+    assert_eq!(spring_entry.description, "a unit spring");
+    assert_eq!(spring_entry.path, Some(dir.join("spring.wgsl")));
+    assert!(spring_entry.runs_with(Algorithm::BruteForce));
+    assert_eq!(registry.get("buggy.wgsl").unwrap().source, spring);
+
+    // complete shaders walk the cell list themselves
+    let full = registry.get("full.wgsl").unwrap();
+    assert_eq!(full.algorithm, Some(Algorithm::UniformGrid));
+    assert!(!full.runs_with(Algorithm::BruteForce));
+    assert!(!full.runs_with(Algorithm::BarnesHut));
+
+    let saved = registry
+        .insert_file(&dir.join("spring.wgsl"), "// stiffer\n".to_string())
+        .clone();
+    assert_eq!(saved.description, "stiffer");
+    assert_eq!(registry.entries().len(), names.len());
+
+    // a directory that is not there leaves the built-in shaders
+    let missing = ShaderRegistry::new(Some(dir.join("missing")));
+    assert_eq!(
+        missing.entries().len(),
+        shader_registry::BUILTIN_SHADERS.len()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,159 @@
+use eden::{
+    reference::ForceModel,
+    simulation::Clock,
+    snapshot::{Snapshot, SnapshotError},
+    Camera, Params, Simulation,
+};
+
+mod common;
+
+fn test_params() -> Params {
+    let mut params = Params::new();
+    params.num_types = 3;
+    params.randomize_matrix();
+    params.num_particles = 256;
+    params.world_size = 20.0;
+    params.num_grids_side = 10;
+    params.seed = 11;
+    params.shader_buffer = ForceModel::LennardJones.shader_source().to_string();
+    params
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let params = test_params();
+    let snapshot = Snapshot {
+        particles: params.spawn_particles(),
+        camera: Some(Camera::new(1.0, 2.0, 0.5, 16.0 / 9.0)),
+        step_count: 1234,
+        clock: bytemuck::cast([0.01f32, 12.34, 1e-7, 0.0]),
+        params,
+    };
+
+    let mut bytes = Vec::new();
+    snapshot.write_to(&mut bytes).unwrap();
+    let loaded = Snapshot::read_from(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(loaded.step_count, 1234);
+    assert_eq!(loaded.clock, snapshot.clock);
+    assert_eq!(loaded.params.shader_buffer, snapshot.params.shader_buffer);
+    assert_eq!(
+        loaded.params.attraction_matrix,
+        snapshot.params.attraction_matrix
+    );
+    assert_eq!(
+        loaded.camera.unwrap().to_slice(),
+        [1.0, 2.0, 0.5, 16.0 / 9.0]
+    );
+    for (a, b) in loaded.particles.iter().zip(&snapshot.particles) {
+        assert_eq!(a.pos, b.pos);
+        assert_eq!(a.vel, b.vel);
+        assert_eq!(a.kind, b.kind);
+    }
+
+    // version 1 had no clock after the step count
+    let mut v1 = bytes[..12].to_vec();
+    v1[8..12].copy_from_slice(&1u32.to_le_bytes());
+    v1.extend_from_slice(&bytes[12..20]);
+    v1.extend_from_slice(&bytes[36..]);
+    let loaded = Snapshot::read_from(&mut v1.as_slice()).unwrap();
+    assert_eq!(loaded.step_count, 1234);
+    assert_eq!(loaded.clock, Clock::default());
+    assert_eq!(loaded.particles.len(), snapshot.particles.len());
+}
+
+#[test]
+fn refuses_to_save_what_would_not_load() {
+    let mut params = test_params();
+    params.dt = 0.0;
+    let snapshot = Snapshot {
+        particles: params.spawn_particles(),
+        camera: None,
+        step_count: 0,
+        clock: Clock::default(),
+        params,
+    };
+
+    let path = std::env::temp_dir().join("eden-test-invalid.snap");
+    std::fs::write(&path, b"kept").unwrap();
+    let err = snapshot.save(&path).unwrap_err();
+    assert!(err.to_string().contains("`dt`"), "{}", err);
+    assert_eq!(std::fs::read(&path).unwrap(), b"kept");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn rejects_foreign_and_future_files() {
+    let err = Snapshot::read_from(&mut &b"PNG\r\n\x1a\n\0\0\0\0"[..]).unwrap_err();
+    assert!(matches!(err, SnapshotError::NotASnapshot), "{}", err);
+
+    let mut bytes = eden::snapshot::MAGIC.to_vec();
+    bytes.extend_from_slice(&99u32.to_le_bytes());
+    let err = Snapshot::read_from(&mut bytes.as_slice()).unwrap_err();
+    assert!(
+        matches!(err, SnapshotError::UnsupportedVersion(99)),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn resumes_where_it_stopped() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    // adaptive, so the dt the clock carries is not the one in the params
+    let mut params = test_params();
+    params.adaptive_dt = true;
+    let mut sim = Simulation::try_new(params, &device).unwrap();
+    sim.step(&device, &queue, 10);
+    let clock = sim.read_clock(&device, &queue);
+
+    let mut bytes = Vec::new();
+    Snapshot::capture(&sim, None, &device, &queue)
+        .write_to(&mut bytes)
+        .unwrap();
+
+    sim.step(&device, &queue, 10);
+    let expected = sim.read_particles(&device, &queue);
+
+    let mut resumed = Snapshot::read_from(&mut bytes.as_slice())
+        .unwrap()
+        .to_simulation(&device, &queue)
+        .unwrap();
+    assert_eq!(resumed.step_count, 10);
+    assert_eq!(resumed.read_clock(&device, &queue), clock);
+    resumed.step(&device, &queue, 10);
+    let actual = resumed.read_particles(&device, &queue);
+
+    assert_eq!(resumed.step_count, sim.step_count);
+    assert_eq!(
+        resumed.read_clock(&device, &queue),
+        sim.read_clock(&device, &queue)
+    );
+    for (a, b) in actual.iter().zip(&expected) {
+        assert!(
+            (a.pos[0] - b.pos[0]).abs() < 1e-5 && (a.pos[1] - b.pos[1]).abs() < 1e-5,
+            "{:?} != {:?}",
+            a.pos,
+            b.pos
+        );
+    }
+}
+
+#[test]
+fn a_stale_shader_is_reported_instead_of_resumed() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let sim = Simulation::try_new(test_params(), &device).unwrap();
+    let mut snapshot = Snapshot::capture(&sim, None, &device, &queue);
+    // written before particles grew to 40 bytes
+    snapshot.params.shader_name = "default.wgsl".to_string();
+    snapshot.params.shader_buffer = include_str!("../src/shaders/default.wgsl").to_string();
+
+    let err = snapshot.to_simulation(&device, &queue).unwrap_err();
+    assert!(err.contains("24-byte"), "{}", err);
+}
@@ -0,0 +1,82 @@
+use std::fs;
+
+use eden::{
+    trajectory::{TrajectoryFormat, TrajectoryRecorder},
+    BoundaryMode, Params, Simulation,
+};
+
+mod common;
+
+#[test]
+fn records_every_kth_step_in_each_format() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = Params::new();
+    params.num_particles = 64;
+    let dir = std::env::temp_dir();
+
+    for (name, format) in [
+        ("trajectory.csv", TrajectoryFormat::Csv),
+        ("trajectory.xyz", TrajectoryFormat::Xyz),
+        ("trajectory.npy", TrajectoryFormat::Npy),
+    ] {
+        let path = dir.join(format!("eden-test-{}", name));
+        assert_eq!(TrajectoryFormat::from_path(&path), Some(format));
+
+        let mut sim = Simulation::try_new(params.clone(), &device).unwrap();
+        let mut recorder = TrajectoryRecorder::create(&path, format, 5, &sim).unwrap();
+        recorder.record(&sim, &device, &queue);
+        for _ in 0..20 {
+            sim.step(&device, &queue, 1);
+            recorder.record(&sim, &device, &queue);
+            recorder.drain().unwrap();
+        }
+        assert_eq!(recorder.finish(&device).unwrap(), 5);
+
+        let bytes = fs::read(&path).unwrap();
+        match format {
+            TrajectoryFormat::Csv => {
+                let text = String::from_utf8(bytes).unwrap();
+                assert_eq!(text.lines().count(), 1 + 5 * 64);
+                assert!(text.lines().last().unwrap().starts_with("20,63,"));
+            }
+            TrajectoryFormat::Xyz => {
+                let text = String::from_utf8(bytes).unwrap();
+                assert_eq!(text.lines().count(), 5 * (2 + 64));
+                assert_eq!(text.matches("step=").count(), 5);
+                assert_eq!(text.matches("pbc=\"F F F\"").count(), 5);
+            }
+            TrajectoryFormat::Npy => {
+                let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+                let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+                assert!(header.contains("'shape': (5, 64, 5)"), "{}", header);
+                assert_eq!(bytes.len() - 10 - header_len, 5 * 64 * 5 * 4);
+            }
+        }
+        let _ = fs::remove_file(&path);
+    }
+}
+
+#[test]
+fn periodic_worlds_are_marked_periodic_in_xyz() {
+    let Some((device, queue)) = common::gpu() else {
+        return;
+    };
+
+    let mut params = Params::new();
+    params.num_particles = 64;
+    params.boundary = BoundaryMode::Periodic;
+    let path = std::env::temp_dir().join("eden-test-periodic.xyz");
+
+    let sim = Simulation::try_new(params, &device).unwrap();
+    let mut recorder = TrajectoryRecorder::create(&path, TrajectoryFormat::Xyz, 1, &sim).unwrap();
+    recorder.record(&sim, &device, &queue);
+    assert_eq!(recorder.finish(&device).unwrap(), 1);
+
+    let text = fs::read_to_string(&path).unwrap();
+    let header = text.lines().nth(1).unwrap();
+    assert!(header.ends_with("pbc=\"T T F\""), "{}", header);
+    let _ = fs::remove_file(&path);
+}